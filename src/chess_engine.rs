@@ -3,11 +3,15 @@
 //!
 //! The central type is [`board::Board`], a bitboard-based position that knows
 //! how to [generate legal moves](board::Board::generate_moves), apply and undo
-//! them, and detect checks and draws. Supporting modules cover the value types
+//! them, and detect checks and draws ([`game_state::GameStatus`] classifies a
+//! finished game). Supporting modules cover the value types
 //! the board is built from ([`bitboard::Bitboard`], [`position::Position`],
-//! [`piece::Piece`], [`castle_rights::CastleRights`], the `moves::Move` type), the
-//! pre-computed attack tables ([`magic_tables`]), and the [`engine`] that picks
-//! the best move.
+//! [`piece::Piece`], [`castle_rights::CastleRights`], the `moves::Move` type and
+//! the [`move_list::MoveList`] moves are generated into),
+//! FEN parsing and position validation ([`fen_parser`]), game records
+//! ([`pgn`]), opening books ([`polyglot`]), the pre-computed
+//! attack tables ([`magic_tables`]), and the [`engine`] that picks the best
+//! move.
 //!
 //! Move generation correctness is pinned down by the perft tests in
 //! `tests/perft_tests.rs`; see [`crate::perft`].
@@ -17,14 +21,18 @@ pub mod castle_rights;
 mod computed_boards;
 mod constants;
 pub mod engine;
-mod fen_parser;
-mod game_state;
+pub mod fen_parser;
+pub mod game_state;
 pub mod magic_tables;
 mod make_move;
 mod masks;
 mod move_generation;
+pub mod move_list;
 pub mod moves;
+pub mod pgn;
 pub mod piece;
+pub mod polyglot;
 pub mod position;
+mod san;
 pub mod utils;
 mod zobrist;
@@ -8,14 +8,24 @@
 //! and the [`empty_tiles`](Board::empty_tiles) set are derived aggregates,
 //! recomputed after edits by `compute_bitboards`. A Zobrist hash (`u64`) is
 //! maintained incrementally as pieces move, and a history stack of `StateDelta`
-//! records lets moves be undone.
+//! records lets moves be undone. With an NNUE network set, the network's
+//! accumulator is kept in step the same way.
 //!
 //! Colours are modelled by the [`Turn`] type alias (`bool`): [`WHITE`] is
 //! `false` and [`BLACK`] is `true`, so the opponent of `turn` is always `!turn`.
+//!
+//! Castling is described by the rights plus the square of each castling rook,
+//! so Chess960 starting positions (any king and rook files) work like the
+//! standard one: the king still ends on the c- or g-file and the rook next to
+//! it on the d- or f-file.
 
 use super::bitboard::Bitboard;
 use super::castle_rights::CastleRights;
-use super::constants::EMPTY_BIT_B;
+use super::constants::{
+    B_KING_ROOK_START, B_QUEEN_ROOK_START, EMPTY_BIT_B, W_KING_ROOK_START, W_QUEEN_ROOK_START,
+};
+use std::sync::Arc;
+
 use super::game_state::StateDelta;
 use super::moves::Move;
 use super::position::Position;
@@ -24,7 +34,8 @@ use crate::chess_engine::{
         BISHOP_ATTACKS, BISHOP_BLOCKERS, BISHOP_MAGICS, KING_RING_MOVES, KNIGHT_MOVES,
         PAWN_ATTACKS, ROOK_ATTACKS, ROOK_BLOCKERS, ROOK_MAGICS, ZOBRIST_TABLE,
     },
-    fen_parser::START_POS_FEN,
+    engine::{evaluation::EvalWeights, nnue::Accumulator},
+    fen_parser::{FenError, START_POS_FEN},
     masks::BLACK_SQUARES,
     piece::{PIECE_COUNT, Piece},
     zobrist::ZobristHash,
@@ -40,6 +51,15 @@ pub const WHITE: Turn = false;
 /// The black side ([`Turn`] = `true`).
 pub const BLACK: Turn = true;
 
+/// Castling rooks of the standard starting position, in
+/// [`CastleRights::castle_index`] order.
+pub(crate) const STANDARD_CASTLING_ROOKS: [Position; 4] = [
+    W_KING_ROOK_START,
+    W_QUEEN_ROOK_START,
+    B_KING_ROOK_START,
+    B_QUEEN_ROOK_START,
+];
+
 /// A full chess position: piece placement, side to move, and the auxiliary
 /// state (castling rights, en passant, clocks, Zobrist hash, undo history).
 ///
@@ -66,10 +86,31 @@ pub struct Board {
     pub fullmove_count: u16,
     /// Which castles are still available to each side.
     pub castle_rights: CastleRights,
+    /// The starting square of the rook each castle moves, indexed like the
+    /// rights (see [`CastleRights::castle_index`]); the corners unless a
+    /// Chess960 FEN says otherwise.
+    pub(crate) castling_rooks: [Position; 4],
+    /// Whether castles are written king-takes-rook (`e1h1`) as Chess960 and
+    /// `UCI_Chess960` require, rather than as the king's two-square step
+    /// (`e1g1`). Set by [`from_fen`](Self::from_fen) for Chess960 castling
+    /// setups; positions where king and rooks are not on their standard
+    /// squares always use king-takes-rook.
+    pub chess960: bool,
     /// The Zobrist hash of the current position, maintained incrementally.
     pub(crate) zobrist_key: ZobristHash,
+    /// The Zobrist hash of the pawns alone, maintained incrementally; the key
+    /// of the search's pawn hash table.
+    pub(crate) pawn_key: ZobristHash,
     /// Undo stack: the pre-move snapshot for every move played from this board.
     pub(crate) history: Vec<StateDelta>,
+    /// The NNUE hidden layer, when evaluating with a network (see
+    /// [`set_network`](Self::set_network)); `None` uses the classical
+    /// evaluation.
+    pub(crate) nnue: Option<Accumulator>,
+    /// The classical evaluation weights (see
+    /// [`set_weights`](Self::set_weights)); `None` uses
+    /// [`EvalWeights::DEFAULT`].
+    pub(crate) weights: Option<Arc<EvalWeights>>,
 }
 
 impl Board {
@@ -96,8 +137,13 @@ impl Board {
             halfmove_count: 0,
             fullmove_count: 0,
             castle_rights: CastleRights::make_default(),
+            castling_rooks: STANDARD_CASTLING_ROOKS,
+            chess960: false,
             zobrist_key: 0,
+            pawn_key: 0,
             history: Vec::new(),
+            nnue: None,
+            weights: None,
         }
     }
 
@@ -141,12 +187,13 @@ impl Board {
     /// // a1 holds a white rook
     /// assert_eq!(board.get_piece_at(Position::new(0)), Some((Piece::Rook, WHITE)));
     /// ```
-    pub fn new_start_pos() -> Result<Self, String> {
+    pub fn new_start_pos() -> Result<Self, FenError> {
         Self::from_fen(START_POS_FEN)
     }
 
     fn compute_initial_zobrist(&mut self) {
         self.zobrist_key = ZOBRIST_TABLE.hash_position(self);
+        self.pawn_key = ZOBRIST_TABLE.hash_pawns(self);
     }
 
     /// Returns `true` when neither side has enough material to deliver mate:
@@ -202,8 +249,8 @@ impl Board {
             .count()
     }
 
-    /// Removes `piece` of colour `turn` from `pos`, keeping the Zobrist hash in
-    /// sync.
+    /// Removes `piece` of colour `turn` from `pos`, keeping the Zobrist hash
+    /// and any NNUE accumulator in sync.
     pub(crate) fn remove_piece(&mut self, turn: Turn, piece: Piece, pos: Position) {
         // remove piece from bitboard and zobrist key, keeping the per-colour
         // player_boards aggregate in sync incrementally. empty_tiles is derived
@@ -215,10 +262,13 @@ impl Board {
         self.piece_boards[bb_index].clear_square(square);
         self.player_boards[usize::from(turn)].clear_square(square);
         self.xor_piece_from_zobrist(turn, piece, pos);
+        if let Some(accumulator) = &mut self.nnue {
+            accumulator.remove(turn, piece, pos);
+        }
     }
 
-    /// Adds `piece` of colour `turn` at `pos`, keeping the Zobrist hash and the
-    /// per-colour player board in sync.
+    /// Adds `piece` of colour `turn` at `pos`, keeping the Zobrist hash, the
+    /// per-colour player board and any NNUE accumulator in sync.
     pub(crate) fn add_piece(&mut self, turn: Turn, piece: Piece, pos: Position) {
         // see remove_piece for why empty_tiles is not touched here
         let bb_index = Self::get_bb_index(piece, turn);
@@ -226,6 +276,9 @@ impl Board {
         self.piece_boards[bb_index].set_square(square);
         self.player_boards[usize::from(turn)].set_square(square);
         self.xor_piece_from_zobrist(turn, piece, pos);
+        if let Some(accumulator) = &mut self.nnue {
+            accumulator.add(turn, piece, pos);
+        }
     }
 
     /// Recomputes [`empty_tiles`](Board::empty_tiles) from the per-colour
@@ -238,10 +291,13 @@ impl Board {
 
     /// Toggles a single `(colour, piece, square)` entry in the Zobrist hash;
     /// XOR-ing the same entry twice cancels out, which is what makes the hash
-    /// cheap to maintain incrementally.
+    /// cheap to maintain incrementally. Pawns are toggled in the pawn key too.
     pub(crate) fn xor_piece_from_zobrist(&mut self, turn: Turn, piece: Piece, pos: Position) {
-        self.zobrist_key ^=
-            ZOBRIST_TABLE.piece_square[usize::from(turn)][piece as usize][pos.as_usize()];
+        let key = ZOBRIST_TABLE.piece_square[usize::from(turn)][piece as usize][pos.as_usize()];
+        self.zobrist_key ^= key;
+        if piece == Piece::Pawn {
+            self.pawn_key ^= key;
+        }
     }
 
     /// Toggles the en-passant-file contribution to the Zobrist hash (a no-op
@@ -254,6 +310,12 @@ impl Board {
         }
     }
 
+    /// The starting square of the rook that castles on the given side.
+    #[must_use]
+    pub fn castling_rook(&self, turn: Turn, king_side: bool) -> Position {
+        self.castling_rooks[self.castle_rights.castle_index(turn, king_side)]
+    }
+
     /// Revokes one castling right (if currently held) and updates the Zobrist
     /// hash to match.
     pub(crate) fn remove_castle(&mut self, turn: Turn, king_side: bool) {
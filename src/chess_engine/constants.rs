@@ -1,6 +1,6 @@
-//! Shared board constants: the empty bitboard, the fixed starting/destination
-//! squares involved in castling, and the directional offsets used to walk rays
-//! during move generation.
+//! Shared board constants: the empty bitboard, the standard starting squares
+//! and the castling destination squares, and the directional offsets used to
+//! walk rays during move generation.
 //!
 //! Square offsets are expressed in `Bitboard`/`Position` index units, where
 //! moving one rank north is `+8` and one file east is `+1`.
@@ -2,9 +2,15 @@
 //!
 //! [`search`] explores the game tree (iterative-deepening alpha-beta with
 //! quiescence) and [`evaluation`] scores the quiet leaf positions it reaches
-//! (tapered material plus piece-square tables). Together they turn a
-//! [`Board`](super::board::Board) into a best move and its evaluation.
+//! (tapered material plus piece-square tables, or an [`nnue`] network).
+//! Together they turn a [`Board`](super::board::Board) into a best move and its
+//! evaluation; [`time_manager`] decides how long a search under a clock may
+//! take, and [`transposition`] caches results between searches (and
+//! [`pawn_table`] pawn-structure scores within one).
 
 pub mod evaluation;
+pub mod nnue;
+pub mod pawn_table;
 pub mod search;
+pub mod time_manager;
 pub mod transposition;
@@ -22,7 +22,13 @@
 //! before indexing and black squares index directly (see `pst_index`).
 //!
 //! `evaluate` returns centipawns from the side-to-move's perspective:
-//! positive is good for whoever is to move.
+//! positive is good for whoever is to move. A board with an NNUE network set
+//! (see [`nnue`](super::nnue)) is scored by the network instead, and one
+//! given tuned weights with [`Board::set_weights`] is scored with those.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
 
 use crate::chess_engine::{
     bitboard::Bitboard,
@@ -36,6 +42,7 @@ use crate::chess_engine::{
 };
 
 use super::super::board::Board;
+use super::pawn_table::{PawnEntry, PawnTable};
 
 // --- Material values (middlegame / endgame), in centipawns ---
 
@@ -307,136 +314,292 @@ const KING_PST_EG: [i32; 64] = [
     -53, -34, -21, -11, -28, -14, -24, -43,
 ];
 
-/// Per-piece evaluation data: mg/eg material values, mg/eg PSTs, and the
-/// piece's game-phase weight.
-struct PieceEval {
-    mg_value: i32,
-    eg_value: i32,
-    mg_pst: &'static [i32; 64],
-    eg_pst: &'static [i32; 64],
-    phase: i32,
-}
+/// Game-phase weight of each piece, indexed by [`piece_index`].
+const PIECE_PHASE: [i32; 6] = [0, KNIGHT_PHASE, BISHOP_PHASE, ROOK_PHASE, QUEEN_PHASE, 0];
 
-/// Looks up the [`PieceEval`] for a piece type. The king's material value is
-/// zero (king vs king is implicit).
-const fn piece_eval(piece: Piece) -> PieceEval {
+/// Index of a piece in the per-piece weight arrays: pawn, knight, bishop,
+/// rook, queen, king.
+const fn piece_index(piece: Piece) -> usize {
     match piece {
-        Piece::Pawn => PieceEval {
-            mg_value: PAWN_MG,
-            eg_value: PAWN_EG,
-            mg_pst: &PAWN_PST_MG,
-            eg_pst: &PAWN_PST_EG,
-            phase: 0,
-        },
-        Piece::Knight => PieceEval {
-            mg_value: KNIGHT_MG,
-            eg_value: KNIGHT_EG,
-            mg_pst: &KNIGHT_PST_MG,
-            eg_pst: &KNIGHT_PST_EG,
-            phase: KNIGHT_PHASE,
-        },
-        Piece::Bishop => PieceEval {
-            mg_value: BISHOP_MG,
-            eg_value: BISHOP_EG,
-            mg_pst: &BISHOP_PST_MG,
-            eg_pst: &BISHOP_PST_EG,
-            phase: BISHOP_PHASE,
-        },
-        Piece::Rook => PieceEval {
-            mg_value: ROOK_MG,
-            eg_value: ROOK_EG,
-            mg_pst: &ROOK_PST_MG,
-            eg_pst: &ROOK_PST_EG,
-            phase: ROOK_PHASE,
-        },
-        Piece::Queen => PieceEval {
-            mg_value: QUEEN_MG,
-            eg_value: QUEEN_EG,
-            mg_pst: &QUEEN_PST_MG,
-            eg_pst: &QUEEN_PST_EG,
-            phase: QUEEN_PHASE,
-        },
+        Piece::Pawn => 0,
+        Piece::Knight => 1,
+        Piece::Bishop => 2,
+        Piece::Rook => 3,
+        Piece::Queen => 4,
         // King (Piece::None never occurs in a piece board).
-        _ => PieceEval {
-            mg_value: 0,
-            eg_value: 0,
-            mg_pst: &KING_PST_MG,
-            eg_pst: &KING_PST_EG,
-            phase: 0,
-        },
+        _ => 5,
+    }
+}
+
+/// Every tunable weight of the classical evaluation.
+///
+/// [`DEFAULT`](Self::DEFAULT) gathers the constants above and is what the
+/// engine plays with unless a board is given others with
+/// [`Board::set_weights`]; the tuner ([`crate::tuning`]) searches for better
+/// values and writes them out with [`save`](Self::save) in a plain-text format
+/// that [`load`](Self::load) reads back: one weight per line, its name followed
+/// by its values. Pairs are `[mg, eg]`; per-piece arrays run pawn, knight,
+/// bishop, rook, queen, king.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalWeights {
+    /// Middlegame material value per piece (the king's is 0).
+    pub material_mg: [i32; 6],
+    /// Endgame material value per piece.
+    pub material_eg: [i32; 6],
+    /// Middlegame PST per piece, rank-8-first.
+    pub pst_mg: [[i32; 64]; 6],
+    /// Endgame PST per piece, rank-8-first.
+    pub pst_eg: [[i32; 64]; 6],
+    /// See `TEMPO_MG`.
+    pub tempo_mg: i32,
+    /// See `PASSED_PAWN_MG`.
+    pub passed_pawn_mg: [i32; 8],
+    /// See `PASSED_PAWN_EG`.
+    pub passed_pawn_eg: [i32; 8],
+    /// See `ISOLATED_PAWN_MG` and `ISOLATED_PAWN_EG`.
+    pub isolated_pawn: [i32; 2],
+    /// See `DOUBLED_PAWN_MG` and `DOUBLED_PAWN_EG`.
+    pub doubled_pawn: [i32; 2],
+    /// See `BISHOP_PAIR_MG` and `BISHOP_PAIR_EG`.
+    pub bishop_pair: [i32; 2],
+    /// See `ROOK_OPEN_FILE_MG`.
+    pub rook_open_file_mg: i32,
+    /// See `ROOK_SEMI_OPEN_FILE_MG`.
+    pub rook_semi_open_file_mg: i32,
+    /// See `KING_SHIELD_MISSING_MG`.
+    pub king_shield_missing_mg: i32,
+    /// See `KNIGHT_MOBILITY`.
+    pub knight_mobility: [i32; 2],
+    /// See `BISHOP_MOBILITY`.
+    pub bishop_mobility: [i32; 2],
+    /// See `ROOK_MOBILITY`.
+    pub rook_mobility: [i32; 2],
+    /// See `QUEEN_MOBILITY`.
+    pub queen_mobility: [i32; 2],
+    /// See `KING_DANGER_MG`.
+    pub king_danger_mg: [i32; 20],
+    /// See `KING_FILE_SEMI_OPEN_MG`.
+    pub king_file_semi_open_mg: i32,
+    /// See `KING_FILE_OPEN_MG`.
+    pub king_file_open_mg: i32,
+    /// See `PASSED_KING_DIST_EG`.
+    pub passed_king_dist_eg: i32,
+    /// See `ROOK_BEHIND_PASSER`.
+    pub rook_behind_passer: [i32; 2],
+}
+
+impl EvalWeights {
+    /// The hand-set weights the engine evaluates with.
+    pub const DEFAULT: Self = Self {
+        material_mg: [PAWN_MG, KNIGHT_MG, BISHOP_MG, ROOK_MG, QUEEN_MG, 0],
+        material_eg: [PAWN_EG, KNIGHT_EG, BISHOP_EG, ROOK_EG, QUEEN_EG, 0],
+        pst_mg: [
+            PAWN_PST_MG,
+            KNIGHT_PST_MG,
+            BISHOP_PST_MG,
+            ROOK_PST_MG,
+            QUEEN_PST_MG,
+            KING_PST_MG,
+        ],
+        pst_eg: [
+            PAWN_PST_EG,
+            KNIGHT_PST_EG,
+            BISHOP_PST_EG,
+            ROOK_PST_EG,
+            QUEEN_PST_EG,
+            KING_PST_EG,
+        ],
+        tempo_mg: TEMPO_MG,
+        passed_pawn_mg: PASSED_PAWN_MG,
+        passed_pawn_eg: PASSED_PAWN_EG,
+        isolated_pawn: [ISOLATED_PAWN_MG, ISOLATED_PAWN_EG],
+        doubled_pawn: [DOUBLED_PAWN_MG, DOUBLED_PAWN_EG],
+        bishop_pair: [BISHOP_PAIR_MG, BISHOP_PAIR_EG],
+        rook_open_file_mg: ROOK_OPEN_FILE_MG,
+        rook_semi_open_file_mg: ROOK_SEMI_OPEN_FILE_MG,
+        king_shield_missing_mg: KING_SHIELD_MISSING_MG,
+        knight_mobility: [KNIGHT_MOBILITY.0, KNIGHT_MOBILITY.1],
+        bishop_mobility: [BISHOP_MOBILITY.0, BISHOP_MOBILITY.1],
+        rook_mobility: [ROOK_MOBILITY.0, ROOK_MOBILITY.1],
+        queen_mobility: [QUEEN_MOBILITY.0, QUEEN_MOBILITY.1],
+        king_danger_mg: KING_DANGER_MG,
+        king_file_semi_open_mg: KING_FILE_SEMI_OPEN_MG,
+        king_file_open_mg: KING_FILE_OPEN_MG,
+        passed_king_dist_eg: PASSED_KING_DIST_EG,
+        rook_behind_passer: [ROOK_BEHIND_PASSER.0, ROOK_BEHIND_PASSER.1],
+    };
+
+    /// Every weight by name, in file order. The king's material is left out:
+    /// both sides always have one, so it cancels and cannot be tuned.
+    #[allow(clippy::similar_names)]
+    fn fields(&mut self) -> Vec<(&'static str, &mut [i32])> {
+        use std::slice::from_mut;
+        let [pawn_mg, knight_mg, bishop_mg, rook_mg, queen_mg, king_mg] = &mut self.pst_mg;
+        let [pawn_eg, knight_eg, bishop_eg, rook_eg, queen_eg, king_eg] = &mut self.pst_eg;
+        vec![
+            ("material_mg", &mut self.material_mg[..5]),
+            ("material_eg", &mut self.material_eg[..5]),
+            ("pawn_pst_mg", pawn_mg),
+            ("pawn_pst_eg", pawn_eg),
+            ("knight_pst_mg", knight_mg),
+            ("knight_pst_eg", knight_eg),
+            ("bishop_pst_mg", bishop_mg),
+            ("bishop_pst_eg", bishop_eg),
+            ("rook_pst_mg", rook_mg),
+            ("rook_pst_eg", rook_eg),
+            ("queen_pst_mg", queen_mg),
+            ("queen_pst_eg", queen_eg),
+            ("king_pst_mg", king_mg),
+            ("king_pst_eg", king_eg),
+            ("tempo_mg", from_mut(&mut self.tempo_mg)),
+            ("passed_pawn_mg", &mut self.passed_pawn_mg),
+            ("passed_pawn_eg", &mut self.passed_pawn_eg),
+            ("isolated_pawn", &mut self.isolated_pawn),
+            ("doubled_pawn", &mut self.doubled_pawn),
+            ("bishop_pair", &mut self.bishop_pair),
+            ("rook_open_file_mg", from_mut(&mut self.rook_open_file_mg)),
+            (
+                "rook_semi_open_file_mg",
+                from_mut(&mut self.rook_semi_open_file_mg),
+            ),
+            (
+                "king_shield_missing_mg",
+                from_mut(&mut self.king_shield_missing_mg),
+            ),
+            ("knight_mobility", &mut self.knight_mobility),
+            ("bishop_mobility", &mut self.bishop_mobility),
+            ("rook_mobility", &mut self.rook_mobility),
+            ("queen_mobility", &mut self.queen_mobility),
+            ("king_danger_mg", &mut self.king_danger_mg),
+            (
+                "king_file_semi_open_mg",
+                from_mut(&mut self.king_file_semi_open_mg),
+            ),
+            ("king_file_open_mg", from_mut(&mut self.king_file_open_mg)),
+            (
+                "passed_king_dist_eg",
+                from_mut(&mut self.passed_king_dist_eg),
+            ),
+            ("rook_behind_passer", &mut self.rook_behind_passer),
+        ]
+    }
+
+    /// All weights as one flat vector, in file order (for the tuner).
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<i32> {
+        self.clone()
+            .fields()
+            .into_iter()
+            .flat_map(|(_, values)| values.to_vec())
+            .collect()
+    }
+
+    /// Sets all weights from a vector laid out like [`to_vec`](Self::to_vec)'s.
+    ///
+    /// # Panics
+    /// Panics if `values` is shorter than [`to_vec`](Self::to_vec)'s result.
+    pub fn set_from_slice(&mut self, values: &[i32]) {
+        let mut values = values.iter();
+        for (_, field) in self.fields() {
+            for value in field {
+                *value = *values.next().expect("one value per weight");
+            }
+        }
+    }
+
+    /// Writes the weights in the text format [`load`](Self::load) reads.
+    #[must_use]
+    pub fn save(&self) -> String {
+        let mut text = String::from("# sabertooth evaluation weights\n");
+        for (name, values) in self.clone().fields() {
+            text.push_str(name);
+            for value in values.iter() {
+                text.push(' ');
+                text.push_str(&value.to_string());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Reads weights written by [`save`](Self::save). Blank lines and `#`
+    /// comments are skipped; weights the text does not mention keep their
+    /// [`DEFAULT`](Self::DEFAULT) values.
+    ///
+    /// # Errors
+    /// Returns an error for an unknown name, a non-integer value, or the wrong
+    /// number of values.
+    pub fn load(text: &str) -> Result<Self, String> {
+        let mut weights = Self::DEFAULT;
+        let mut fields = weights.fields();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            let name = tokens.next().unwrap_or_default();
+            let Some((_, field)) = fields.iter_mut().find(|(field, _)| *field == name) else {
+                return Err(format!("Unknown evaluation weight: {name}"));
+            };
+            let values = tokens
+                .map(str::parse)
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|e| format!("Invalid value for {name}: {e}"))?;
+            if values.len() != field.len() {
+                return Err(format!(
+                    "{name} takes {} values, got {}",
+                    field.len(),
+                    values.len()
+                ));
+            }
+            field.copy_from_slice(&values);
+        }
+        drop(fields);
+        Ok(weights)
+    }
+
+    /// Reads a weights file written by [`save`](Self::save).
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or does not parse.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read weights {}: {e}", path.display()))?;
+        Self::load(&text).map_err(|e| format!("{}: {e}", path.display()))
     }
 }
 
-/// Computes the pawn-structure and piece-feature terms for one side, returning
-/// `(mg, eg)` bonuses from that side's perspective: passed, isolated, and
-/// doubled pawns, the bishop pair, rooks on open/semi-open files, and the
-/// king's pawn shield.
+impl Default for EvalWeights {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Computes the terms that depend on the pawns alone for one side: its
+/// passed pawns, and the `(mg, eg)` isolated- and doubled-pawn penalties from
+/// that side's perspective. These are what the [`PawnTable`] caches.
 #[allow(
     clippy::cast_possible_wrap,
     clippy::cast_possible_truncation,
     clippy::similar_names
 )]
-fn side_features(board: &Board, color: Turn) -> (i32, i32) {
+fn pawn_structure(board: &Board, color: Turn, w: &EvalWeights) -> (Bitboard, i32, i32) {
     let own_pawns = board.get_piece_bitboard(Piece::Pawn, color);
     let enemy_pawns = board.get_piece_bitboard(Piece::Pawn, !color);
-    let all_pawns = own_pawns | enemy_pawns;
+    let mut passed = Bitboard::new();
     let mut mg = 0;
     let mut eg = 0;
 
     // Passed and isolated pawns.
-    let own_king_sq = board
-        .get_piece_bitboard(Piece::King, color)
-        .trailing_zeros();
-    let enemy_king_sq = board
-        .get_piece_bitboard(Piece::King, !color)
-        .trailing_zeros();
     let mut pawns = own_pawns;
     while pawns.is_not_empty() {
         let sq = pawns.trailing_zeros();
-        let file = sq % 8;
-        let relative_rank = if color == WHITE { sq / 8 } else { 7 - sq / 8 };
         if (PASSED_PAWN_MASKS[usize::from(color)][sq] & enemy_pawns).is_empty() {
-            let mut pass_mg = PASSED_PAWN_MG[relative_rank];
-            let mut pass_eg = PASSED_PAWN_EG[relative_rank];
-
-            // The stop square is one rank ahead of the passer. A pawn already
-            // on the promotion rank (only reachable from an unvalidated FEN)
-            // has no stop square, so skip the heuristics that would otherwise
-            // index off the board.
-            if relative_rank < 7 {
-                let stop_sq = if color == WHITE { sq + 8 } else { sq - 8 };
-
-                // a blockaded passer (stop square occupied) is worth much less
-                if !board.empty_tiles.is_square_set(stop_sq) {
-                    pass_mg /= 2;
-                    pass_eg /= 2;
-                }
-
-                // the endgame king race: shepherding king close to the stop
-                // square, defending king far — scaled by how advanced the pawn is
-                pass_eg += PASSED_KING_DIST_EG
-                    * (chebyshev(enemy_king_sq, stop_sq) - chebyshev(own_king_sq, stop_sq))
-                    * relative_rank as i32;
-            }
-
-            // a rook behind the passer pushes it and defends it as it runs
-            let behind = FILE_MASKS[file] & board.get_piece_bitboard(Piece::Rook, color);
-            let rook_is_behind = if color == WHITE {
-                (behind & Bitboard::from_u64((1_u64 << sq) - 1)).is_not_empty()
-            } else {
-                (behind & !Bitboard::from_u64((1_u64 << (sq + 1)) - 1)).is_not_empty()
-            };
-            if rook_is_behind {
-                pass_mg += ROOK_BEHIND_PASSER.0;
-                pass_eg += ROOK_BEHIND_PASSER.1;
-            }
-
-            mg += pass_mg;
-            eg += pass_eg;
+            passed.set_square(sq);
         }
-        if (ADJACENT_FILE_MASKS[file] & own_pawns).is_empty() {
-            mg += ISOLATED_PAWN_MG;
-            eg += ISOLATED_PAWN_EG;
+        if (ADJACENT_FILE_MASKS[sq % 8] & own_pawns).is_empty() {
+            mg += w.isolated_pawn[0];
+            eg += w.isolated_pawn[1];
         }
         pawns.reset_lsb();
     }
@@ -445,15 +608,103 @@ fn side_features(board: &Board, color: Turn) -> (i32, i32) {
     for file_mask in FILE_MASKS {
         let pawns_on_file = (own_pawns & file_mask).count_bits() as i32;
         if pawns_on_file > 1 {
-            mg += DOUBLED_PAWN_MG * (pawns_on_file - 1);
-            eg += DOUBLED_PAWN_EG * (pawns_on_file - 1);
+            mg += w.doubled_pawn[0] * (pawns_on_file - 1);
+            eg += w.doubled_pawn[1] * (pawns_on_file - 1);
+        }
+    }
+
+    (passed, mg, eg)
+}
+
+/// Computes both sides' [`pawn_structure`] under `w`, White minus Black,
+/// keyed by the board's pawn key.
+#[allow(clippy::similar_names)]
+fn pawn_entry(board: &Board, w: &EvalWeights) -> PawnEntry {
+    let (white_passed, white_mg, white_eg) = pawn_structure(board, WHITE, w);
+    let (black_passed, black_mg, black_eg) = pawn_structure(board, BLACK, w);
+    let mut passed = [Bitboard::new(); 2];
+    passed[usize::from(WHITE)] = white_passed;
+    passed[usize::from(BLACK)] = black_passed;
+    PawnEntry {
+        key: board.pawn_key,
+        passed,
+        mg: white_mg - black_mg,
+        eg: white_eg - black_eg,
+    }
+}
+
+/// Computes the piece-feature terms for one side, returning `(mg, eg)`
+/// bonuses from that side's perspective: its `passed` pawns scaled by rank,
+/// blockade, king race and a rook behind them, the bishop pair, rooks on
+/// open/semi-open files, and the king's pawn shield.
+#[allow(
+    clippy::cast_possible_wrap,
+    clippy::cast_possible_truncation,
+    clippy::similar_names
+)]
+fn side_features(board: &Board, color: Turn, w: &EvalWeights, passed: Bitboard) -> (i32, i32) {
+    let own_pawns = board.get_piece_bitboard(Piece::Pawn, color);
+    let enemy_pawns = board.get_piece_bitboard(Piece::Pawn, !color);
+    let all_pawns = own_pawns | enemy_pawns;
+    let mut mg = 0;
+    let mut eg = 0;
+
+    // Passed pawns.
+    let own_king_sq = board
+        .get_piece_bitboard(Piece::King, color)
+        .trailing_zeros();
+    let enemy_king_sq = board
+        .get_piece_bitboard(Piece::King, !color)
+        .trailing_zeros();
+    let mut passers = passed;
+    while passers.is_not_empty() {
+        let sq = passers.trailing_zeros();
+        let file = sq % 8;
+        let relative_rank = if color == WHITE { sq / 8 } else { 7 - sq / 8 };
+        let mut pass_mg = w.passed_pawn_mg[relative_rank];
+        let mut pass_eg = w.passed_pawn_eg[relative_rank];
+
+        // The stop square is one rank ahead of the passer. A pawn already on
+        // the promotion rank (only reachable from an unvalidated FEN) has no
+        // stop square, so skip the heuristics that would otherwise index off
+        // the board.
+        if relative_rank < 7 {
+            let stop_sq = if color == WHITE { sq + 8 } else { sq - 8 };
+
+            // a blockaded passer (stop square occupied) is worth much less
+            if !board.empty_tiles.is_square_set(stop_sq) {
+                pass_mg /= 2;
+                pass_eg /= 2;
+            }
+
+            // the endgame king race: shepherding king close to the stop
+            // square, defending king far — scaled by how advanced the pawn is
+            pass_eg += w.passed_king_dist_eg
+                * (chebyshev(enemy_king_sq, stop_sq) - chebyshev(own_king_sq, stop_sq))
+                * relative_rank as i32;
+        }
+
+        // a rook behind the passer pushes it and defends it as it runs
+        let behind = FILE_MASKS[file] & board.get_piece_bitboard(Piece::Rook, color);
+        let rook_is_behind = if color == WHITE {
+            (behind & Bitboard::from_u64((1_u64 << sq) - 1)).is_not_empty()
+        } else {
+            (behind & !Bitboard::from_u64((1_u64 << (sq + 1)) - 1)).is_not_empty()
+        };
+        if rook_is_behind {
+            pass_mg += w.rook_behind_passer[0];
+            pass_eg += w.rook_behind_passer[1];
         }
+
+        mg += pass_mg;
+        eg += pass_eg;
+        passers.reset_lsb();
     }
 
     // Bishop pair.
     if board.get_piece_bitboard(Piece::Bishop, color).count_bits() >= 2 {
-        mg += BISHOP_PAIR_MG;
-        eg += BISHOP_PAIR_EG;
+        mg += w.bishop_pair[0];
+        eg += w.bishop_pair[1];
     }
 
     // Rooks on open and semi-open files.
@@ -461,9 +712,9 @@ fn side_features(board: &Board, color: Turn) -> (i32, i32) {
     while rooks.is_not_empty() {
         let file = rooks.trailing_zeros() % 8;
         if (FILE_MASKS[file] & all_pawns).is_empty() {
-            mg += ROOK_OPEN_FILE_MG;
+            mg += w.rook_open_file_mg;
         } else if (FILE_MASKS[file] & own_pawns).is_empty() {
-            mg += ROOK_SEMI_OPEN_FILE_MG;
+            mg += w.rook_semi_open_file_mg;
         }
         rooks.reset_lsb();
     }
@@ -483,7 +734,7 @@ fn side_features(board: &Board, color: Turn) -> (i32, i32) {
     if let Some(front_rank) = front_rank {
         let shield = PASSED_PAWN_MASKS[usize::from(color)][king_sq] & RANK_MASKS[front_rank];
         let missing = shield.count_bits() - (shield & own_pawns).count_bits();
-        mg += KING_SHIELD_MISSING_MG * missing as i32;
+        mg += w.king_shield_missing_mg * missing as i32;
     }
 
     (mg, eg)
@@ -517,37 +768,37 @@ fn pawn_attack_map(board: &Board, color: Turn) -> Bitboard {
 /// piece, not covered by an enemy pawn) against a per-piece baseline, so an
 /// averagely active piece contributes nothing and trapped pieces are punished.
 #[allow(clippy::cast_possible_wrap)]
-fn side_mobility(board: &Board, color: Turn) -> (i32, i32) {
+fn side_mobility(board: &Board, color: Turn, w: &EvalWeights) -> (i32, i32) {
     let occupancy = !board.empty_tiles;
     // safe: not a friendly piece's square, not attacked by an enemy pawn
     let safe = !(board.player_boards[usize::from(color)] | pawn_attack_map(board, !color));
     let mut mg = 0;
     let mut eg = 0;
 
-    let mut score_piece = |count: i32, base: i32, weight: (i32, i32)| {
-        mg += weight.0 * (count - base);
-        eg += weight.1 * (count - base);
+    let mut score_piece = |count: i32, base: i32, weight: [i32; 2]| {
+        mg += weight[0] * (count - base);
+        eg += weight[1] * (count - base);
     };
 
     let mut knights = board.get_piece_bitboard(Piece::Knight, color);
     while knights.is_not_empty() {
         let sq = knights.trailing_zeros();
         let moves = (KNIGHT_MOVES[sq] & safe).count_bits() as i32;
-        score_piece(moves, KNIGHT_MOBILITY_BASE, KNIGHT_MOBILITY);
+        score_piece(moves, KNIGHT_MOBILITY_BASE, w.knight_mobility);
         knights.reset_lsb();
     }
     let mut bishops = board.get_piece_bitboard(Piece::Bishop, color);
     while bishops.is_not_empty() {
         let sq = bishops.trailing_zeros();
         let moves = (bishop_attacks(sq, occupancy) & safe).count_bits() as i32;
-        score_piece(moves, BISHOP_MOBILITY_BASE, BISHOP_MOBILITY);
+        score_piece(moves, BISHOP_MOBILITY_BASE, w.bishop_mobility);
         bishops.reset_lsb();
     }
     let mut rooks = board.get_piece_bitboard(Piece::Rook, color);
     while rooks.is_not_empty() {
         let sq = rooks.trailing_zeros();
         let moves = (rook_attacks(sq, occupancy) & safe).count_bits() as i32;
-        score_piece(moves, ROOK_MOBILITY_BASE, ROOK_MOBILITY);
+        score_piece(moves, ROOK_MOBILITY_BASE, w.rook_mobility);
         rooks.reset_lsb();
     }
     let mut queens = board.get_piece_bitboard(Piece::Queen, color);
@@ -555,7 +806,7 @@ fn side_mobility(board: &Board, color: Turn) -> (i32, i32) {
         let sq = queens.trailing_zeros();
         let moves = ((bishop_attacks(sq, occupancy) | rook_attacks(sq, occupancy)) & safe)
             .count_bits() as i32;
-        score_piece(moves, QUEEN_MOBILITY_BASE, QUEEN_MOBILITY);
+        score_piece(moves, QUEEN_MOBILITY_BASE, w.queen_mobility);
         queens.reset_lsb();
     }
 
@@ -567,7 +818,7 @@ fn side_mobility(board: &Board, color: Turn) -> (i32, i32) {
 /// attacks touch the king zone (the king and its ring) and maps the total
 /// through [`KING_DANGER_MG`], plus a penalty for semi-open/open files next to
 /// the king.
-fn king_danger(board: &Board, color: Turn) -> i32 {
+fn king_danger(board: &Board, color: Turn, w: &EvalWeights) -> i32 {
     let king_sq = board
         .get_piece_bitboard(Piece::King, color)
         .trailing_zeros();
@@ -620,7 +871,7 @@ fn king_danger(board: &Board, color: Turn) -> i32 {
         true,
         true,
     );
-    let mut danger = -KING_DANGER_MG[units.min(KING_DANGER_MG.len() - 1)];
+    let mut danger = -w.king_danger_mg[units.min(w.king_danger_mg.len() - 1)];
 
     // files on and next to the king with no pawn cover invite heavy pieces
     let own_pawns = board.get_piece_bitboard(Piece::Pawn, color);
@@ -628,9 +879,9 @@ fn king_danger(board: &Board, color: Turn) -> i32 {
     let king_file = king_sq % 8;
     for file_mask in &FILE_MASKS[king_file.saturating_sub(1)..=(king_file + 1).min(7)] {
         if (*file_mask & own_pawns).is_empty() {
-            danger += KING_FILE_SEMI_OPEN_MG;
+            danger += w.king_file_semi_open_mg;
             if (*file_mask & all_pawns).is_empty() {
-                danger += KING_FILE_OPEN_MG;
+                danger += w.king_file_open_mg;
             }
         }
     }
@@ -651,22 +902,69 @@ const fn pst_index(square: usize, is_white: bool) -> usize {
 ///
 /// Accumulates separate middlegame and endgame scores (material + PST for
 /// every piece, from White's perspective) plus a tempo bonus for the side to
-/// move, then blends the two by game phase.
+/// move, then blends the two by game phase, all under the board's weights
+/// (see [`Board::set_weights`]). A board with an NNUE accumulator is scored by
+/// the network instead.
+#[must_use]
+pub fn evaluate(board: &Board) -> i32 {
+    if let Some(accumulator) = &board.nnue {
+        return accumulator.evaluate(board.turn);
+    }
+    evaluate_with(board, board.weights())
+}
+
+/// [`evaluate`], taking the pawn-structure terms from `pawns` when the board's
+/// pawn structure has been seen before and storing them there when not. Gives
+/// exactly the same score as `evaluate`.
+pub(crate) fn evaluate_cached(board: &Board, pawns: &mut PawnTable) -> i32 {
+    if let Some(accumulator) = &board.nnue {
+        return accumulator.evaluate(board.turn);
+    }
+    let w = board.weights();
+    let entry = pawns.probe(board.pawn_key).unwrap_or_else(|| {
+        let entry = pawn_entry(board, w);
+        pawns.store(entry);
+        entry
+    });
+    classical(board, w, &entry)
+}
+
+impl Board {
+    /// Evaluates the board classically with `weights`, or with
+    /// [`EvalWeights::DEFAULT`] again with `None`.
+    pub fn set_weights(&mut self, weights: Option<Arc<EvalWeights>>) {
+        self.weights = weights;
+    }
+
+    /// The weights the classical evaluation of this board uses.
+    fn weights(&self) -> &EvalWeights {
+        self.weights.as_deref().unwrap_or(&EvalWeights::DEFAULT)
+    }
+}
+
+/// The classical evaluation of `board` under `weights`, from the side to
+/// move's perspective; any NNUE network on the board is ignored.
+#[must_use]
+pub fn evaluate_with(board: &Board, w: &EvalWeights) -> i32 {
+    classical(board, w, &pawn_entry(board, w))
+}
+
+/// The classical evaluation, given the board's pawn-structure terms.
 #[allow(clippy::similar_names)]
-pub(crate) fn evaluate(board: &Board) -> i32 {
+fn classical(board: &Board, w: &EvalWeights, pawns: &PawnEntry) -> i32 {
     let mut mg = 0;
     let mut eg = 0;
     let mut game_phase = 0;
 
     for (index, mut piece_board) in board.piece_boards.into_iter().enumerate() {
         let (piece, color) = Board::get_piece_information_index(index);
-        let eval = piece_eval(piece);
+        let piece = piece_index(piece);
         // extract all pieces from bitboard
         while piece_board.is_not_empty() {
             let pos = piece_board.trailing_zeros();
             let pst_idx = pst_index(pos, color == WHITE);
-            let mg_score = eval.mg_value + eval.mg_pst[pst_idx];
-            let eg_score = eval.eg_value + eval.eg_pst[pst_idx];
+            let mg_score = w.material_mg[piece] + w.pst_mg[piece][pst_idx];
+            let eg_score = w.material_eg[piece] + w.pst_eg[piece][pst_idx];
             if color == WHITE {
                 mg += mg_score;
                 eg += eg_score;
@@ -674,32 +972,34 @@ pub(crate) fn evaluate(board: &Board) -> i32 {
                 mg -= mg_score;
                 eg -= eg_score;
             }
-            game_phase += eval.phase;
+            game_phase += PIECE_PHASE[piece];
             piece_board.reset_lsb();
         }
     }
 
     // Pawn structure and piece features, white minus black.
-    let (white_mg, white_eg) = side_features(board, WHITE);
-    let (black_mg, black_eg) = side_features(board, BLACK);
+    mg += pawns.mg;
+    eg += pawns.eg;
+    let (white_mg, white_eg) = side_features(board, WHITE, w, pawns.passed[usize::from(WHITE)]);
+    let (black_mg, black_eg) = side_features(board, BLACK, w, pawns.passed[usize::from(BLACK)]);
     mg += white_mg - black_mg;
     eg += white_eg - black_eg;
 
     // Mobility, white minus black.
-    let (white_mob_mg, white_mob_eg) = side_mobility(board, WHITE);
-    let (black_mob_mg, black_mob_eg) = side_mobility(board, BLACK);
+    let (white_mob_mg, white_mob_eg) = side_mobility(board, WHITE, w);
+    let (black_mob_mg, black_mob_eg) = side_mobility(board, BLACK, w);
     mg += white_mob_mg - black_mob_mg;
     eg += white_mob_eg - black_mob_eg;
 
     // King danger (each side's own penalty, ≤ 0), middlegame only: with the
     // heavy pieces off the board an "attacked" king zone means little.
-    mg += king_danger(board, WHITE) - king_danger(board, BLACK);
+    mg += king_danger(board, WHITE, w) - king_danger(board, BLACK, w);
 
     // Tempo: the side to move has the initiative in the middlegame.
     if board.turn == WHITE {
-        mg += TEMPO_MG;
+        mg += w.tempo_mg;
     } else {
-        mg -= TEMPO_MG;
+        mg -= w.tempo_mg;
     }
 
     // Blend by game phase (clamped: early promotions can exceed TOTAL_PHASE).
@@ -717,12 +1017,19 @@ pub(crate) fn evaluate(board: &Board) -> i32 {
 #[cfg(test)]
 mod tests {
     use super::{
-        BISHOP_PAIR_EG, BISHOP_PAIR_MG, DOUBLED_PAWN_EG, DOUBLED_PAWN_MG, ISOLATED_PAWN_EG,
-        ISOLATED_PAWN_MG, KING_SHIELD_MISSING_MG, KNIGHT_MOBILITY, KNIGHT_MOBILITY_BASE,
-        PASSED_PAWN_EG, PASSED_PAWN_MG, ROOK_BEHIND_PASSER, ROOK_OPEN_FILE_MG,
-        ROOK_SEMI_OPEN_FILE_MG, TEMPO_MG, evaluate, king_danger, side_features, side_mobility,
+        BISHOP_PAIR_EG, BISHOP_PAIR_MG, DOUBLED_PAWN_EG, DOUBLED_PAWN_MG, EvalWeights,
+        ISOLATED_PAWN_EG, ISOLATED_PAWN_MG, KING_SHIELD_MISSING_MG, KNIGHT_MOBILITY,
+        KNIGHT_MOBILITY_BASE, PASSED_PAWN_EG, PASSED_PAWN_MG, PAWN_MG, ROOK_BEHIND_PASSER,
+        ROOK_OPEN_FILE_MG, ROOK_SEMI_OPEN_FILE_MG, TEMPO_MG, evaluate, evaluate_cached,
+        evaluate_with, king_danger, pawn_entry, pawn_structure, side_features, side_mobility,
     };
-    use crate::chess_engine::board::{Board, WHITE};
+    use std::sync::Arc;
+
+    use rand::{RngExt, SeedableRng};
+    use rand_pcg::Pcg64Mcg;
+
+    use crate::chess_engine::board::{BLACK, Board, WHITE};
+    use crate::chess_engine::engine::pawn_table::PawnTable;
     use crate::chess_engine::utils::init_tables;
 
     #[test]
@@ -761,15 +1068,24 @@ mod tests {
         assert!(evaluate(&black) < 0);
     }
 
-    /// `side_features(WHITE)` difference between two positions, `(mg, eg)`.
+    /// White's pawn-structure plus piece-feature terms, `(mg, eg)`.
+    #[allow(clippy::similar_names)]
+    fn white_features(board: &Board) -> (i32, i32) {
+        let w = &EvalWeights::DEFAULT;
+        let (passed, pawn_mg, pawn_eg) = pawn_structure(board, WHITE, w);
+        let (mg, eg) = side_features(board, WHITE, w, passed);
+        (pawn_mg + mg, pawn_eg + eg)
+    }
+
+    /// [`white_features`] difference between two positions, `(mg, eg)`.
     /// Kings are kept off the back ranks in these fixtures so the pawn-shield
     /// term stays silent and single features can be isolated.
     #[allow(clippy::similar_names)]
     fn white_features_diff(fen_a: &str, fen_b: &str) -> (i32, i32) {
         let a = Board::from_fen(fen_a).unwrap();
         let b = Board::from_fen(fen_b).unwrap();
-        let (a_mg, a_eg) = side_features(&a, WHITE);
-        let (b_mg, b_eg) = side_features(&b, WHITE);
+        let (a_mg, a_eg) = white_features(&a);
+        let (b_mg, b_eg) = white_features(&b);
         (a_mg - b_mg, a_eg - b_eg)
     }
 
@@ -828,13 +1144,10 @@ mod tests {
     fn bare_king_pays_full_shield_penalty() {
         // king on e1 with no pawns at all: all three shield squares are empty
         let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
-        assert_eq!(
-            side_features(&board, WHITE),
-            (3 * KING_SHIELD_MISSING_MG, 0)
-        );
+        assert_eq!(white_features(&board), (3 * KING_SHIELD_MISSING_MG, 0));
         // a king that has left the back ranks takes no shield penalty
         let out = Board::from_fen("4k3/8/8/8/4K3/8/8/8 w - - 0 1").unwrap();
-        assert_eq!(side_features(&out, WHITE), (0, 0));
+        assert_eq!(white_features(&out), (0, 0));
     }
 
     #[test]
@@ -844,10 +1157,13 @@ mod tests {
         // pawn cover interfere)
         let center = Board::from_fen("8/8/8/3N4/8/8/8/K6k w - - 0 1").unwrap();
         let expected = KNIGHT_MOBILITY.0 * (8 - KNIGHT_MOBILITY_BASE);
-        assert_eq!(side_mobility(&center, WHITE).0, expected);
+        assert_eq!(
+            side_mobility(&center, WHITE, &EvalWeights::DEFAULT).0,
+            expected
+        );
         // a cornered knight has two squares and scores below the baseline
         let corner = Board::from_fen("N7/8/8/8/8/8/8/K6k w - - 0 1").unwrap();
-        assert!(side_mobility(&corner, WHITE).0 < 0);
+        assert!(side_mobility(&corner, WHITE, &EvalWeights::DEFAULT).0 < 0);
     }
 
     #[test]
@@ -857,7 +1173,10 @@ mod tests {
         let attacked = Board::from_fen("k7/8/8/8/6q1/8/8/r5K1 w - - 0 1").unwrap();
         // …while a lone distant queen touches nothing near g1
         let safe = Board::from_fen("k7/8/8/q7/8/8/8/6K1 w - - 0 1").unwrap();
-        assert!(king_danger(&attacked, WHITE) < king_danger(&safe, WHITE));
+        assert!(
+            king_danger(&attacked, WHITE, &EvalWeights::DEFAULT)
+                < king_danger(&safe, WHITE, &EvalWeights::DEFAULT)
+        );
     }
 
     #[test]
@@ -866,8 +1185,8 @@ mod tests {
         // same passer and kings; only the rook moves from behind (c1) to in
         // front (c8) of the pawn's path
         let diff = white_features_diff(
-            "4k3/8/8/2P5/8/8/8/2R1K3 w - - 0 1",
-            "2R1k3/8/8/2P5/8/8/8/4K3 w - - 0 1",
+            "8/4k3/8/2P5/8/8/8/2R1K3 w - - 0 1",
+            "2R5/4k3/8/2P5/8/8/8/4K3 w - - 0 1",
         );
         assert_eq!(diff, ROOK_BEHIND_PASSER);
     }
@@ -898,4 +1217,95 @@ mod tests {
         let base_endgame = Board::from_fen("4k3/8/8/8/8/8/2P5/4K3 w - - 0 1").unwrap();
         assert!(evaluate(&endgame) - evaluate(&base_endgame) > 100);
     }
+
+    #[test]
+    fn weights_file_round_trips_and_drives_the_evaluation() {
+        init_tables();
+        let mut weights = EvalWeights::DEFAULT;
+        weights.bishop_pair = [40, 60];
+        weights.pst_eg[5][0] = -99;
+        let text = weights.save();
+        assert_eq!(EvalWeights::load(&text).unwrap(), weights);
+        let mut values = weights.to_vec();
+        values[0] += 1;
+        let mut shifted = EvalWeights::DEFAULT;
+        shifted.set_from_slice(&values);
+        assert_eq!(shifted.material_mg[0], PAWN_MG + 1);
+        // the king's material is not a weight, so the tuner cannot move it
+        let material = text.lines().find(|line| line.starts_with("material_mg"));
+        assert_eq!(material.unwrap().split_whitespace().count(), 6);
+        assert!(EvalWeights::load("material_mg 1 2 3 4 5 6").is_err());
+
+        // a partial file keeps the defaults for everything it leaves out
+        let partial = EvalWeights::load("# only one term\nbishop_pair 40 60\n").unwrap();
+        assert_eq!(partial.bishop_pair, [40, 60]);
+        assert_eq!(partial.tempo_mg, TEMPO_MG);
+        assert!(EvalWeights::load("bishop_pair 40").is_err());
+        assert!(EvalWeights::load("no_such_term 1").is_err());
+
+        let board = Board::from_fen("4k3/8/8/7K/8/8/8/1B3B2 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate_with(&board, &EvalWeights::DEFAULT),
+            evaluate(&board)
+        );
+        assert!(evaluate_with(&board, &partial) > evaluate(&board));
+    }
+
+    #[test]
+    fn a_board_evaluates_with_weights_loaded_from_a_file() {
+        init_tables();
+        let path = std::env::temp_dir().join(format!("weights-{}.txt", std::process::id()));
+        std::fs::write(&path, "tempo_mg 40\nbishop_pair 90 90\n").unwrap();
+        let weights = EvalWeights::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(EvalWeights::open(&path).is_err());
+
+        let mut board = Board::from_fen("4k3/8/8/7K/8/8/8/1B3B2 w - - 0 1").unwrap();
+        let default = evaluate(&board);
+        board.set_weights(Some(Arc::new(weights.clone())));
+        assert_eq!(evaluate(&board), evaluate_with(&board, &weights));
+        assert!(evaluate(&board) > default);
+        assert_eq!(
+            evaluate_cached(&board, &mut PawnTable::new()),
+            evaluate(&board)
+        );
+        board.set_weights(None);
+        assert_eq!(evaluate(&board), default);
+    }
+
+    #[test]
+    fn cached_and_uncached_evaluations_agree() {
+        init_tables();
+        // one table shared by every game, as in a search: unmaking revisits
+        // the structures already stored
+        // each side's passers are filed under its own colour
+        let board = Board::from_fen("4k3/8/8/3P4/8/6p1/8/4K3 w - - 0 1").unwrap();
+        let entry = pawn_entry(&board, &EvalWeights::DEFAULT);
+        assert_eq!(entry.passed[usize::from(WHITE)].trailing_zeros(), 35);
+        assert_eq!(entry.passed[usize::from(BLACK)].trailing_zeros(), 22);
+
+        let mut pawns = PawnTable::new();
+        let mut rng = Pcg64Mcg::seed_from_u64(7);
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/1P1p4/8/4P3/8/8/6p1/R3K2R b KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let mut board = Board::from_fen(fen).unwrap();
+            for _ in 0..120 {
+                assert_eq!(evaluate_cached(&board, &mut pawns), evaluate(&board));
+                // a second probe is a hit and must agree too
+                assert_eq!(evaluate_cached(&board, &mut pawns), evaluate(&board));
+                let moves = board.generate_moves(board.turn);
+                if moves.is_empty() {
+                    break;
+                }
+                board.commit_verified_move(moves[rng.random_range(0..moves.len())]);
+            }
+            while !board.history.is_empty() {
+                board.unmake_move();
+                assert_eq!(evaluate_cached(&board, &mut pawns), evaluate(&board));
+            }
+        }
+    }
 }
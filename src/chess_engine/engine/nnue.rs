@@ -0,0 +1,305 @@
+//! An efficiently updatable neural network (NNUE) evaluation.
+//!
+//! The network is the simple *768 → N×2 → 1* design: one input per
+//! (colour, piece, square), a hidden layer of `N` neurons computed twice — once
+//! from each side's point of view — and a single output. Each perspective sees
+//! the board from its own side (its pieces first, ranks flipped for Black), so
+//! one set of weights serves both colours. The output neuron reads the side to
+//! move's half first, then the opponent's, through a clipped `ReLU`.
+//!
+//! The hidden layer is what makes it cheap: it is a sum of weight columns, one
+//! per piece on the board, so an [`Accumulator`] kept on the [`Board`] adds or
+//! subtracts a single column whenever a piece is placed or lifted
+//! (`add_piece`/`remove_piece`), and make/unmake keep it current for free.
+//! [`evaluate`](super::evaluation) then only runs the small output layer.
+//!
+//! # Network file
+//!
+//! Little-endian `i16`s, in order: the feature weights (`768 × N`, input-major),
+//! the `N` hidden biases, the `2N` output weights (side to move first), and the
+//! output bias. Trailing zero padding (as written by trainers that align files
+//! to 64 bytes) is allowed. Hidden values are quantised by `QA`, output
+//! weights by `QB`, and the result is scaled by `EVAL_SCALE` into centipawns.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::chess_engine::{
+    board::{BLACK, Board, Turn, WHITE},
+    piece::Piece,
+    position::Position,
+};
+
+/// Number of input features: 2 colours × 6 pieces × 64 squares.
+const INPUTS: usize = 768;
+/// Quantisation of the hidden layer (its clipping ceiling).
+const QA: i32 = 255;
+/// Quantisation of the output weights.
+const QB: i32 = 64;
+/// Centipawns per unit of network output.
+const EVAL_SCALE: i32 = 400;
+/// Bytes of zero padding a network file may end with.
+const MAX_PADDING: usize = 64;
+
+/// A loaded network: the weights shared by every board that evaluates with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    /// `INPUTS × hidden`, the column for input `i` at `i * hidden`.
+    feature_weights: Vec<i16>,
+    feature_bias: Vec<i16>,
+    /// `2 × hidden`: the side to move's half, then the opponent's.
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+impl Network {
+    /// Parses a network file (see the [module documentation](self)); the
+    /// hidden-layer size is inferred from the length.
+    ///
+    /// # Errors
+    /// Returns an error if the length fits no hidden-layer size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        // 2 bytes per value; INPUTS + 3 values per neuron, plus the output bias
+        let per_neuron = 2 * (INPUTS + 3);
+        let hidden = bytes.len().saturating_sub(2) / per_neuron;
+        let used = hidden * per_neuron + 2;
+        if hidden == 0 || bytes.len() - used >= MAX_PADDING || bytes[used..].iter().any(|&b| b != 0)
+        {
+            return Err(format!(
+                "NNUE file of {} bytes does not match a 768-input network",
+                bytes.len()
+            ));
+        }
+        let mut values = bytes[..used]
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]));
+        let mut take = |count: usize| values.by_ref().take(count).collect::<Vec<_>>();
+        Ok(Self {
+            hidden,
+            feature_weights: take(INPUTS * hidden),
+            feature_bias: take(hidden),
+            output_weights: take(2 * hidden),
+            output_bias: take(1)[0],
+        })
+    }
+
+    /// Reads a network file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Cannot read NNUE file {}: {e}", path.display()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The network in file form, without padding.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.feature_weights
+            .iter()
+            .chain(&self.feature_bias)
+            .chain(&self.output_weights)
+            .chain(std::iter::once(&self.output_bias))
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    /// Number of hidden neurons per perspective.
+    #[must_use]
+    pub const fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    /// The weight column of one input.
+    fn column(&self, input: usize) -> &[i16] {
+        &self.feature_weights[input * self.hidden..(input + 1) * self.hidden]
+    }
+}
+
+/// The input index of `piece` of colour `color` on `square`, as seen by
+/// `perspective`: its own pieces first, and the board flipped for Black.
+fn feature_index(perspective: Turn, color: Turn, piece: Piece, square: Position) -> usize {
+    let piece_index = match piece {
+        Piece::Pawn => 0,
+        Piece::Knight => 1,
+        Piece::Bishop => 2,
+        Piece::Rook => 3,
+        Piece::Queen => 4,
+        _ => 5,
+    };
+    let square = if perspective == WHITE {
+        square.as_usize()
+    } else {
+        square.as_usize() ^ 0x38
+    };
+    usize::from(color != perspective) * 384 + piece_index * 64 + square
+}
+
+/// The hidden layer for one position, from both sides' points of view,
+/// indexed by [`Turn`].
+#[derive(Clone)]
+pub struct Accumulator {
+    network: Arc<Network>,
+    values: [Vec<i16>; 2],
+}
+
+impl PartialEq for Accumulator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.network, &other.network) && self.values == other.values
+    }
+}
+
+impl Eq for Accumulator {}
+
+impl Accumulator {
+    /// Computes the hidden layer of `board` from scratch.
+    fn new(network: Arc<Network>, board: &Board) -> Self {
+        let mut accumulator = Self {
+            values: [network.feature_bias.clone(), network.feature_bias.clone()],
+            network,
+        };
+        for (index, mut pieces) in board.piece_boards.into_iter().enumerate() {
+            let (piece, color) = Board::get_piece_information_index(index);
+            while pieces.is_not_empty() {
+                accumulator.add(color, piece, Position::new(pieces.trailing_zeros()));
+                pieces.reset_lsb();
+            }
+        }
+        accumulator
+    }
+
+    /// Accounts for `piece` of colour `color` arriving on `square`.
+    pub(crate) fn add(&mut self, color: Turn, piece: Piece, square: Position) {
+        for perspective in [WHITE, BLACK] {
+            let column = self
+                .network
+                .column(feature_index(perspective, color, piece, square));
+            for (value, weight) in self.values[usize::from(perspective)].iter_mut().zip(column) {
+                *value = value.wrapping_add(*weight);
+            }
+        }
+    }
+
+    /// Accounts for `piece` of colour `color` leaving `square`.
+    pub(crate) fn remove(&mut self, color: Turn, piece: Piece, square: Position) {
+        for perspective in [WHITE, BLACK] {
+            let column = self
+                .network
+                .column(feature_index(perspective, color, piece, square));
+            for (value, weight) in self.values[usize::from(perspective)].iter_mut().zip(column) {
+                *value = value.wrapping_sub(*weight);
+            }
+        }
+    }
+
+    /// Runs the output layer: centipawns from `turn`'s point of view.
+    pub(crate) fn evaluate(&self, turn: Turn) -> i32 {
+        let hidden = self.network.hidden;
+        let (own_weights, their_weights) = self.network.output_weights.split_at(hidden);
+        let layer = |values: &[i16], weights: &[i16]| -> i64 {
+            values
+                .iter()
+                .zip(weights)
+                .map(|(&value, &weight)| {
+                    i64::from(i32::from(value).clamp(0, QA) * i32::from(weight))
+                })
+                .sum()
+        };
+        let output = layer(&self.values[usize::from(turn)], own_weights)
+            + layer(&self.values[usize::from(!turn)], their_weights)
+            + i64::from(self.network.output_bias);
+        let centipawns = output * i64::from(EVAL_SCALE) / i64::from(QA * QB);
+        i32::try_from(centipawns.clamp(-30_000, 30_000)).unwrap_or_default()
+    }
+}
+
+impl Board {
+    /// Switches the board to NNUE evaluation with `network`, or back to the
+    /// classical evaluation with `None`. The accumulator is built from the
+    /// current position and then follows every move made and unmade.
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(|network| Accumulator::new(network, self));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::{RngExt, SeedableRng};
+    use rand_pcg::Pcg64Mcg;
+
+    use super::{Accumulator, INPUTS, Network};
+    use crate::chess_engine::{board::Board, engine::evaluation::evaluate, utils::init_tables};
+
+    /// A network of small random weights.
+    fn random_network(hidden: usize, seed: u64) -> Network {
+        let mut rng = Pcg64Mcg::seed_from_u64(seed);
+        let mut values =
+            |count: usize| -> Vec<i16> { (0..count).map(|_| rng.random_range(-64..64)).collect() };
+        Network {
+            hidden,
+            feature_weights: values(INPUTS * hidden),
+            feature_bias: values(hidden),
+            output_weights: values(2 * hidden),
+            output_bias: 17,
+        }
+    }
+
+    #[test]
+    fn file_format_round_trips_with_padding() {
+        let network = random_network(8, 1);
+        let mut bytes = network.to_bytes();
+        assert_eq!(Network::from_bytes(&bytes).unwrap(), network);
+        bytes.extend_from_slice(&[0; 30]);
+        assert_eq!(Network::from_bytes(&bytes).unwrap().hidden_size(), 8);
+        bytes.push(1);
+        assert!(Network::from_bytes(&bytes).is_err());
+        assert!(Network::from_bytes(&[0; 100]).is_err());
+    }
+
+    #[test]
+    fn incremental_updates_match_a_full_refresh() {
+        init_tables();
+        let network = Arc::new(random_network(16, 2));
+        // castles, en passant and promotions with and without capture
+        let mut board = Board::from_fen("r3k2r/1P1p4/8/4P3/8/8/6p1/R3K2R b KQkq - 0 1").unwrap();
+        board.set_network(Some(Arc::clone(&network)));
+        let start = board.nnue.clone();
+
+        let mut rng = Pcg64Mcg::seed_from_u64(3);
+        for _ in 0..40 {
+            let moves = board.generate_moves(board.turn);
+            if moves.is_empty() {
+                break;
+            }
+            board.commit_verified_move(moves[rng.random_range(0..moves.len())]);
+            let refreshed = Accumulator::new(Arc::clone(&network), &board);
+            assert!(board.nnue.as_ref() == Some(&refreshed));
+        }
+        while !board.history.is_empty() {
+            board.unmake_move();
+        }
+        assert!(board.nnue == start);
+    }
+
+    #[test]
+    fn evaluation_is_colour_symmetric_and_falls_back_to_classical() {
+        init_tables();
+        let network = Arc::new(random_network(16, 4));
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mirrored = "rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 3";
+        let mut board = Board::from_fen(fen).unwrap();
+        let mut flipped = Board::from_fen(mirrored).unwrap();
+        let classical = evaluate(&board);
+        board.set_network(Some(Arc::clone(&network)));
+        flipped.set_network(Some(network));
+        assert_eq!(evaluate(&board), evaluate(&flipped));
+
+        board.set_network(None);
+        assert_eq!(evaluate(&board), classical);
+    }
+}
@@ -0,0 +1,98 @@
+//! A pawn hash table: a small per-thread cache of pawn-structure scores.
+//!
+//! Pawns move rarely, so the same pawn structure comes up at a great many
+//! nodes of one search. The terms that depend on the pawns alone — isolated
+//! and doubled pawns, and which pawns are passed — are therefore computed once
+//! per structure and cached here, keyed by the board's *pawn key* (the Zobrist
+//! hash of its pawns, kept up to date by make/unmake like the full hash).
+//! The passed-pawn bitboards are cached with the scores, since the rest of the
+//! evaluation scales each passer by the pieces around it.
+//!
+//! Each search thread owns its table, so it needs no locking; a slot holds one
+//! entry and a store simply replaces whatever was there. The full key is
+//! stored and compared, so a hit is never a different structure.
+
+use crate::chess_engine::{bitboard::Bitboard, zobrist::ZobristHash};
+
+/// Number of slots: `2^14` entries of 40 bytes, about 640 KiB per thread.
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// The cached pawn-structure evaluation of one pawn configuration.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PawnEntry {
+    /// Pawn key of the structure.
+    pub key: ZobristHash,
+    /// Passed pawns, indexed by [`Turn`](crate::chess_engine::board::Turn).
+    pub passed: [Bitboard; 2],
+    /// Middlegame pawn-structure score, White minus Black.
+    pub mg: i32,
+    /// Endgame pawn-structure score, White minus Black.
+    pub eg: i32,
+}
+
+/// A fixed-size, always-replace cache of [`PawnEntry`]s.
+pub struct PawnTable {
+    slots: Box<[Option<PawnEntry>]>,
+}
+
+impl PawnTable {
+    /// Creates an empty table.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            slots: vec![None; PAWN_TABLE_SIZE].into_boxed_slice(),
+        }
+    }
+
+    /// The slot `key` maps to.
+    #[allow(clippy::cast_possible_truncation)]
+    const fn index(key: ZobristHash) -> usize {
+        key as usize & (PAWN_TABLE_SIZE - 1)
+    }
+
+    /// Looks up the entry for `key`.
+    #[must_use]
+    pub fn probe(&self, key: ZobristHash) -> Option<PawnEntry> {
+        self.slots[Self::index(key)].filter(|entry| entry.key == key)
+    }
+
+    /// Stores `entry`, replacing whatever shared its slot.
+    pub fn store(&mut self, entry: PawnEntry) {
+        self.slots[Self::index(entry.key)] = Some(entry);
+    }
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PAWN_TABLE_SIZE, PawnEntry, PawnTable};
+    use crate::chess_engine::bitboard::Bitboard;
+
+    fn entry(key: u64, mg: i32) -> PawnEntry {
+        PawnEntry {
+            key,
+            passed: [Bitboard::new(); 2],
+            mg,
+            eg: -mg,
+        }
+    }
+
+    #[test]
+    fn probe_returns_only_the_stored_key() {
+        let mut table = PawnTable::new();
+        assert!(table.probe(5).is_none());
+        table.store(entry(5, 10));
+        assert_eq!(table.probe(5), Some(entry(5, 10)));
+
+        // a key sharing the slot replaces it, and the old key then misses
+        let clash = 5 + PAWN_TABLE_SIZE as u64;
+        table.store(entry(clash, 20));
+        assert!(table.probe(5).is_none());
+        assert_eq!(table.probe(clash).unwrap().mg, 20);
+    }
+}
@@ -3,36 +3,41 @@
 //! The driver, [`search_position`], runs *iterative deepening* — it searches to
 //! depth 1, then 2, and so on — over a fail-soft [negamax] with alpha-beta
 //! pruning. Each iteration opens an *aspiration window* around the previous
-//! score, widening it on a fail. Inside the tree the search prunes and reduces:
-//! *null-move pruning* (a reduced search after passing the turn that still
-//! fails high cuts the node), *principal variation search* (only the first
-//! move gets a full window; the rest are probed with a zero-width window), and
-//! *late move reductions* (late quiet moves are probed a ply shallower and
-//! re-searched only on a fail-high). A node in check is extended one ply.
+//! score, widening it on a fail. In *multi-PV* mode an iteration searches the
+//! root once per requested line, each pass excluding the moves already chosen,
+//! so the top N root moves come back with their own scores and PVs. Inside the
+//! tree the search prunes and reduces: *null-move pruning* (a reduced search
+//! after passing the turn that still fails high cuts the node), *principal
+//! variation search* (only the first move gets a full window; the rest are
+//! probed with a zero-width window), and *late move reductions* (late quiet
+//! moves are probed a ply shallower and re-searched only on a fail-high). A
+//! node in check is extended one ply.
 //!
-//! At the leaves a quiescence search resolves pending
-//! captures and promotions so the static [evaluation](super::evaluation) is only
-//! applied to quiet positions; while in check it searches every evasion
-//! instead, and hopeless captures are skipped by *delta pruning* and a
-//! negative *static exchange evaluation* ([`see`]). Moves are ordered to make
-//! alpha-beta prune more: the
-//! transposition-table move, promotions and MVV-LVA captures, then quiet moves
-//! led by the *killer moves* (quiet refutations of sibling nodes) and ranked by
-//! the *history heuristic* (how often a move's origin→destination caused
-//! cutoffs). Draws (fifty-move rule,
-//! repetition, insufficient material) score `0`, and mates are encoded as
+//! At the leaves a quiescence search resolves pending captures and promotions
+//! so the static [evaluation](super::evaluation) is only applied to quiet
+//! positions; while in check it searches every evasion instead, and hopeless
+//! captures are skipped by *delta pruning* and a negative *static exchange
+//! evaluation* ([`see`]). Moves are ordered to make alpha-beta prune more, and
+//! handed out lazily by a `MovePicker` so a node that cuts off early does not
+//! generate the rest: the transposition-table move, promotions and MVV-LVA
+//! captures that do not lose material, then quiet moves led by the *killer
+//! moves* (quiet refutations of sibling nodes) and ranked by the *history
+//! heuristic* (how often a move's origin→destination caused cutoffs), and
+//! finally the losing captures. Draws (fifty-move rule, repetition,
+//! insufficient material) score `0`, and mates are encoded as
 //! `MATE_SCORE - ply` so that shorter mates score higher.
 //!
 //! The search aborts cooperatively: every `ABORT_CHECK_INTERVAL` nodes it
-//! polls a stop flag and an optional deadline, so the UCI layer can stop it
-//! mid-search. The UCI layer owns time allocation; the search only obeys the
-//! limits it is handed.
+//! polls a stop flag, an optional deadline, and an optional node budget, so
+//! the UCI layer can stop it mid-search. Between iterations the main worker
+//! also consults a `TimeManager` holding the soft time limit, and starts no
+//! new iteration once the best move has settled for long enough.
 //!
 //! [negamax]: https://www.chessprogramming.org/Negamax
 
-use std::sync::LazyLock;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::time::Instant;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
 
 use crate::chess_engine::bitboard::Bitboard;
 use crate::chess_engine::board::{BLACK, Turn, WHITE};
@@ -40,8 +45,11 @@ use crate::chess_engine::computed_boards::{
     BISHOP_ATTACKS, BISHOP_BLOCKERS, BISHOP_MAGICS, KING_RING_MOVES, KNIGHT_MOVES, PAWN_ATTACKS,
     ROOK_ATTACKS, ROOK_BLOCKERS, ROOK_MAGICS,
 };
-use crate::chess_engine::engine::evaluation::evaluate;
+use crate::chess_engine::engine::evaluation::evaluate_cached;
+use crate::chess_engine::engine::pawn_table::PawnTable;
+use crate::chess_engine::engine::time_manager::{PonderClock, TimeManager};
 use crate::chess_engine::engine::transposition::{Bound, TranspositionTable};
+use crate::chess_engine::move_list::MoveList;
 use crate::chess_engine::piece::Piece;
 
 use super::super::{board::Board, moves::Move, moves::SpecialMove};
@@ -75,17 +83,45 @@ const ASPIRATION_MAX_MARGIN: i32 = 640;
 /// Maximum iterative-deepening depth the search will attempt.
 pub const MAX_DEPTH: u8 = 64;
 
+/// Plies a mate search ([`SearchLimits::mate`]) iterates beyond the length of
+/// the mate it looks for.
+const MATE_SEARCH_EXTRA_PLIES: u8 = 4;
+
 /// Hard cap on the distance from the root, including check extensions; a node
 /// this deep returns its static evaluation instead of recursing further.
 const MAX_PLY: usize = 128;
 
-/// The conditions under which a search stops.
-#[derive(Debug, Clone, Copy)]
+/// The conditions under which a search stops, and how many root lines it
+/// reports.
+#[derive(Debug, Clone)]
 pub struct SearchLimits {
     /// Maximum iterative-deepening depth.
     pub depth: u8,
     /// Hard wall-clock limit for the whole search, if any.
     pub deadline: Option<Instant>,
+    /// Soft time limit, measured from the start of the search: once it has
+    /// passed (stretched or shrunk by the `TimeManager` as the best move and
+    /// score settle), no new iteration is started. See
+    /// [`time_manager`](super::time_manager).
+    pub soft_time: Option<Duration>,
+    /// The clock of a pondering search, shared with the thread that sends
+    /// `ponderhit`: once it has started, its hard and soft limits apply as
+    /// [`deadline`](Self::deadline) and [`soft_time`](Self::soft_time) would.
+    pub ponder: Option<Arc<PonderClock>>,
+    /// Node budget across all search threads, if any. Polled every
+    /// `ABORT_CHECK_INTERVAL` nodes, so a search may overshoot it slightly.
+    pub nodes: Option<u64>,
+    /// Number of best root moves to search and report (multi-PV); `1` for a
+    /// normal search.
+    pub multi_pv: usize,
+    /// Mate search: stop as soon as a mate in at most this many moves is
+    /// proven for the side to move, rather than at the first mate of any
+    /// length.
+    pub mate: Option<u8>,
+    /// Root moves the search may choose from (UCI `searchmoves`); empty means
+    /// every legal move. Moves that are not legal at the root are ignored, and
+    /// a list with no legal move in it does not restrict the search.
+    pub search_moves: Vec<Move>,
 }
 
 impl SearchLimits {
@@ -96,6 +132,40 @@ impl SearchLimits {
         Self {
             depth: depth.min(MAX_DEPTH),
             deadline: None,
+            soft_time: None,
+            ponder: None,
+            nodes: None,
+            multi_pv: 1,
+            mate: None,
+            search_moves: Vec::new(),
+        }
+    }
+
+    /// Limits the search to roughly `nodes` nodes (see [`nodes`](Self::nodes)),
+    /// with no depth or time limit.
+    #[must_use]
+    pub const fn node_budget(nodes: u64) -> Self {
+        Self {
+            depth: MAX_DEPTH,
+            deadline: None,
+            soft_time: None,
+            ponder: None,
+            nodes: Some(nodes),
+            multi_pv: 1,
+            mate: None,
+            search_moves: Vec::new(),
+        }
+    }
+
+    /// A mate search for a mate in at most `moves` moves. Iterations run to
+    /// `MATE_SEARCH_EXTRA_PLIES` beyond the mate's length, so lines hidden by
+    /// reductions at the nominal depth are still found, and then stop.
+    #[must_use]
+    pub fn mate(moves: u8) -> Self {
+        let plies = u16::from(moves) * 2 + u16::from(MATE_SEARCH_EXTRA_PLIES);
+        Self {
+            mate: Some(moves),
+            ..Self::depth(u8::try_from(plies).unwrap_or(MAX_DEPTH))
         }
     }
 
@@ -106,6 +176,12 @@ impl SearchLimits {
         Self {
             depth: MAX_DEPTH,
             deadline: None,
+            soft_time: None,
+            ponder: None,
+            nodes: None,
+            multi_pv: 1,
+            mate: None,
+            search_moves: Vec::new(),
         }
     }
 }
@@ -126,18 +202,37 @@ pub struct SearchResult {
     /// The principal variation: the expected line of best play, `best_move`
     /// first.
     pub pv: Vec<Move>,
+    /// The best root moves in descending score order, one per requested multi-PV
+    /// line (fewer if the position has fewer legal moves); the first line is
+    /// `score`/`pv`.
+    pub lines: Vec<RootLine>,
+}
+
+/// One multi-PV line: a root move's score and its principal variation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootLine {
+    /// Score from the side-to-move's perspective (see [`SearchResult::score`]).
+    pub score: i32,
+    /// The line of best play starting with this root move.
+    pub pv: Vec<Move>,
 }
 
 /// Mutable state threaded through the recursive search: the stop signal, the
-/// deadline, this worker's local node count, the shared cross-thread node total,
-/// the transposition table, the quiet-move ordering heuristics, and whether an
-/// abort has been requested.
+/// deadline and node budget, this worker's local node count, the shared
+/// cross-thread node total, the transposition table, this worker's pawn hash
+/// table, the quiet-move ordering heuristics, and whether an abort has been
+/// requested.
 struct SearchContext<'a> {
     stop: &'a AtomicBool,
     deadline: Option<Instant>,
+    /// A pondering search's clock, whose deadline applies once it starts.
+    ponder: Option<&'a PonderClock>,
+    max_nodes: Option<u64>,
     nodes: u64,
     aborted: bool,
     tt: &'a TranspositionTable,
+    /// Pawn-structure cache for the evaluation, private to this worker.
+    pawn_table: PawnTable,
     /// Aggregate node count across all search threads, flushed in bulk so the
     /// hot path stays contention-free.
     shared_nodes: &'a AtomicU64,
@@ -151,20 +246,29 @@ struct SearchContext<'a> {
     /// History heuristic: for each (origin, destination) pair, how often quiet
     /// moves with that geometry caused beta cutoffs, weighted by depth².
     history: Box<[[i32; 64]; 64]>,
+    /// Root moves the current pass must skip: the lines already found at this
+    /// depth in multi-PV mode.
+    excluded_root_moves: Vec<Move>,
 }
 
 impl SearchContext<'_> {
     /// Counts the current node and, every [`ABORT_CHECK_INTERVAL`] nodes, flushes
-    /// the interval into the shared total and checks the stop flag and deadline.
-    /// Returns `true` once an abort has been triggered.
+    /// the interval into the shared total and checks the stop flag, the node
+    /// budget, and the deadline. Returns `true` once an abort has been triggered.
     fn count_node_and_check_abort(&mut self) -> bool {
         self.nodes += 1;
         if self.nodes.is_multiple_of(ABORT_CHECK_INTERVAL) {
-            self.shared_nodes
-                .fetch_add(ABORT_CHECK_INTERVAL, Ordering::Relaxed);
-            if self.stop.load(Ordering::Relaxed) {
+            let total = self
+                .shared_nodes
+                .fetch_add(ABORT_CHECK_INTERVAL, Ordering::Relaxed)
+                + ABORT_CHECK_INTERVAL;
+            if self.stop.load(Ordering::Relaxed)
+                || self.max_nodes.is_some_and(|max_nodes| total >= max_nodes)
+            {
                 self.aborted = true;
-            } else if let Some(deadline) = self.deadline
+            } else if let Some(deadline) = self
+                .deadline
+                .or_else(|| self.ponder.and_then(PonderClock::deadline))
                 && Instant::now() >= deadline
             {
                 self.aborted = true;
@@ -207,7 +311,7 @@ impl SearchContext<'_> {
 pub fn find_best_move(board: &Board, depth: u8) -> SearchResult {
     /// A process-wide table reused across `find_best_move` calls, so repeated
     /// fixed-depth searches (e.g. the WAC suite's 300 positions) don't each
-    /// allocate and zero a fresh 64 MiB table. Cleared at the start of every
+    /// allocate and zero a fresh default-sized table. Cleared at the start of every
     /// call to keep each search self-contained and deterministic.
     static TT: LazyLock<TranspositionTable> = LazyLock::new(TranspositionTable::new);
 
@@ -215,7 +319,7 @@ pub fn find_best_move(board: &Board, depth: u8) -> SearchResult {
     TT.clear();
     search_position(
         &mut board.clone(),
-        SearchLimits::depth(depth),
+        &SearchLimits::depth(depth),
         &stop,
         &TT,
         1,
@@ -229,14 +333,15 @@ pub fn find_best_move(board: &Board, depth: u8) -> SearchResult {
 /// remaining `threads - 1` *helper* workers search the same root on their own
 /// board clones and share the transposition table, diverging through TT timing
 /// races so they widen the main worker's effective search. All workers share the
-/// stop flag, the deadline, and an aggregate node counter; each completed search
-/// returns the result of the main worker's last fully completed iteration.
+/// stop flag, the deadline, and an aggregate node counter (which the node budget
+/// is checked against); each completed search returns the result of the main
+/// worker's last fully completed iteration.
 ///
 /// `threads` is clamped to at least 1. When `report` is set, a UCI `info` line is
 /// printed per depth by the main worker.
 pub fn search_position(
     board: &mut Board,
-    limits: SearchLimits,
+    limits: &SearchLimits,
     stop: &AtomicBool,
     tt: &TranspositionTable,
     threads: usize,
@@ -251,13 +356,17 @@ pub fn search_position(
     let make_ctx = |order_noise: u64| SearchContext {
         stop,
         deadline: limits.deadline,
+        ponder: limits.ponder.as_deref(),
+        max_nodes: limits.nodes,
         nodes: 0,
         aborted: false,
         tt,
+        pawn_table: PawnTable::new(),
         shared_nodes: &shared_nodes,
         order_noise,
         killers: [[None; 2]; MAX_PLY],
         history: Box::new([[0; 64]; 64]),
+        excluded_root_moves: Vec::new(),
     };
 
     if threads <= 1 {
@@ -293,12 +402,14 @@ pub fn search_position(
 }
 
 /// One worker's iterative-deepening loop: searches depths `start_depth..` until
-/// the depth limit, deadline, stop flag, or a forced mate ends it, returning the
-/// last fully completed iteration. Node statistics reflect the shared
+/// the depth limit, deadline, node budget, stop flag, or a forced mate ends it,
+/// or (main worker only) the scaled soft time limit has passed, returning the
+/// last fully completed iteration (or, in multi-PV mode, the lines an
+/// interrupted iteration did finish). Node statistics reflect the shared
 /// cross-thread total.
 fn run_iterative(
     board: &mut Board,
-    limits: SearchLimits,
+    limits: &SearchLimits,
     ctx: &mut SearchContext,
     start: Instant,
     report: bool,
@@ -310,98 +421,174 @@ fn run_iterative(
         depth: 0,
         nodes: 0,
         pv: Vec::new(),
+        lines: Vec::new(),
     };
 
-    let mut prev_score = 0;
+    // `searchmoves` is applied by excluding every other root move up front,
+    // exactly as multi-PV excludes the lines it has already found
+    let legal_moves = board.generate_moves(board.turn);
+    let restricted = legal_moves
+        .iter()
+        .any(|mv| limits.search_moves.contains(mv));
+    let (root_moves, ignored_root_moves): (Vec<Move>, Vec<Move>) = legal_moves
+        .into_iter()
+        .partition(|mv| !restricted || limits.search_moves.contains(mv));
+    // never ask for more lines than there are root moves
+    let multi_pv = limits.multi_pv.clamp(1, root_moves.len().max(1));
+    let mut prev_scores = vec![0; multi_pv];
+    // only the main worker manages time; stopping it winds the helpers down
+    let mut time_manager = match (&limits.soft_time, &limits.ponder) {
+        _ if ctx.order_noise != 0 => None,
+        (Some(soft), _) => Some(TimeManager::new(start, *soft)),
+        (None, Some(_)) => Some(TimeManager::pondering()),
+        (None, None) => None,
+    };
     for depth in start_depth..=limits.depth.max(start_depth) {
-        let mut pv = Vec::new();
-
-        // Aspiration window: centre a narrow window on the last iteration's
-        // score; on a fail outside it, widen the failing side and re-search.
-        let mut margin = ASPIRATION_WINDOW;
-        let (mut alpha, mut beta) = if depth >= ASPIRATION_MIN_DEPTH {
-            (prev_score - margin, prev_score + margin)
-        } else {
-            (-INFINITY, INFINITY)
-        };
-        let score = loop {
-            pv.clear();
-            let score = negamax(board, depth, 0, alpha, beta, true, ctx, &mut pv);
-            if ctx.aborted || (score > alpha && score < beta) {
-                break score;
-            }
-            margin = margin.saturating_mul(4);
-            if score <= alpha {
-                alpha = if margin > ASPIRATION_MAX_MARGIN {
-                    -INFINITY
-                } else {
-                    score - margin
-                };
-            } else {
-                beta = if margin > ASPIRATION_MAX_MARGIN {
-                    INFINITY
-                } else {
-                    score + margin
-                };
+        let mut lines: Vec<RootLine> = Vec::with_capacity(multi_pv);
+        ctx.excluded_root_moves.clone_from(&ignored_root_moves);
+        for &prev_score in &prev_scores {
+            let (score, pv) = aspiration_search(board, depth, prev_score, ctx);
+            if ctx.aborted {
+                break;
             }
-        };
-        if ctx.aborted {
+            let root_move = pv.first().copied();
+            lines.push(RootLine { score, pv });
+            // a later pass can outscore an earlier one when the search is
+            // unstable; the stable sort keeps ties in search order
+            lines.sort_by_key(|line| std::cmp::Reverse(line.score));
+            // no move means the root is mate or stalemate: nothing left to find
+            let Some(root_move) = root_move else { break };
+            ctx.excluded_root_moves.push(root_move);
+        }
+        // aborted before even the first line of this depth completed
+        if lines.is_empty() {
             break;
         }
-        prev_score = score;
+        for (prev_score, line) in prev_scores.iter_mut().zip(&lines) {
+            *prev_score = line.score;
+        }
 
         result = SearchResult {
-            best_move: pv.first().copied(),
-            score,
+            best_move: lines[0].pv.first().copied(),
+            score: lines[0].score,
             depth,
             nodes: ctx.shared_nodes.load(Ordering::Relaxed),
-            pv,
+            pv: lines[0].pv.clone(),
+            lines,
         };
+        // an abort during a later multi-PV pass still keeps the lines that
+        // completed, but the depth is not reported or continued
+        if ctx.aborted {
+            break;
+        }
 
         if report {
-            print_info(&result, start);
+            print_info(&result, start, ctx.tt.hashfull());
         }
 
-        // a forced mate was found; deeper iterations cannot improve it
-        if score.abs() >= MATE_THRESHOLD {
+        // a mate search ends once a short enough mate is proven; otherwise a
+        // forced mate was found and deeper iterations cannot improve it
+        if let Some(moves) = limits.mate {
+            if result.score >= MATE_SCORE - (2 * i32::from(moves) - 1) {
+                break;
+            }
+        } else if result.score.abs() >= MATE_THRESHOLD {
             break;
         }
+
+        // the soft time limit, scaled by how settled the search is, has passed:
+        // a new iteration would likely be cut off by the hard deadline anyway
+        if let Some(time_manager) = &mut time_manager {
+            // a pondering search gets its soft limit once ponderhit arrives
+            if let Some((started, soft)) = limits.ponder.as_ref().and_then(|clock| clock.soft()) {
+                time_manager.start(started, soft);
+            }
+            time_manager.update(result.best_move, result.score);
+            if time_manager.should_stop() {
+                break;
+            }
+        }
     }
 
     result.nodes = ctx.shared_nodes.load(Ordering::Relaxed);
     result
 }
 
-/// Prints a UCI `info` line for a completed depth (score in centipawns or
-/// `mate N`, plus nodes, time, and the principal variation).
-fn print_info(result: &SearchResult, start: Instant) {
+/// Searches the root to `depth` inside an *aspiration window* centred on
+/// `prev_score` (the same line's score from the previous iteration), widening
+/// the failing side and re-searching until the score lands inside the window.
+/// Returns the score and its principal variation.
+fn aspiration_search(
+    board: &mut Board,
+    depth: u8,
+    prev_score: i32,
+    ctx: &mut SearchContext,
+) -> (i32, Vec<Move>) {
+    let mut pv = Vec::new();
+    let mut margin = ASPIRATION_WINDOW;
+    let (mut alpha, mut beta) = if depth >= ASPIRATION_MIN_DEPTH {
+        (prev_score - margin, prev_score + margin)
+    } else {
+        (-INFINITY, INFINITY)
+    };
+    loop {
+        pv.clear();
+        let score = negamax(board, depth, 0, alpha, beta, true, ctx, &mut pv);
+        if ctx.aborted || (score > alpha && score < beta) {
+            return (score, pv);
+        }
+        margin = margin.saturating_mul(4);
+        if score <= alpha {
+            alpha = if margin > ASPIRATION_MAX_MARGIN {
+                -INFINITY
+            } else {
+                score - margin
+            };
+        } else {
+            beta = if margin > ASPIRATION_MAX_MARGIN {
+                INFINITY
+            } else {
+                score + margin
+            };
+        }
+    }
+}
+
+/// Prints the UCI `info` lines for a completed depth, one per multi-PV line
+/// (score in centipawns or `mate N`, plus nodes, time, table fill in
+/// permille, and the line's PV).
+fn print_info(result: &SearchResult, start: Instant, hashfull: u16) {
     let elapsed = start.elapsed();
     let millis = elapsed.as_millis().max(1);
     let nps = (u128::from(result.nodes) * 1000) / millis;
 
-    let score = if result.score.abs() >= MATE_THRESHOLD {
-        // moves (not plies) until mate, negative when we are getting mated
-        let plies = MATE_SCORE - result.score.abs();
-        let moves = (plies + 1) / 2;
-        format!("mate {}", if result.score > 0 { moves } else { -moves })
-    } else {
-        format!("cp {}", result.score)
-    };
+    for (index, line) in result.lines.iter().enumerate() {
+        let score = if line.score.abs() >= MATE_THRESHOLD {
+            // moves (not plies) until mate, negative when we are getting mated
+            let plies = MATE_SCORE - line.score.abs();
+            let moves = (plies + 1) / 2;
+            format!("mate {}", if line.score > 0 { moves } else { -moves })
+        } else {
+            format!("cp {}", line.score)
+        };
 
-    let pv: Vec<String> = result
-        .pv
-        .iter()
-        .map(std::string::ToString::to_string)
-        .collect();
-    println!(
-        "info depth {} score {} nodes {} time {} nps {} pv {}",
-        result.depth,
-        score,
-        result.nodes,
-        millis,
-        nps,
-        pv.join(" ")
-    );
+        let pv: Vec<String> = line
+            .pv
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect();
+        println!(
+            "info depth {} multipv {} score {} nodes {} time {} nps {} hashfull {} pv {}",
+            result.depth,
+            index + 1,
+            score,
+            result.nodes,
+            millis,
+            nps,
+            hashfull,
+            pv.join(" ")
+        );
+    }
 }
 
 /// Negamax with alpha-beta pruning (fail-soft). Returns the score from the
@@ -440,7 +627,7 @@ fn negamax(
     }
 
     if usize::from(ply) >= MAX_PLY {
-        return evaluate(board);
+        return evaluate_cached(board, &mut ctx.pawn_table);
     }
 
     let in_check = board.in_check(board.turn);
@@ -504,29 +691,22 @@ fn negamax(
         }
     }
 
-    let mut moves = board.generate_moves(board.turn);
-    if moves.is_empty() {
-        return if in_check {
-            // mated: worse the closer to the root it happens
-            -(MATE_SCORE - i32::from(ply))
-        } else {
-            0 // stalemate
-        };
-    }
     let ply_killers = ctx.killers[usize::from(ply)];
-    order_moves(
-        board,
-        &mut moves,
-        tt_move,
-        ctx.order_noise,
-        ply_killers,
-        &ctx.history,
-    );
+    let mut picker = MovePicker::new(tt_move, ply_killers, ctx.order_noise);
 
     let alpha_orig = alpha;
     let mut best_score = -INFINITY;
     let mut best_move: Option<Move> = None;
-    for (move_index, mv) in moves.into_iter().enumerate() {
+    let mut has_legal_move = false;
+    let mut searched = 0;
+    while let Some(mv) = picker.next(board, &ctx.history) {
+        has_legal_move = true;
+        // multi-PV: later passes skip the root moves earlier passes chose
+        if ply == 0 && ctx.excluded_root_moves.contains(&mv) {
+            continue;
+        }
+        let move_index = searched;
+        searched += 1;
         let quiet = !is_tactical(board, mv);
         board.commit_verified_move(mv);
         let mut child_pv = Vec::new();
@@ -622,8 +802,18 @@ fn negamax(
             break; // beta cutoff
         }
     }
+    if !has_legal_move {
+        return if in_check {
+            // mated: worse the closer to the root it happens
+            -(MATE_SCORE - i32::from(ply))
+        } else {
+            0 // stalemate
+        };
+    }
 
-    // Classify the result relative to the original window and cache it.
+    // Classify the result relative to the original window and cache it. A
+    // root pass with excluded moves did not search the whole position, so its
+    // result must not stand in for the root's.
     let bound = if best_score <= alpha_orig {
         Bound::Upper
     } else if best_score >= beta {
@@ -631,13 +821,15 @@ fn negamax(
     } else {
         Bound::Exact
     };
-    ctx.tt.store(
-        board.zobrist_key,
-        best_move,
-        score_to_tt(best_score, ply) as i16,
-        depth,
-        bound,
-    );
+    if ply > 0 || ctx.excluded_root_moves.is_empty() {
+        ctx.tt.store(
+            board.zobrist_key,
+            best_move,
+            score_to_tt(best_score, ply) as i16,
+            depth,
+            bound,
+        );
+    }
 
     best_score
 }
@@ -666,8 +858,9 @@ fn score_to_tt(score: i32, ply: u8) -> i32 {
     }
 }
 
-/// Quiescence search: extend the search through captures and promotions so
-/// that the static evaluation is only applied to quiet positions. Hopeless
+/// Quiescence search: extend the search through captures and queen promotions
+/// (only those are [generated](Board::generate_captures)) so that the static
+/// evaluation is only applied to quiet positions. Hopeless
 /// captures are skipped by delta pruning and by a negative static exchange
 /// evaluation ([`see`]).
 ///
@@ -707,7 +900,7 @@ fn quiescence(
     let mut stand_pat = -INFINITY;
 
     if !in_check {
-        stand_pat = evaluate(board);
+        stand_pat = evaluate_cached(board, &mut ctx.pawn_table);
         if stand_pat >= beta {
             return stand_pat;
         }
@@ -717,16 +910,17 @@ fn quiescence(
         }
     }
 
-    let mut moves = board.generate_moves(board.turn);
-    if in_check {
+    let mut moves = if in_check {
         // every evasion is searched: the position stays tactical until the
         // check is resolved
-        if moves.is_empty() {
+        let evasions = board.generate_evasions(board.turn);
+        if evasions.is_empty() {
             return -(MATE_SCORE - i32::from(ply));
         }
+        evasions
     } else {
-        moves.retain(|&m| is_tactical(board, m));
-    }
+        board.generate_captures(board.turn)
+    };
     order_moves(
         board,
         &mut moves,
@@ -801,6 +995,57 @@ fn quiescence(
     best_score
 }
 
+/// Never set: quiet-position checks run their quiescence searches to the end.
+static NEVER_STOP: AtomicBool = AtomicBool::new(false);
+/// Where quiet-position checks flush their node counts; nothing reads it.
+static QUIET_CHECK_NODES: AtomicU64 = AtomicU64::new(0);
+
+/// Tells *quiet* positions apart: the side to move is not in check and the
+/// quiescence search finds no capture or promotion that improves on the
+/// static evaluation. Used to pick positions the static evaluation can be
+/// trained on.
+///
+/// The search context, with its pawn hash table, is built once and reused by
+/// every check, so a worker can call [`is_quiet`](Self::is_quiet) once per
+/// move without allocating.
+pub(crate) struct QuietCheck<'a> {
+    ctx: SearchContext<'a>,
+}
+
+impl<'a> QuietCheck<'a> {
+    /// A checker whose quiescence searches use `tt`. The table should serve
+    /// only these checks: one shared with a full search would hand back
+    /// deeper scores than the quiescence search's own.
+    pub(crate) fn new(tt: &'a TranspositionTable) -> Self {
+        Self {
+            ctx: SearchContext {
+                stop: &NEVER_STOP,
+                deadline: None,
+                ponder: None,
+                max_nodes: None,
+                nodes: 0,
+                aborted: false,
+                tt,
+                pawn_table: PawnTable::new(),
+                shared_nodes: &QUIET_CHECK_NODES,
+                order_noise: 0,
+                killers: [[None; 2]; MAX_PLY],
+                history: Box::new([[0; 64]; 64]),
+                excluded_root_moves: Vec::new(),
+            },
+        }
+    }
+
+    /// Returns `true` if `board` is quiet.
+    pub(crate) fn is_quiet(&mut self, board: &mut Board) -> bool {
+        if board.in_check(board.turn) {
+            return false;
+        }
+        let static_eval = evaluate_cached(board, &mut self.ctx.pawn_table);
+        quiescence(board, 0, -INFINITY, INFINITY, &mut self.ctx) == static_eval
+    }
+}
+
 /// Returns `true` if the side to move has any piece besides pawns and the
 /// king. Null-move pruning is unsound without one: pawn/king endgames are
 /// where zugzwang (every move loses) is common.
@@ -938,7 +1183,7 @@ fn see(board: &Board, mv: Move) -> i32 {
 
 /// Returns `true` if `mv` is a capture, promotion, or en passant — the moves
 /// the quiescence search extends through.
-fn is_tactical(board: &Board, mv: Move) -> bool {
+pub(crate) fn is_tactical(board: &Board, mv: Move) -> bool {
     match mv.get_special_move() {
         SpecialMove::Promotion | SpecialMove::EnPassant => true,
         SpecialMove::Castle => false,
@@ -974,6 +1219,150 @@ const MAX_HISTORY_SCORE: i32 = 3_000;
 /// skipped when winning the victim plus this margin still cannot lift alpha.
 const DELTA_MARGIN: i32 = 200;
 
+/// The stages of a [`MovePicker`], in the order it passes through them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out a node's moves one at a time, best first, generating each
+/// category only once the previous ones are used up: the transposition-table
+/// move (before anything is generated), captures and queen promotions that do
+/// not lose material by MVV-LVA, the killer moves and then the other quiet
+/// moves by history, and last the captures that lose material by [`see`].
+/// A node that cuts off early — most often on the TT move — never generates
+/// or orders the rest.
+///
+/// The TT move and the killers come from other positions (a key collision, a
+/// sibling node), so each is checked with [`Board::is_legal`] before it is
+/// handed out, and skipped when its category is generated later.
+struct MovePicker {
+    stage: Stage,
+    tt_move: Option<Move>,
+    /// The killers, each cleared once found not to be a legal quiet move here.
+    killers: [Option<Move>; 2],
+    /// The next killer the `Killers` stage tries.
+    killer: usize,
+    noise: u64,
+    /// The stage's generated, ordered moves, with `next` the one to hand out.
+    moves: MoveList,
+    next: usize,
+    /// Losing captures, deferred until after the quiet moves.
+    bad_captures: MoveList,
+}
+
+impl MovePicker {
+    /// A picker trying `tt_move` first and `killers` ahead of the other quiet
+    /// moves; `noise` is the worker's ordering jitter (see [`order_jitter`]).
+    const fn new(tt_move: Option<Move>, killers: [Option<Move>; 2], noise: u64) -> Self {
+        Self {
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            killer: 0,
+            noise,
+            moves: MoveList::new(),
+            next: 0,
+            bad_captures: MoveList::new(),
+        }
+    }
+
+    /// The next move to search, or `None` once every legal move has been
+    /// handed out. `history` ranks the quiet moves.
+    fn next(&mut self, board: &mut Board, history: &[[i32; 64]; 64]) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    self.tt_move = self.tt_move.filter(|&mv| board.is_legal(mv));
+                    if self.tt_move.is_some() {
+                        return self.tt_move;
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.moves = board.generate_captures(board.turn);
+                    self.order(board, history);
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    while let Some(mv) = self.take() {
+                        if mv.get_special_move() == SpecialMove::NormalMove && see(board, mv) < 0 {
+                            self.bad_captures.push(mv);
+                        } else {
+                            return Some(mv);
+                        }
+                    }
+                    self.stage = Stage::Killers;
+                }
+                Stage::Killers => {
+                    while let Some(&killer) = self.killers.get(self.killer) {
+                        let slot = self.killer;
+                        self.killer += 1;
+                        let killer = killer.filter(|&mv| {
+                            self.tt_move != Some(mv)
+                                && (slot == 0 || self.killers[0] != Some(mv))
+                                && !is_tactical(board, mv)
+                                && board.is_legal(mv)
+                        });
+                        self.killers[slot] = killer;
+                        if killer.is_some() {
+                            return killer;
+                        }
+                    }
+                    self.stage = Stage::GenerateQuiets;
+                }
+                Stage::GenerateQuiets => {
+                    self.moves = board.generate_quiets(board.turn);
+                    self.order(board, history);
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    if let Some(mv) = self.take() {
+                        return Some(mv);
+                    }
+                    self.moves = std::mem::take(&mut self.bad_captures);
+                    self.next = 0;
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if let Some(mv) = self.take() {
+                        return Some(mv);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Scores and sorts the freshly generated `moves` and rewinds to the first.
+    fn order(&mut self, board: &Board, history: &[[i32; 64]; 64]) {
+        order_moves(board, &mut self.moves, None, self.noise, [None; 2], history);
+        self.next = 0;
+    }
+
+    /// Hands out the stage's next move, skipping the already searched TT move
+    /// and, among the quiet moves, the killers.
+    fn take(&mut self) -> Option<Move> {
+        while let Some(&mv) = self.moves.get(self.next) {
+            self.next += 1;
+            let searched = self.tt_move == Some(mv)
+                || (self.stage == Stage::Quiets && self.killers.contains(&Some(mv)));
+            if !searched {
+                return Some(mv);
+            }
+        }
+        None
+    }
+}
+
 /// Order moves so the most forcing ones are searched first: the
 /// transposition-table move, then promotions and captures (MVV-LVA: most
 /// valuable victim, least valuable attacker), then the ply's killer moves,
@@ -982,25 +1371,19 @@ const DELTA_MARGIN: i32 = 200;
 /// diverge.
 fn order_moves(
     board: &Board,
-    moves: &mut [Move],
+    moves: &mut MoveList,
     tt_move: Option<Move>,
     noise: u64,
     killers: [Option<Move>; 2],
     history: &[[i32; 64]; 64],
 ) {
-    let mut scored: Vec<(i32, Move)> = moves
-        .iter()
-        .map(|&mv| {
-            (
-                move_order_score(board, mv, tt_move, killers, history) + order_jitter(noise, mv),
-                mv,
-            )
-        })
-        .collect();
-    scored.sort_by_key(|(score, _)| -*score);
-    for (slot, (_, mv)) in moves.iter_mut().zip(scored) {
-        *slot = mv;
+    for index in 0..moves.len() {
+        let mv = moves[index];
+        let score =
+            move_order_score(board, mv, tt_move, killers, history) + order_jitter(noise, mv);
+        moves.set_score(index, score);
     }
+    moves.sort_by_score();
 }
 
 /// A small (`0..64`) per-thread perturbation, derived from the worker's `noise`
@@ -1040,7 +1423,12 @@ fn move_order_score(
         }
         _ => (),
     }
-    let victim = board.get_piece_type_containing_position(mv.get_dest());
+    // a king-takes-rook castle (Chess960) targets its own rook: still quiet
+    let victim = if mv.get_special_move() == SpecialMove::Castle {
+        Piece::None
+    } else {
+        board.get_piece_type_containing_position(mv.get_dest())
+    };
     if victim != Piece::None {
         let attacker = board.get_piece_type_containing_position(mv.get_origin());
         score += 8_000 + piece_value(victim) * 10 - piece_value(attacker);
@@ -1060,8 +1448,20 @@ fn move_order_score(
 
 #[cfg(test)]
 mod tests {
-    use super::see;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    use super::{
+        ABORT_CHECK_INTERVAL, MATE_SCORE, MATE_THRESHOLD, MovePicker, QuietCheck, SearchLimits,
+        SearchResult, is_tactical, search_position, see,
+    };
     use crate::chess_engine::board::Board;
+    use crate::chess_engine::engine::time_manager::{PonderClock, TimeBudget};
+    use crate::chess_engine::engine::transposition::TranspositionTable;
+    use crate::chess_engine::fen_parser::START_POS_FEN;
+    use crate::chess_engine::moves::Move;
+    use crate::chess_engine::position::Position;
     use crate::chess_engine::utils::init_tables;
 
     /// SEE of the legal move `mv` (UCI notation) in `fen`.
@@ -1076,6 +1476,21 @@ mod tests {
         see(&board, mv)
     }
 
+    #[test]
+    fn quiet_positions_have_nothing_to_capture() {
+        init_tables();
+        let tt = TranspositionTable::with_size_mb(1);
+        let mut check = QuietCheck::new(&tt);
+        let mut quiet = |fen: &str| check.is_quiet(&mut Board::from_fen(fen).unwrap());
+        assert!(quiet(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        ));
+        // a queen hangs to the rook
+        assert!(!quiet("k7/8/8/3q4/8/8/3R4/K7 w - - 0 1"));
+        // in check
+        assert!(!quiet("k7/8/8/8/8/8/1q6/K7 w - - 0 1"));
+    }
+
     #[test]
     fn see_capturing_an_undefended_piece_wins_its_value() {
         // rook takes a queen nobody defends
@@ -1100,4 +1515,223 @@ mod tests {
         // exchange up through the first: win a whole rook
         assert!(see_of("k7/8/8/3r4/8/8/3R4/K2R4 w - - 0 1", "d2d5") >= 500);
     }
+
+    /// Searches `fen` to `depth` with `multi_pv` lines on a fresh table.
+    fn multi_pv_search(fen: &str, depth: u8, multi_pv: usize) -> SearchResult {
+        init_tables();
+        let mut board = Board::from_fen(fen).unwrap();
+        let limits = SearchLimits {
+            multi_pv,
+            ..SearchLimits::depth(depth)
+        };
+        let stop = AtomicBool::new(false);
+        search_position(
+            &mut board,
+            &limits,
+            &stop,
+            &TranspositionTable::new(),
+            1,
+            false,
+        )
+    }
+
+    #[test]
+    fn multi_pv_returns_distinct_root_moves_best_first() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let result = multi_pv_search(fen, 5, 4);
+        assert_eq!(result.lines.len(), 4);
+        assert_eq!(result.lines[0].pv, result.pv);
+        assert_eq!(result.lines[0].score, result.score);
+        assert!(result.lines.windows(2).all(|w| w[0].score >= w[1].score));
+
+        let mut roots: Vec<_> = result.lines.iter().map(|line| line.pv[0]).collect();
+        roots.dedup();
+        assert_eq!(roots.len(), 4);
+
+        // the single-line search agrees on the top score
+        assert_eq!(multi_pv_search(fen, 5, 1).score, result.score);
+    }
+
+    #[test]
+    fn multi_pv_finds_the_mate_and_the_alternatives() {
+        // Ra8# mates; every other line scores far below it
+        let result = multi_pv_search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3, 3);
+        assert_eq!(result.lines[0].pv[0].to_string(), "a1a8");
+        assert!(result.lines[0].score >= MATE_THRESHOLD);
+        assert!(
+            result.lines[1..]
+                .iter()
+                .all(|line| line.score < MATE_THRESHOLD)
+        );
+    }
+
+    #[test]
+    fn multi_pv_is_capped_by_the_number_of_legal_moves() {
+        // the lone king has three moves
+        let result = multi_pv_search("7k/8/8/8/8/8/8/K7 b - - 0 1", 3, 10);
+        assert_eq!(result.lines.len(), 3);
+    }
+
+    /// Single-threaded search of `fen` under `limits`.
+    fn search_with(fen: &str, limits: &SearchLimits) -> SearchResult {
+        init_tables();
+        let mut board = Board::from_fen(fen).unwrap();
+        let stop = AtomicBool::new(false);
+        search_position(
+            &mut board,
+            limits,
+            &stop,
+            &TranspositionTable::new(),
+            1,
+            false,
+        )
+    }
+
+    #[test]
+    fn node_budget_is_reproducible() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let first = search_with(fen, &SearchLimits::node_budget(50_000));
+        let second = search_with(fen, &SearchLimits::node_budget(50_000));
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.pv, second.pv);
+        assert_eq!(first.score, second.score);
+        // polled every ABORT_CHECK_INTERVAL nodes, so it overshoots by less
+        assert!(first.nodes < 50_000 + ABORT_CHECK_INTERVAL);
+    }
+
+    #[test]
+    fn search_moves_restrict_the_root_on_every_thread() {
+        // Ra8# is the only sensible move, but it is not on the list
+        init_tables();
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let allowed: Vec<Move> = board
+            .generate_moves(board.turn)
+            .into_iter()
+            .filter(|mv| ["a1a2", "g1f1", "g1h1"].contains(&mv.to_string().as_str()))
+            .collect();
+        assert_eq!(allowed.len(), 3);
+        let limits = SearchLimits {
+            search_moves: allowed.clone(),
+            multi_pv: 5,
+            ..SearchLimits::depth(5)
+        };
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new();
+        let result = search_position(&mut board, &limits, &stop, &tt, 3, false);
+        assert!(allowed.contains(&result.best_move.unwrap()));
+        assert!(result.score < MATE_THRESHOLD);
+        // multi-PV is capped by the whitelist
+        assert_eq!(result.lines.len(), 3);
+
+        // a list without a legal move does not restrict the search
+        let limits = SearchLimits {
+            search_moves: vec![Move::new_default(Position::new(0), Position::new(63))],
+            ..SearchLimits::depth(3)
+        };
+        let result = search_with(fen, &limits);
+        assert_eq!(result.best_move.unwrap().to_string(), "a1a8");
+    }
+
+    #[test]
+    fn mate_search_stops_once_the_mate_is_proven() {
+        // Kb6 Kb8 Rh8#: mate in two
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let result = search_with(fen, &SearchLimits::mate(2));
+        assert_eq!(result.score, MATE_SCORE - 3);
+        assert!(result.depth < SearchLimits::mate(2).depth);
+
+        // asked for a mate in one, the longer mate does not end the search
+        let result = search_with(fen, &SearchLimits::mate(1));
+        assert_eq!(result.depth, SearchLimits::mate(1).depth);
+
+        // a mate in one is also a mate within two moves
+        let result = search_with("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &SearchLimits::mate(2));
+        assert_eq!(result.score, MATE_SCORE - 1);
+        assert_eq!(result.best_move.unwrap().to_string(), "a1a8");
+    }
+
+    #[test]
+    fn pondering_search_runs_until_its_clock_from_ponderhit_expires() {
+        let clock = Arc::new(PonderClock::new(TimeBudget {
+            soft: Some(Duration::from_millis(50)),
+            hard: Duration::from_millis(300),
+        }));
+        let limits = SearchLimits {
+            ponder: Some(Arc::clone(&clock)),
+            ..SearchLimits::infinite()
+        };
+        let start = Instant::now();
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(200));
+                clock.ponder_hit();
+            });
+            search_with(START_POS_FEN, &limits)
+        });
+        assert!(result.best_move.is_some());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn move_picker_hands_out_every_move_once_in_stages() {
+        init_tables();
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let legal = board.generate_moves(board.turn);
+        let find = |uci: &str| legal.iter().copied().find(|m| m.to_string() == uci);
+        let (tt_move, killer) = (find("a2a3"), find("b2b3"));
+        assert!(tt_move.is_some() && killer.is_some());
+
+        let history = [[0; 64]; 64];
+        let mut picker = MovePicker::new(tt_move, [killer, None], 0);
+        let mut order = Vec::new();
+        while let Some(mv) = picker.next(&mut board, &history) {
+            order.push(mv);
+        }
+
+        // every legal move exactly once
+        let mut sorted: Vec<u16> = order.iter().map(Move::get_raw).collect();
+        let mut expected: Vec<u16> = legal.iter().map(Move::get_raw).collect();
+        sorted.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(sorted, expected);
+
+        // the TT move, then the captures that hold material, then the killer
+        // leading the quiet moves, then the losing captures (Qxf6 among them)
+        assert_eq!(order[0], tt_move.unwrap());
+        let first_quiet = 1 + order[1..]
+            .iter()
+            .position(|&m| !is_tactical(&board, m))
+            .unwrap();
+        assert_eq!(order[first_quiet], killer.unwrap());
+        assert!(
+            order[1..first_quiet]
+                .iter()
+                .all(|&m| is_tactical(&board, m))
+        );
+        let losing = order.iter().position(|m| m.to_string() == "f3f6").unwrap();
+        assert!(see(&board, order[losing]) < 0);
+        assert!(order[losing..].iter().all(|&m| is_tactical(&board, m)));
+        assert!(
+            order[first_quiet..losing]
+                .iter()
+                .any(|&m| !is_tactical(&board, m))
+        );
+
+        // a TT move or killer from another position is never handed out: the
+        // rook on a1 is blocked by its own pawn. A killer that captures here
+        // waits for the captures, and comes out once.
+        let blocked = Move::new_default(Position::new(0), Position::new(56));
+        let capture = find("e5f7").unwrap();
+        let mut picker = MovePicker::new(Some(blocked), [Some(blocked), Some(capture)], 0);
+        let mut order = Vec::new();
+        while let Some(mv) = picker.next(&mut board, &history) {
+            assert_ne!(mv, blocked);
+            order.push(mv);
+        }
+        assert_eq!(order.len(), legal.len());
+        assert_eq!(order.iter().filter(|&&mv| mv == capture).count(), 1);
+    }
 }
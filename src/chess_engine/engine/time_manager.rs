@@ -0,0 +1,314 @@
+//! Time management: how long to think about a move under a clock.
+//!
+//! [`TimeBudget::from_clock`] splits the remaining time into two limits. The
+//! *soft* limit is the target: iterative deepening starts no new iteration
+//! once it has passed. The *hard* limit is a safety net that aborts the search
+//! mid-iteration. The clock is shared out over `movestogo` moves when the GUI
+//! sends it (a classical time control), or over an assumed
+//! `DEFAULT_MOVES_TO_GO` otherwise (sudden death, where the increment matters
+//! more), always keeping `MOVE_OVERHEAD` in reserve for I/O latency.
+//!
+//! While searching, a `TimeManager` stretches or shrinks the soft limit
+//! between iterations. A best move that keeps changing, or a score that drops
+//! from one iteration to the next, means the search has not settled and earns
+//! more time. A best move that stays put lets the search stop early.
+//!
+//! A `go ponder` search thinks on the opponent's time, so its clock only
+//! starts on `ponderhit`. It shares a [`PonderClock`] with the UCI thread,
+//! which marks the moment of the hit; from then on the running search obeys
+//! the clock's budget as if it had been started with it.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::chess_engine::moves::Move;
+
+/// Safety margin subtracted from the clock so the engine never flags because
+/// of I/O latency.
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/// Moves the remaining clock is assumed to cover when the GUI sends no
+/// `movestogo`.
+const DEFAULT_MOVES_TO_GO: u32 = 25;
+/// The hard limit is this many soft limits...
+const HARD_LIMIT_FACTOR: u32 = 3;
+/// ...but never more than this share of the usable clock, in permille.
+const HARD_LIMIT_MAX_PERMILLE: u32 = 750;
+
+/// Soft-limit scale for a perfectly stable best move, in permille.
+const STABLE_SCALE_PERMILLE: u32 = 700;
+/// Score drop (in centipawns) at which the extra time for a falling score
+/// stops growing.
+const MAX_SCORE_DROP: i32 = 100;
+/// Extra soft-limit permille per centipawn of score drop.
+const SCORE_DROP_PERMILLE_PER_CP: u32 = 5;
+
+/// The two time limits of a timed search, measured from its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBudget {
+    /// Target thinking time: no new iteration starts past it (after scaling by
+    /// the `TimeManager`). `None` means use the whole hard limit (`movetime`).
+    pub soft: Option<Duration>,
+    /// Absolute limit: the search aborts once it is reached.
+    pub hard: Duration,
+}
+
+impl TimeBudget {
+    /// Allocates time for one move from the side to move's `remaining` clock,
+    /// its `increment`, and the GUI's `movestogo`, if any.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use sabertooth::chess_engine::engine::time_manager::TimeBudget;
+    ///
+    /// let minute = Duration::from_secs(60);
+    /// let sudden_death = TimeBudget::from_clock(minute, Duration::ZERO, None);
+    /// let last_move = TimeBudget::from_clock(minute, Duration::ZERO, Some(1));
+    /// assert!(sudden_death.soft < last_move.soft);
+    /// assert!(sudden_death.soft.unwrap() < sudden_death.hard);
+    /// assert!(last_move.hard < minute);
+    /// ```
+    #[must_use]
+    pub fn from_clock(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Self {
+        let usable = remaining.saturating_sub(MOVE_OVERHEAD);
+        let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let floor = Duration::from_millis(1);
+
+        // an even share of the clock plus most of the increment, but never more
+        // than half of what is left
+        let soft = (usable / moves_to_go + increment * 3 / 4)
+            .min(usable / 2)
+            .max(floor);
+        let hard = (soft * HARD_LIMIT_FACTOR)
+            .min(usable * HARD_LIMIT_MAX_PERMILLE / 1000)
+            .max(soft);
+        Self {
+            soft: Some(soft),
+            hard,
+        }
+    }
+
+    /// A fixed thinking time (`go movetime`): searches until the time is up,
+    /// less `MOVE_OVERHEAD`.
+    #[must_use]
+    pub fn fixed(movetime: Duration) -> Self {
+        Self {
+            soft: None,
+            hard: movetime
+                .saturating_sub(MOVE_OVERHEAD)
+                .max(Duration::from_millis(1)),
+        }
+    }
+}
+
+/// The clock of a pondering search: a [`TimeBudget`] that starts counting
+/// only once [`ponder_hit`](Self::ponder_hit) is called.
+#[derive(Debug)]
+pub struct PonderClock {
+    budget: TimeBudget,
+    created: Instant,
+    /// Microseconds from `created` to the `ponderhit`; `u64::MAX` until then.
+    hit: AtomicU64,
+}
+
+impl PonderClock {
+    /// A stopped clock holding `budget`.
+    #[must_use]
+    pub fn new(budget: TimeBudget) -> Self {
+        Self {
+            budget,
+            created: Instant::now(),
+            hit: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// Starts the budget now. Later calls are ignored.
+    pub fn ponder_hit(&self) {
+        let elapsed = u64::try_from(self.created.elapsed().as_micros()).unwrap_or(u64::MAX - 1);
+        let _ = self
+            .hit
+            .compare_exchange(u64::MAX, elapsed, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    /// When the budget started, if it has.
+    fn started(&self) -> Option<Instant> {
+        let hit = self.hit.load(Ordering::Relaxed);
+        (hit != u64::MAX).then(|| self.created + Duration::from_micros(hit))
+    }
+
+    /// The hard deadline, once the budget has started.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.started().map(|started| started + self.budget.hard)
+    }
+
+    /// When the budget started and its soft limit, once it has started (and
+    /// if it has a soft limit).
+    pub(crate) fn soft(&self) -> Option<(Instant, Duration)> {
+        self.started().zip(self.budget.soft)
+    }
+}
+
+/// Tracks how settled the search is across iterations and decides when the
+/// scaled soft limit has been reached.
+pub(crate) struct TimeManager {
+    /// When the soft limit started counting, and its length; `None` while a
+    /// pondering search waits for `ponderhit`.
+    limit: Option<(Instant, Duration)>,
+    /// Decaying count of best-move changes, in permille: each completed
+    /// iteration halves it and a change adds 1000.
+    instability: u32,
+    /// How far the score fell in the last iteration, in centipawns (0 if it
+    /// did not).
+    score_drop: i32,
+    last: Option<(Option<Move>, i32)>,
+}
+
+impl TimeManager {
+    /// A manager for a search that started at `start` with soft limit `soft`.
+    pub(crate) const fn new(start: Instant, soft: Duration) -> Self {
+        Self {
+            limit: Some((start, soft)),
+            instability: 0,
+            score_drop: 0,
+            last: None,
+        }
+    }
+
+    /// A manager for a pondering search: it tracks stability from the start,
+    /// but has no soft limit until [`start`](Self::start) gives it one.
+    pub(crate) const fn pondering() -> Self {
+        Self {
+            limit: None,
+            instability: 0,
+            score_drop: 0,
+            last: None,
+        }
+    }
+
+    /// Sets the soft limit: `soft`, counted from `start`.
+    pub(crate) const fn start(&mut self, start: Instant, soft: Duration) {
+        self.limit = Some((start, soft));
+    }
+
+    /// Records a completed iteration's best move and score.
+    pub(crate) fn update(&mut self, best_move: Option<Move>, score: i32) {
+        self.instability /= 2;
+        self.score_drop = 0;
+        if let Some((last_move, last_score)) = self.last {
+            if last_move != best_move {
+                self.instability += 1000;
+            }
+            self.score_drop = (last_score - score).clamp(0, MAX_SCORE_DROP);
+        }
+        self.last = Some((best_move, score));
+    }
+
+    /// The soft limit scaled for the current stability: from 0.7× for a best
+    /// move that never changes, up past 2× for one that keeps changing, and up
+    /// to a further 1.5× while the score is falling. [`Duration::MAX`] while
+    /// there is no soft limit yet.
+    pub(crate) fn scaled_soft(&self) -> Duration {
+        let Some((_, soft)) = self.limit else {
+            return Duration::MAX;
+        };
+        let stability = STABLE_SCALE_PERMILLE + self.instability;
+        let drop = 1000 + self.score_drop.unsigned_abs() * SCORE_DROP_PERMILLE_PER_CP;
+        soft * stability / 1000 * drop / 1000
+    }
+
+    /// Whether the search should start no further iterations.
+    pub(crate) fn should_stop(&self) -> bool {
+        self.limit
+            .is_some_and(|(start, _)| start.elapsed() >= self.scaled_soft())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{MOVE_OVERHEAD, PonderClock, TimeBudget, TimeManager};
+    use crate::chess_engine::moves::Move;
+    use crate::chess_engine::position::Position;
+
+    #[test]
+    fn movestogo_shares_the_clock_over_the_remaining_moves() {
+        let clock = Duration::from_secs(40);
+        let usable = clock.saturating_sub(MOVE_OVERHEAD);
+        let forty = TimeBudget::from_clock(clock, Duration::ZERO, Some(40));
+        let ten = TimeBudget::from_clock(clock, Duration::ZERO, Some(10));
+        assert_eq!(forty.soft, Some(usable / 40));
+        assert_eq!(ten.soft, Some(usable / 10));
+        for budget in [forty, ten] {
+            assert!(budget.hard >= budget.soft.unwrap());
+            assert!(budget.hard <= clock * 3 / 4);
+        }
+    }
+
+    #[test]
+    fn increment_adds_time_and_tiny_clocks_stay_positive() {
+        let clock = Duration::from_secs(10);
+        let plain = TimeBudget::from_clock(clock, Duration::ZERO, None);
+        let with_inc = TimeBudget::from_clock(clock, Duration::from_secs(1), None);
+        assert!(with_inc.soft > plain.soft);
+
+        let flagging = TimeBudget::from_clock(Duration::from_millis(5), Duration::ZERO, None);
+        assert!(flagging.hard >= Duration::from_millis(1));
+        assert_eq!(
+            TimeBudget::fixed(Duration::from_millis(10)).hard,
+            Duration::from_millis(1)
+        );
+    }
+
+    #[test]
+    fn instability_and_score_drops_extend_the_soft_limit() {
+        let soft = Duration::from_secs(1);
+        let a = Some(Move::new_default(Position::new(12), Position::new(28)));
+        let b = Some(Move::new_default(Position::new(11), Position::new(27)));
+
+        let mut stable = TimeManager::new(Instant::now(), soft);
+        for _ in 0..6 {
+            stable.update(a, 20);
+        }
+        assert!(stable.scaled_soft() < soft);
+
+        let mut flipping = TimeManager::new(Instant::now(), soft);
+        for i in 0..6 {
+            flipping.update(if i % 2 == 0 { a } else { b }, 20);
+        }
+        assert!(flipping.scaled_soft() > soft * 2);
+
+        let mut falling = TimeManager::new(Instant::now(), soft);
+        falling.update(a, 50);
+        falling.update(a, -50);
+        assert!(falling.scaled_soft() > soft);
+    }
+
+    #[test]
+    fn pondering_clock_starts_on_ponderhit() {
+        let budget = TimeBudget {
+            soft: Some(Duration::from_secs(1)),
+            hard: Duration::from_secs(3),
+        };
+        let clock = PonderClock::new(budget);
+        assert_eq!(clock.deadline(), None);
+        assert_eq!(clock.soft(), None);
+
+        clock.ponder_hit();
+        let (started, soft) = clock.soft().unwrap();
+        assert_eq!(soft, budget.soft.unwrap());
+        assert_eq!(clock.deadline(), Some(started + budget.hard));
+        clock.ponder_hit();
+        assert_eq!(clock.soft().unwrap().0, started);
+
+        // the manager tracks stability while pondering but never stops early
+        let a = Some(Move::new_default(Position::new(12), Position::new(28)));
+        let mut manager = TimeManager::pondering();
+        manager.update(a, 20);
+        assert!(!manager.should_stop());
+        let two_seconds_ago = Instant::now().checked_sub(Duration::from_secs(2)).unwrap();
+        manager.start(two_seconds_ago, soft);
+        manager.update(a, 20);
+        assert!(manager.should_stop());
+    }
+}
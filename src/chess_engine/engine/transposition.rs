@@ -1,6 +1,9 @@
 //! A lock-free transposition table: a fixed-size cache of search results keyed
 //! by the board's Zobrist hash.
 //!
+//! The size is chosen in MiB at construction (the UCI `Hash` option) and can be
+//! changed between searches with [`resize`](TranspositionTable::resize).
+//!
 //! Each searched position records its score, the depth it was searched to, a
 //! [`Bound`] describing whether that score is exact or only a bound (from an
 //! alpha-beta cutoff), and the best move found. Re-reaching the same position —
@@ -8,6 +11,18 @@
 //! the game — can then reuse the result to cut the subtree off, and can always
 //! reuse the stored move to improve move ordering.
 //!
+//! # Buckets and replacement
+//!
+//! A key selects a *bucket* of four slots sharing one 64-byte cache line, and
+//! the position may live in any of them, so a probe costs a single cache miss
+//! and a deep entry is not evicted just because a shallow one hashes next to it.
+//! A store overwrites the position's own slot when it is present; otherwise it
+//! takes an empty slot, or evicts the least valuable entry — the shallowest,
+//! with every search generation of age counting as
+//! `AGE_DEPTH_PENALTY` plies of depth, so stale entries from earlier searches go
+//! first. [`hashfull`](TranspositionTable::hashfull) samples how much of the
+//! table the current search has filled, for the UCI `info` line.
+//!
 //! # Concurrency
 //!
 //! The table is shared (via `Arc`) between the search thread and the UCI thread,
@@ -29,11 +44,21 @@ use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
 
 use crate::chess_engine::moves::Move;
 
-/// Number of slots in the table. `2^22` slots × 16 bytes/slot = 64 MiB. A power
-/// of two so the index is a cheap `key & MASK`.
-const TABLE_SIZE: usize = 1 << 22;
-/// Mask turning a Zobrist key into a slot index.
-const INDEX_MASK: u64 = (TABLE_SIZE as u64) - 1;
+/// Table size used by [`TranspositionTable::new`]: `2^20` buckets × 64
+/// bytes/bucket.
+pub const DEFAULT_HASH_MB: usize = 64;
+/// Smallest table size accepted, in MiB.
+pub const MIN_HASH_MB: usize = 1;
+/// Largest table size accepted, in MiB.
+pub const MAX_HASH_MB: usize = 32_768;
+
+/// Slots per bucket: four 16-byte slots fill a 64-byte cache line.
+const BUCKET_SLOTS: usize = 4;
+/// How many plies of depth one generation of age is worth when choosing which
+/// entry of a full bucket to evict.
+const AGE_DEPTH_PENALTY: i32 = 8;
+/// Number of leading slots [`TranspositionTable::hashfull`] samples.
+const HASHFULL_SAMPLE: usize = 1000;
 
 /// Whether a stored score is exact or merely a bound from an alpha-beta cutoff.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -96,6 +121,28 @@ impl Slot {
             data: AtomicU64::new(0),
         }
     }
+
+    /// Loads the slot's data word, or `None` unless it validates against `key`.
+    fn load_matching(&self, key: u64) -> Option<u64> {
+        let data = self.data.load(Ordering::Relaxed);
+        let stored_key = self.key.load(Ordering::Relaxed);
+        // Lockless validation: a consistent entry satisfies stored_key ^ data == key.
+        (stored_key ^ data == key).then_some(data)
+    }
+}
+
+/// A cache-line-aligned group of slots that one key may occupy.
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; BUCKET_SLOTS],
+}
+
+impl Bucket {
+    const fn empty() -> Self {
+        Self {
+            slots: [const { Slot::empty() }; BUCKET_SLOTS],
+        }
+    }
 }
 
 // Data-word bit layout:
@@ -131,29 +178,85 @@ const fn unpack_generation(data: u64) -> u8 {
     (data >> GEN_SHIFT) as u8
 }
 
-/// A fixed-size, lock-free transposition table.
+/// The number of buckets fitting in `mb` MiB (clamped to
+/// `MIN_HASH_MB..=MAX_HASH_MB`), rounded down to a power of two so the index is
+/// a cheap `key & mask`.
+const fn bucket_count(mb: usize) -> usize {
+    let mb = if mb < MIN_HASH_MB {
+        MIN_HASH_MB
+    } else if mb > MAX_HASH_MB {
+        MAX_HASH_MB
+    } else {
+        mb
+    };
+    let buckets = mb * (1 << 20) / size_of::<Bucket>();
+    1 << (usize::BITS - 1 - buckets.leading_zeros())
+}
+
+/// Allocates `count` empty buckets.
+fn empty_buckets(count: usize) -> Vec<Bucket> {
+    (0..count).map(|_| Bucket::empty()).collect()
+}
+
+/// A fixed-size, lock-free, bucketed transposition table.
 pub struct TranspositionTable {
-    slots: Vec<Slot>,
+    buckets: Vec<Bucket>,
+    /// Mask turning a Zobrist key into a bucket index (`buckets.len() - 1`).
+    index_mask: u64,
     /// Bumped once per search so entries from earlier searches can be preferred
     /// for replacement.
     generation: AtomicU8,
 }
 
 impl TranspositionTable {
-    /// Allocates a fresh 64 MiB table with all slots empty.
+    /// Allocates a fresh table of [`DEFAULT_HASH_MB`] MiB with all slots empty.
     #[must_use]
     pub fn new() -> Self {
-        let slots = (0..TABLE_SIZE).map(|_| Slot::empty()).collect();
+        Self::with_size_mb(DEFAULT_HASH_MB)
+    }
+
+    /// Allocates a fresh, empty table of at most `mb` MiB: the size is clamped to
+    /// `MIN_HASH_MB..=MAX_HASH_MB` and rounded down to a power-of-two bucket
+    /// count.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::engine::transposition::TranspositionTable;
+    ///
+    /// assert_eq!(TranspositionTable::with_size_mb(16).size_mb(), 16);
+    /// assert_eq!(TranspositionTable::with_size_mb(24).size_mb(), 16);
+    /// ```
+    #[must_use]
+    pub fn with_size_mb(mb: usize) -> Self {
+        let count = bucket_count(mb);
         Self {
-            slots,
+            buckets: empty_buckets(count),
+            index_mask: count as u64 - 1,
             generation: AtomicU8::new(0),
         }
     }
 
+    /// Reallocates the table at `mb` MiB (clamped and rounded as in
+    /// [`with_size_mb`](Self::with_size_mb)), discarding every entry. Taking
+    /// `&mut self` guarantees no search is using the table meanwhile.
+    pub fn resize(&mut self, mb: usize) {
+        let count = bucket_count(mb);
+        // free the old table before allocating the new one to bound peak memory
+        self.buckets = Vec::new();
+        self.buckets = empty_buckets(count);
+        self.index_mask = count as u64 - 1;
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// The table's size in MiB.
+    #[must_use]
+    pub const fn size_mb(&self) -> usize {
+        self.buckets.len() * size_of::<Bucket>() / (1 << 20)
+    }
+
     /// Empties every slot. Safe to call while a search holds a shared reference;
     /// intended for `ucinewgame`.
     pub fn clear(&self) {
-        for slot in &self.slots {
+        for slot in self.buckets.iter().flat_map(|bucket| &bucket.slots) {
             slot.key.store(0, Ordering::Relaxed);
             slot.data.store(0, Ordering::Relaxed);
         }
@@ -166,21 +269,18 @@ impl TranspositionTable {
     }
 
     #[allow(clippy::cast_possible_truncation)]
-    const fn index(key: u64) -> usize {
-        (key & INDEX_MASK) as usize
+    const fn index(&self, key: u64) -> usize {
+        (key & self.index_mask) as usize
     }
 
     /// Looks up `key`. Returns `None` on a miss or a detected torn read.
     #[allow(clippy::cast_possible_truncation)]
     #[must_use]
     pub fn probe(&self, key: u64) -> Option<TtData> {
-        let slot = &self.slots[Self::index(key)];
-        let data = slot.data.load(Ordering::Relaxed);
-        let stored_key = slot.key.load(Ordering::Relaxed);
-        // Lockless validation: a consistent entry satisfies stored_key ^ data == key.
-        if stored_key ^ data != key {
-            return None;
-        }
+        let data = self.buckets[self.index(key)]
+            .slots
+            .iter()
+            .find_map(|slot| slot.load_matching(key))?;
         let mv_raw = data as u16;
         let mv = if mv_raw == 0 {
             None
@@ -198,28 +298,78 @@ impl TranspositionTable {
 
     /// Records the result of searching the position with the given `key`.
     ///
-    /// Uses a depth-preferred replacement policy with aging: an existing entry is
-    /// overwritten only if it is empty, comes from an earlier generation, or was
-    /// searched no deeper than this one.
+    /// If the position already has a slot in its bucket, that slot is updated —
+    /// unless it holds a deeper result from this search and the new one is not
+    /// exact — and an update without a move keeps the stored one. Otherwise the
+    /// new entry takes an empty slot or evicts the entry with the lowest
+    /// depth-minus-age value (see the [module docs](self)).
+    #[allow(clippy::cast_possible_truncation)]
     pub fn store(&self, key: u64, mv: Option<Move>, score: i16, depth: u8, bound: Bound) {
-        let slot = &self.slots[Self::index(key)];
+        let bucket = &self.buckets[self.index(key)];
         let generation = self.generation.load(Ordering::Relaxed);
 
-        let existing = slot.data.load(Ordering::Relaxed);
-        let replace = existing == 0
-            || unpack_generation(existing) != generation
-            || depth >= unpack_depth(existing);
-        if !replace {
-            return;
+        let mut victim = &bucket.slots[0];
+        let mut victim_worth = i32::MAX;
+        for slot in &bucket.slots {
+            if let Some(existing) = slot.load_matching(key) {
+                let keep = unpack_generation(existing) == generation
+                    && bound != Bound::Exact
+                    && depth < unpack_depth(existing);
+                if keep {
+                    return;
+                }
+                // a store without a move keeps the one already known
+                let mv = mv.or_else(|| {
+                    let raw = existing as u16;
+                    (raw != 0).then(|| Move::make_raw(raw))
+                });
+                Self::write(slot, key, pack(mv, score, depth, bound, generation));
+                return;
+            }
+
+            let data = slot.data.load(Ordering::Relaxed);
+            let worth = if data == 0 {
+                i32::MIN
+            } else {
+                let age = generation.wrapping_sub(unpack_generation(data));
+                i32::from(unpack_depth(data)) - AGE_DEPTH_PENALTY * i32::from(age)
+            };
+            if worth < victim_worth {
+                victim = slot;
+                victim_worth = worth;
+            }
         }
+        Self::write(victim, key, pack(mv, score, depth, bound, generation));
+    }
 
-        let data = pack(mv, score, depth, bound, generation);
+    /// Publishes `data` for `key` into `slot`.
+    fn write(slot: &Slot, key: u64, data: u64) {
         // Store data first, then key = zobrist ^ data, so a concurrent reader that
         // sees the new key also sees the matching data (and otherwise fails the
         // XOR check).
         slot.data.store(data, Ordering::Relaxed);
         slot.key.store(key ^ data, Ordering::Relaxed);
     }
+
+    /// How full the table is with entries from the current search, in permille,
+    /// estimated from the first `HASHFULL_SAMPLE` slots (the UCI `hashfull`
+    /// field).
+    #[must_use]
+    pub fn hashfull(&self) -> u16 {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let used = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| &bucket.slots)
+            .take(HASHFULL_SAMPLE)
+            .filter(|slot| {
+                let data = slot.data.load(Ordering::Relaxed);
+                data != 0 && unpack_generation(data) == generation
+            })
+            .count();
+        // HASHFULL_SAMPLE is 1000, so the count is already in permille
+        u16::try_from(used).unwrap_or(u16::MAX)
+    }
 }
 
 impl Default for TranspositionTable {
@@ -299,6 +449,104 @@ mod tests {
         assert!(tt.probe(key).is_none());
     }
 
+    #[test]
+    fn sizes_round_down_to_a_power_of_two_within_bounds() {
+        assert_eq!(size_of::<Bucket>(), 64);
+        assert_eq!(bucket_count(1), 1 << 14);
+        assert_eq!(bucket_count(DEFAULT_HASH_MB), 1 << 20);
+        assert_eq!(bucket_count(3), 1 << 15);
+        assert_eq!(bucket_count(0), bucket_count(MIN_HASH_MB));
+        assert_eq!(bucket_count(usize::MAX), bucket_count(MAX_HASH_MB));
+    }
+
+    /// `count` distinct keys that all map to the bucket of `base`.
+    fn same_bucket_keys(base: u64, count: u64) -> Vec<u64> {
+        (1..=count).map(|i| base ^ (i << 40)).collect()
+    }
+
+    #[test]
+    fn deep_entry_survives_shallow_collisions() {
+        let tt = TranspositionTable::with_size_mb(1);
+        tt.new_generation();
+        let deep = 0x1234_0000_0000_0042;
+        tt.store(deep, Some(sample_move()), 55, 20, Bound::Exact);
+        for key in same_bucket_keys(deep, 10) {
+            tt.store(key, None, 1, 1, Bound::Upper);
+        }
+        assert_eq!(tt.probe(deep).unwrap().depth, 20);
+        // the bucket still holds the most recent shallow entry too
+        assert!(
+            tt.probe(*same_bucket_keys(deep, 10).last().unwrap())
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn stale_generations_are_evicted_first() {
+        let tt = TranspositionTable::with_size_mb(1);
+        tt.new_generation();
+        let base = 0x5555_0000_0000_0007;
+        let old = same_bucket_keys(base, 4);
+        for &key in &old {
+            tt.store(key, None, 0, 12, Bound::Lower);
+        }
+        // many searches later a shallow entry still displaces an old deep one
+        for _ in 0..4 {
+            tt.new_generation();
+        }
+        tt.store(base, None, 0, 2, Bound::Lower);
+        assert!(tt.probe(base).is_some());
+        assert_eq!(
+            old.iter().filter(|&&key| tt.probe(key).is_some()).count(),
+            3
+        );
+    }
+
+    #[test]
+    fn same_position_updates_in_place_and_keeps_its_move() {
+        let tt = TranspositionTable::with_size_mb(1);
+        tt.new_generation();
+        let key = 0x7777_0000_0000_0003;
+        tt.store(key, Some(sample_move()), 10, 4, Bound::Lower);
+        // a shallower bound from the same search is ignored
+        tt.store(key, None, -5, 2, Bound::Upper);
+        assert_eq!(tt.probe(key).unwrap().score, 10);
+        // a deeper move-less result replaces it but inherits the move
+        tt.store(key, None, 30, 6, Bound::Upper);
+        let got = tt.probe(key).unwrap();
+        assert_eq!((got.score, got.depth, got.mv), (30, 6, Some(sample_move())));
+    }
+
+    #[test]
+    fn hashfull_counts_only_the_current_generation() {
+        let tt = TranspositionTable::with_size_mb(1);
+        assert_eq!(tt.hashfull(), 0);
+        tt.new_generation();
+        // fill the first 100 buckets (400 of the 1000 sampled slots)
+        for bucket in 0..100 {
+            for key in same_bucket_keys(bucket, 4) {
+                tt.store(key, None, 0, 3, Bound::Exact);
+            }
+        }
+        assert_eq!(tt.hashfull(), 400);
+        tt.new_generation();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn resize_changes_size_and_discards_entries() {
+        let mut tt = TranspositionTable::with_size_mb(1);
+        assert_eq!(tt.size_mb(), 1);
+        let key = 0x0F0F_0F0F_0F0F_0F0F;
+        tt.store(key, Some(sample_move()), 12, 6, Bound::Exact);
+
+        tt.resize(4);
+        assert_eq!(tt.size_mb(), 4);
+        assert!(tt.probe(key).is_none());
+        tt.store(key, Some(sample_move()), 12, 6, Bound::Exact);
+        assert_eq!(tt.probe(key).unwrap().score, 12);
+    }
+
     #[test]
     fn no_move_stored_as_none() {
         let tt = TranspositionTable::new();
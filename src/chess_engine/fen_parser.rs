@@ -1,13 +1,35 @@
-//! [Forsyth–Edwards Notation][fen] parsing for [`Board`].
+//! [Forsyth–Edwards Notation][fen] parsing and writing for [`Board`].
 //!
 //! A FEN string has six space-separated fields: piece placement (rank 8 first),
 //! side to move, castling rights, en-passant target, half-move clock, and
-//! full-move number. This parser requires all six fields to be present.
+//! full-move number. [`Board::from_fen`] requires all six fields to be
+//! present, and the writer ([`Board::to_fen`], or the [`Display`](fmt::Display)
+//! impl) always emits all six, so `from_fen(to_fen(b))` reproduces `b`.
+//! [`Board::from_fen_lenient`] also takes the four- and five-field FENs GUIs
+//! and EPD files send, defaulting the missing counters to `0` and `1`.
+//!
+//! Both check the position as well as the syntax ([`Board::validate`]): one
+//! king a side, no pawn on the first or eighth rank, the side that just moved
+//! not in check, castling rights backed by their king and rook, and an
+//! en-passant square a double pawn step can have left. Every rejection is a
+//! [`FenError`] naming the field, square or right at fault.
+//!
+//! For Chess960 the castling field may name rooks by file, as in Shredder-FEN
+//! (`HAha`) or X-FEN (`KQkq`, with a file letter only when another rook stands
+//! further out on the same side). `KQkq` pick the outermost rook on each side
+//! of the king. The writer emits X-FEN, which is plain `KQkq` for standard
+//! chess.
 //!
 //! [fen]: https://www.chessprogramming.org/Forsyth-Edwards_Notation
 
+use std::fmt;
+
+use super::bitboard::Bitboard;
 use super::constants::EMPTY_BIT_B;
-use crate::chess_engine::board::{BLACK, Board, PLAYER_COUNT, Turn, WHITE};
+use super::masks::{RANK_1, RANK_8};
+use crate::chess_engine::board::{
+    BLACK, Board, PLAYER_COUNT, STANDARD_CASTLING_ROOKS, Turn, WHITE,
+};
 use crate::chess_engine::castle_rights::CastleRights;
 use crate::chess_engine::piece::{PIECE_COUNT, Piece};
 use crate::chess_engine::position::Position;
@@ -15,18 +37,118 @@ use crate::chess_engine::position::Position;
 /// FEN of the standard starting position.
 pub const START_POS_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
+/// Why a FEN string was rejected: a malformed field, or a position that
+/// cannot arise in a game (see [`Board::validate`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// The string has the wrong number of fields.
+    FieldCount(usize),
+    /// The placement field does not have eight ranks.
+    RankCount(usize),
+    /// A rank does not describe exactly eight squares.
+    RankLength {
+        /// The rank, numbered `1..=8`.
+        rank: usize,
+        /// How many squares it describes.
+        squares: usize,
+    },
+    /// A placement character is neither a piece letter nor a digit.
+    InvalidPiece {
+        /// The rank it is on, numbered `1..=8`.
+        rank: usize,
+        /// The character.
+        piece: char,
+    },
+    /// The side-to-move field is not `w` or `b`.
+    InvalidSideToMove(String),
+    /// A castling character is not `KQkq`, a file letter or `-`.
+    InvalidCastling(char),
+    /// The en-passant field is neither `-` nor a square.
+    InvalidEnPassant(String),
+    /// The half-move clock is not a number from 0 to 255.
+    InvalidHalfmoveClock(String),
+    /// The full-move number is not a number from 0 to 65535.
+    InvalidFullmoveNumber(String),
+    /// A side does not have exactly one king.
+    KingCount {
+        /// The side.
+        side: Turn,
+        /// How many kings it has.
+        count: u32,
+    },
+    /// A pawn stands on the first or eighth rank.
+    PawnOnBackRank(Position),
+    /// The side that just moved is in check.
+    OpponentInCheck,
+    /// A castling right (as written in `KQkq`) without its king on the back
+    /// rank and its rook on the castling square.
+    CastlingMismatch(char),
+    /// An en-passant square that no double pawn step can have left.
+    ImpossibleEnPassant(Position),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |turn: &Turn| if *turn == WHITE { "White" } else { "Black" };
+        match self {
+            Self::FieldCount(found) => write!(f, "FEN must have 6 fields, found {found}"),
+            Self::RankCount(found) => write!(f, "Board must have 8 ranks, found {found}"),
+            Self::RankLength { rank, squares } => {
+                write!(f, "Rank {rank} must have 8 squares, found {squares}")
+            }
+            Self::InvalidPiece { rank, piece } => {
+                write!(f, "Invalid piece encoding '{piece}' on rank {rank}")
+            }
+            Self::InvalidSideToMove(field) => write!(f, "Invalid side to move: {field}"),
+            Self::InvalidCastling(ch) => write!(f, "Invalid castle character: {ch}"),
+            Self::InvalidEnPassant(field) => write!(f, "Invalid en passant position: {field}"),
+            Self::InvalidHalfmoveClock(field) => write!(f, "Invalid halfmove clock: {field}"),
+            Self::InvalidFullmoveNumber(field) => write!(f, "Invalid fullmove: {field}"),
+            Self::KingCount { side: turn, count } => {
+                write!(f, "{} must have one king, found {count}", side(turn))
+            }
+            Self::PawnOnBackRank(square) => {
+                write!(f, "Pawn on a back rank: {}", square.algebraic_notation())
+            }
+            Self::OpponentInCheck => write!(f, "The side not to move is in check"),
+            Self::CastlingMismatch(right) => {
+                write!(
+                    f,
+                    "Castling right {right} has no king and rook to castle with"
+                )
+            }
+            Self::ImpossibleEnPassant(square) => write!(
+                f,
+                "Impossible en passant square: {}",
+                square.algebraic_notation()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Lets the `String`-error functions elsewhere in the crate use `?` on a FEN.
+impl From<FenError> for String {
+    fn from(error: FenError) -> Self {
+        error.to_string()
+    }
+}
+
 impl Board {
-    /// Parses a six-field FEN string into a [`Board`].
+    /// Parses a six-field FEN string into a [`Board`], and checks that the
+    /// position is one a game can reach ([`validate`](Self::validate)).
     ///
     /// # Errors
     ///
-    /// Returns `Err` with a human-readable message if the string does not have
-    /// exactly six fields, has more than eight ranks, or contains an invalid
-    /// piece letter, side-to-move, castling character, en-passant square, or
-    /// move counter.
+    /// Returns the [`FenError`] describing the first problem found: the wrong
+    /// number of fields or ranks, an invalid piece letter, side-to-move,
+    /// castling character, en-passant square, or move counter, or a position
+    /// that fails [`validate`](Self::validate).
     ///
     /// ```
     /// use sabertooth::chess_engine::board::{Board, BLACK, WHITE};
+    /// use sabertooth::chess_engine::fen_parser::FenError;
     /// use sabertooth::chess_engine::piece::Piece;
     /// use sabertooth::chess_engine::position::Position;
     ///
@@ -38,57 +160,159 @@ impl Board {
     /// assert_eq!(board.get_piece_at(Position::new(28)), Some((Piece::Pawn, WHITE))); // e4
     ///
     /// // every field must be present
-    /// assert!(Board::from_fen("garbage").is_err());
+    /// assert_eq!(Board::from_fen("garbage").err(), Some(FenError::FieldCount(1)));
+    /// // and the position must be legal: here White has no king
+    /// assert_eq!(
+    ///     Board::from_fen("4k3/8/8/8/8/8/8/8 w - - 0 1").err(),
+    ///     Some(FenError::KingCount { side: WHITE, count: 0 })
+    /// );
+    /// ```
+    pub fn from_fen(string: &str) -> Result<Self, FenError> {
+        Self::parse_fen(string, false)
+    }
+
+    /// Like [`from_fen`](Self::from_fen), but also accepts a FEN without its
+    /// half-move clock, or without both counters, as GUIs and EPD files send
+    /// them; a missing clock is `0` and a missing move number `1`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FenError`] as [`from_fen`](Self::from_fen) does, for fewer
+    /// than four or more than six fields.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::board::Board;
+    ///
+    /// let board = Board::from_fen_lenient("4k3/8/8/8/8/8/8/4K2R w K -").unwrap();
+    /// assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
     /// ```
-    pub fn from_fen(string: &str) -> Result<Self, String> {
+    pub fn from_fen_lenient(string: &str) -> Result<Self, FenError> {
+        Self::parse_fen(string, true)
+    }
+
+    /// Checks that the position can arise in a game of chess: each side has
+    /// exactly one king, no pawn stands on the first or eighth rank, the side
+    /// that just moved is not in check, every castling right has its king on
+    /// the back rank and its rook on the castling square, and an en-passant
+    /// square lies behind a pawn that can just have made a double step.
+    /// [`from_fen`](Self::from_fen) runs this on every position it parses.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`FenError`] for the first invariant that does not hold.
+    pub fn validate(&self) -> Result<(), FenError> {
+        for side in [WHITE, BLACK] {
+            let count = self.get_piece_bitboard(Piece::King, side).count_bits();
+            if count != 1 {
+                return Err(FenError::KingCount { side, count });
+            }
+        }
+
+        let pawns = self.get_piece_bitboard(Piece::Pawn, WHITE)
+            | self.get_piece_bitboard(Piece::Pawn, BLACK);
+        if let Some(square) = (pawns & (RANK_1 | RANK_8)).first_set_bit() {
+            return Err(FenError::PawnOnBackRank(Position::new(square)));
+        }
+
+        for (index, right) in ['K', 'Q', 'k', 'q'].into_iter().enumerate() {
+            let (turn, king_side) = (index >= 2, index % 2 == 0);
+            if !self.castle_rights.can_castle(turn, king_side) {
+                continue;
+            }
+            let rank = if turn == WHITE { 0 } else { 7 };
+            let king = self.get_piece_bitboard(Piece::King, turn).trailing_zeros();
+            let rook = self.castling_rook(turn, king_side).as_usize();
+            let rook_outside = if king_side { rook > king } else { rook < king };
+            if king / 8 != rank
+                || rook / 8 != rank
+                || !rook_outside
+                || !self
+                    .get_piece_bitboard(Piece::Rook, turn)
+                    .is_square_set(rook)
+            {
+                return Err(FenError::CastlingMismatch(right));
+            }
+        }
+
+        if let Some(square) = self.en_passant.first_set_bit() {
+            // the square a pawn of the side that just moved skipped: behind
+            // it that pawn, in front of it the empty square it started from
+            let (file, rank) = Position::new(square).get_file_and_rank();
+            let (ep_rank, pawn_rank, start_rank) = if self.turn == WHITE {
+                (5, 4, 6)
+            } else {
+                (2, 3, 1)
+            };
+            let pawn = Position::from_file_and_rank(file, pawn_rank);
+            let start = Position::from_file_and_rank(file, start_rank);
+            if rank != ep_rank
+                || self.get_piece_at(pawn) != Some((Piece::Pawn, !self.turn))
+                || !self.empty_tiles.is_square_set(square)
+                || !self.empty_tiles.is_square_set(start.as_usize())
+            {
+                return Err(FenError::ImpossibleEnPassant(Position::new(square)));
+            }
+        }
+
+        if self.in_check(!self.turn) {
+            return Err(FenError::OpponentInCheck);
+        }
+        Ok(())
+    }
+
+    /// Parses and validates a FEN; `lenient` lets the counters be missing.
+    fn parse_fen(string: &str, lenient: bool) -> Result<Self, FenError> {
         let mut piece_boards = [EMPTY_BIT_B; PIECE_COUNT * PLAYER_COUNT];
 
         let parts: Vec<&str> = string.split_whitespace().collect();
-        if parts.len() != 6 {
-            return Err("FEN must have 6 fields".to_string());
+        let fields = if lenient { 4..=6 } else { 6..=6 };
+        if !fields.contains(&parts.len()) {
+            return Err(FenError::FieldCount(parts.len()));
         }
 
         // set board positions
-        for (rank_index, rank) in parts[0].split('/').enumerate() {
+        let ranks: Vec<&str> = parts[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::RankCount(ranks.len()));
+        }
+        for (rank_index, rank) in ranks.into_iter().enumerate() {
+            let rank_number = 8 - rank_index;
             let mut x: usize = 0;
-            if rank_index > 7 {
-                return Err("Board must have 8 ranks".to_string());
-            }
             for ch in rank.chars() {
                 if let Some(blank_tiles) = ch.to_digit(10) {
                     x += blank_tiles as usize;
                 } else {
                     let turn: Turn = if ch.is_lowercase() { BLACK } else { WHITE };
                     let Ok(piece) = Piece::try_from(ch.to_string().as_str()) else {
-                        return Err("Invalid piece encoding".to_string());
+                        return Err(FenError::InvalidPiece {
+                            rank: rank_number,
+                            piece: ch,
+                        });
                     };
-                    let position = Position::from_file_and_rank(x, 7 - rank_index);
-
-                    let piece_index = Self::get_bb_index(piece, turn);
-                    piece_boards[piece_index].set_square(position.as_usize());
+                    if x < 8 {
+                        let position = Position::from_file_and_rank(x, 7 - rank_index);
+                        let piece_index = Self::get_bb_index(piece, turn);
+                        piece_boards[piece_index].set_square(position.as_usize());
+                    }
                     x += 1;
                 }
             }
+            if x != 8 {
+                return Err(FenError::RankLength {
+                    rank: rank_number,
+                    squares: x,
+                });
+            }
         }
         // turn
         let turn = match parts[1] {
             "w" => WHITE,
             "b" => BLACK,
-            _ => return Err("Invalid side to move".to_string()),
+            other => return Err(FenError::InvalidSideToMove(other.to_string())),
         };
 
-        // castle rights
-        let mut castle_rights = [false, false, false, false];
-        for ch in parts[2].chars() {
-            match ch {
-                'K' => castle_rights[0] = true,
-                'Q' => castle_rights[1] = true,
-                'k' => castle_rights[2] = true,
-                'q' => castle_rights[3] = true,
-                '-' => (),
-                _ => return Err(format!("Invalid castle character: {ch}")),
-            }
-        }
+        // castle rights, and the rooks they belong to
+        let (castle_rights, castling_rooks, shredder) = parse_castling(parts[2], &piece_boards)?;
         let castle = CastleRights::make(
             castle_rights[0],
             castle_rights[1],
@@ -100,33 +324,205 @@ impl Board {
         if parts[3] != "-" {
             match Position::try_from(parts[3]) {
                 Ok(pos) => en_passant.set_square(pos.as_usize()),
-                _ => return Err("Invalid en passant position".to_string()),
+                _ => return Err(FenError::InvalidEnPassant(parts[3].to_string())),
             }
         }
         // halfmove count
-        let halfmove_count = parts[4]
+        let halfmove = parts.get(4).copied().unwrap_or("0");
+        let halfmove_count = halfmove
             .parse::<u8>()
-            .map_err(|_| "Invalid halfmove clock")?;
+            .map_err(|_| FenError::InvalidHalfmoveClock(halfmove.to_string()))?;
 
         // fullmove
-        let full_move = parts[5].parse::<u16>().map_err(|_| "Invalid fullmove")?;
-
-        Ok(Self::new_from_bitboards(
+        let fullmove = parts.get(5).copied().unwrap_or("1");
+        let fullmove_count = fullmove
+            .parse::<u16>()
+            .map_err(|_| FenError::InvalidFullmoveNumber(fullmove.to_string()))?;
+        let mut board = Self::new_from_bitboards(
             piece_boards,
             turn,
             en_passant,
             halfmove_count,
-            full_move,
+            fullmove_count,
             castle,
-        ))
+        );
+        board.castling_rooks = castling_rooks;
+        // file letters, or any castling king or rook off its standard square,
+        // mean a Chess960 game
+        let standard_king = |turn| {
+            board
+                .get_piece_bitboard(Piece::King, turn)
+                .is_square_set(if turn == WHITE { 4 } else { 60 })
+        };
+        board.chess960 = shredder
+            || (0..4).any(|index| {
+                castle_rights[index]
+                    && (castling_rooks[index] != STANDARD_CASTLING_ROOKS[index]
+                        || !standard_king(index >= 2))
+            });
+        board.validate()?;
+        Ok(board)
+    }
+
+    /// Serialises the position as a six-field FEN string; the inverse of
+    /// [`from_fen`](Self::from_fen). Equivalent to `board.to_string()`.
+    ///
+    /// The en-passant field is written whenever [`en_passant`](Self::en_passant)
+    /// is set, i.e. after every double pawn step, whether or not a capture is
+    /// actually available.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::board::Board;
+    /// use sabertooth::chess_engine::utils::init_tables;
+    ///
+    /// init_tables();
+    /// let mut board = Board::new_start_pos().unwrap();
+    /// assert!(board.play_string_move("e2e4"));
+    /// assert_eq!(
+    ///     board.to_fen(),
+    ///     "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn to_fen(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Parses the castling field: the rights in castle-index order, the square of
+/// each right's rook, and whether the field named rooks by file
+/// (Shredder-FEN or X-FEN letters rather than `KQkq`). `K`/`Q` pick the
+/// outermost rook on that side of the king, falling back to the corner.
+fn parse_castling(
+    field: &str,
+    piece_boards: &[Bitboard; PIECE_COUNT * PLAYER_COUNT],
+) -> Result<([bool; 4], [Position; 4], bool), FenError> {
+    let mut castle_rights = [false, false, false, false];
+    let mut castling_rooks = STANDARD_CASTLING_ROOKS;
+    let mut shredder = false;
+    for ch in field.chars() {
+        if ch == '-' {
+            continue;
+        }
+        let turn: Turn = ch.is_ascii_lowercase();
+        let rank = if turn == WHITE { 0 } else { 7 };
+        let king_file = piece_boards[Board::get_bb_index(Piece::King, turn)]
+            .first_set_bit()
+            .filter(|&square| square / 8 == rank)
+            .map_or(4, |square| square % 8);
+        let rooks = piece_boards[Board::get_bb_index(Piece::Rook, turn)];
+        let rook_file = match ch.to_ascii_uppercase() {
+            // the outermost rook on that side of the king, if any
+            'K' => (king_file + 1..8)
+                .rev()
+                .find(|&file| rooks.is_square_set(rank * 8 + file))
+                .unwrap_or(7),
+            'Q' => (0..king_file)
+                .find(|&file| rooks.is_square_set(rank * 8 + file))
+                .unwrap_or(0),
+            file @ 'A'..='H' => {
+                shredder = true;
+                file as usize - 'A' as usize
+            }
+            _ => return Err(FenError::InvalidCastling(ch)),
+        };
+        let king_side = rook_file > king_file;
+        let index = 2 * usize::from(turn) + usize::from(!king_side);
+        castle_rights[index] = true;
+        castling_rooks[index] = Position::from_file_and_rank(rook_file, rank);
+    }
+    Ok((castle_rights, castling_rooks, shredder))
+}
+
+/// Formats the board as a FEN string (see [`Board::to_fen`]).
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // piece placement, rank 8 first; runs of empty squares become digits
+        for rank in (0..8).rev() {
+            let mut empty_run = 0;
+            for file in 0..8 {
+                match self.get_piece_at(Position::from_file_and_rank(file, rank)) {
+                    Some((piece, turn)) => {
+                        if empty_run > 0 {
+                            write!(f, "{empty_run}")?;
+                            empty_run = 0;
+                        }
+                        let letter = piece.to_notation();
+                        if turn == WHITE {
+                            write!(f, "{}", letter.to_uppercase())?;
+                        } else {
+                            write!(f, "{letter}")?;
+                        }
+                    }
+                    None => empty_run += 1,
+                }
+            }
+            if empty_run > 0 {
+                write!(f, "{empty_run}")?;
+            }
+            if rank > 0 {
+                write!(f, "/")?;
+            }
+        }
+
+        // side to move
+        write!(f, " {} ", if self.turn == WHITE { 'w' } else { 'b' })?;
+
+        // castle rights, in the conventional KQkq order; a rook that is not the
+        // outermost on its side is named by its file (X-FEN)
+        let mut any_castle = false;
+        for (turn, king_side, ch) in [
+            (WHITE, true, 'K'),
+            (WHITE, false, 'Q'),
+            (BLACK, true, 'k'),
+            (BLACK, false, 'q'),
+        ] {
+            if self.castle_rights.can_castle(turn, king_side) {
+                let rook = self.castling_rook(turn, king_side);
+                let (rook_file, rank) = rook.get_file_and_rank();
+                let rooks = self.get_piece_bitboard(Piece::Rook, turn);
+                let mut outer_files = if king_side {
+                    rook_file + 1..8
+                } else {
+                    0..rook_file
+                };
+                if outer_files.any(|file| rooks.is_square_set(rank * 8 + file)) {
+                    let file = &rook.algebraic_notation()[..1];
+                    if turn == WHITE {
+                        write!(f, "{}", file.to_uppercase())?;
+                    } else {
+                        write!(f, "{file}")?;
+                    }
+                } else {
+                    write!(f, "{ch}")?;
+                }
+                any_castle = true;
+            }
+        }
+        if !any_castle {
+            write!(f, "-")?;
+        }
+
+        // en passant
+        if self.en_passant.is_not_empty() {
+            let square = Position::new(self.en_passant.trailing_zeros());
+            write!(f, " {}", square.algebraic_notation())?;
+        } else {
+            write!(f, " -")?;
+        }
+
+        // clocks
+        write!(f, " {} {}", self.halfmove_count, self.fullmove_count)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{FenError, START_POS_FEN};
     use crate::chess_engine::board::{BLACK, Board, WHITE};
     use crate::chess_engine::piece::Piece;
     use crate::chess_engine::position::Position;
+    use crate::chess_engine::utils::init_tables;
 
     #[test]
     fn start_pos_fen_places_pieces() {
@@ -158,7 +554,205 @@ mod tests {
 
     #[test]
     fn rejects_malformed_fen() {
-        assert!(Board::from_fen("garbage").is_err());
-        assert!(Board::from_fen("8/8/8/8/8/8/8/8 x - - 0 1").is_err());
+        let error = |fen: &str| Board::from_fen(fen).err();
+        assert_eq!(error("garbage"), Some(FenError::FieldCount(1)));
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+            Some(FenError::InvalidSideToMove("x".to_string()))
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/4K3 w - - 0 1"),
+            Some(FenError::RankCount(7))
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K4 w - - 0 1"),
+            Some(FenError::RankLength {
+                rank: 1,
+                squares: 9
+            })
+        );
+        assert_eq!(
+            error("4k3/8/8/8/3X4/8/8/4K3 w - - 0 1"),
+            Some(FenError::InvalidPiece {
+                rank: 4,
+                piece: 'X'
+            })
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w Z - 0 1"),
+            Some(FenError::InvalidCastling('Z'))
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - z9 0 1"),
+            Some(FenError::InvalidEnPassant("z9".to_string()))
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+            Some(FenError::InvalidHalfmoveClock("x".to_string()))
+        );
+        // the message names the field for the `String`-error callers
+        assert_eq!(
+            String::from(FenError::InvalidFullmoveNumber("-1".to_string())),
+            "Invalid fullmove: -1"
+        );
+    }
+
+    #[test]
+    fn rejects_impossible_positions() {
+        let error = |fen: &str| Board::from_fen(fen).err();
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+            Some(FenError::KingCount {
+                side: WHITE,
+                count: 2
+            })
+        );
+        assert_eq!(
+            error("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Some(FenError::KingCount {
+                side: BLACK,
+                count: 0
+            })
+        );
+        assert_eq!(
+            error("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Some(FenError::PawnOnBackRank(Position::new(63)))
+        );
+        // White to move, yet the black king on e8 is attacked by the rook
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"),
+            Some(FenError::OpponentInCheck)
+        );
+        // no rook on h1, and a king off its back rank
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/R3K3 w KQ - 0 1"),
+            Some(FenError::CastlingMismatch('K'))
+        );
+        assert_eq!(
+            error("r7/4k3/8/8/8/8/8/4K3 w q - 0 1"),
+            Some(FenError::CastlingMismatch('q'))
+        );
+        // e6 needs a black pawn on e5 that came from an empty e7
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - e6 0 1"),
+            Some(FenError::ImpossibleEnPassant(Position::new(44)))
+        );
+        assert_eq!(
+            error("4k3/8/8/4p3/8/8/8/4K3 b - e6 0 1"),
+            Some(FenError::ImpossibleEnPassant(Position::new(44)))
+        );
+        assert!(Board::from_fen("4k3/8/8/4pP2/8/8/8/4K3 w - e6 0 1").is_ok());
+    }
+
+    #[test]
+    fn lenient_parsing_defaults_missing_counters() {
+        let full = "4k3/8/8/8/8/8/8/4K2R w K - 3 7";
+        assert_eq!(Board::from_fen_lenient(full).unwrap().to_fen(), full);
+        for fen in ["4k3/8/8/8/8/8/8/4K2R w K -", "4k3/8/8/8/8/8/8/4K2R w K - 0"] {
+            assert_eq!(
+                Board::from_fen_lenient(fen).unwrap().to_fen(),
+                "4k3/8/8/8/8/8/8/4K2R w K - 0 1"
+            );
+            assert_eq!(
+                Board::from_fen(fen).err(),
+                Some(FenError::FieldCount(fen.split(' ').count()))
+            );
+        }
+        assert_eq!(
+            Board::from_fen_lenient("4k3/8/8/8/8/8/8/4K2R w K").err(),
+            Some(FenError::FieldCount(3))
+        );
+        // the position is still validated
+        assert!(Board::from_fen_lenient("8/8/8/8/8/8/8/4K3 w - -").is_err());
+    }
+
+    #[test]
+    fn to_fen_reproduces_input() {
+        for fen in [
+            START_POS_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    /// Walks the move tree from `board` to `depth` plies and checks at every
+    /// node that re-parsing the written FEN rebuilds the same position (the
+    /// undo history aside, which a FEN does not carry).
+    fn assert_round_trips(board: &mut Board, depth: u32) {
+        let mut expected = board.clone();
+        expected.history.clear();
+        let fen = board.to_fen();
+        let parsed = Board::from_fen(&fen).unwrap();
+        assert!(
+            parsed == expected,
+            "FEN round trip changed the board: {fen}"
+        );
+
+        if depth == 0 {
+            return;
+        }
+        for mv in board.generate_moves(board.turn) {
+            board.commit_verified_move(mv);
+            assert_round_trips(board, depth - 1);
+            board.unmake_move();
+        }
+    }
+
+    #[test]
+    fn from_fen_inverts_to_fen_across_perft_positions() {
+        init_tables();
+        for (fen, depth) in [
+            (START_POS_FEN, 3),
+            // Kiwipete: castling, en passant, and promotions all show up
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                2,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                2,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                2,
+            ),
+        ] {
+            let mut board = Board::from_fen(fen).unwrap();
+            assert_round_trips(&mut board, depth);
+        }
+    }
+
+    #[test]
+    fn reads_chess960_castling_fields() {
+        // Shredder-FEN: rooks named by file; the king sits on g1/g8
+        let board =
+            Board::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")
+                .unwrap();
+        assert!(board.chess960);
+        assert_eq!(board.castling_rook(WHITE, true), Position::new(7)); // h1
+        assert_eq!(board.castling_rook(WHITE, false), Position::new(5)); // f1
+        assert_eq!(board.castling_rook(BLACK, false), Position::new(61)); // f8
+        // written back as X-FEN: both rooks are the outermost on their side
+        assert_eq!(
+            board.to_fen(),
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9"
+        );
+
+        // X-FEN needs a file letter only for an inner rook
+        let fen = "1r2k1rr/8/8/8/8/8/8/RR2K2R w KBgq - 0 1";
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(board.castling_rook(WHITE, false), Position::new(1)); // b1
+        assert_eq!(board.castling_rook(BLACK, true), Position::new(62)); // g8
+        assert_eq!(board.to_fen(), fen);
+
+        // the standard setup stays standard
+        assert!(!Board::from_fen(START_POS_FEN).unwrap().chess960);
+        assert!(Board::from_fen("8/8/8/8/8/8/8/4K2R w X - 0 1").is_err());
     }
 }
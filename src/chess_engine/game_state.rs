@@ -1,14 +1,28 @@
-//! [`StateDelta`], the per-move record used to undo moves.
+//! [`StateDelta`], the per-move record used to undo moves, [`GameStatus`], how
+//! the game stands in a position, and [`GameResult`], how a game ended.
 //!
-//! Before a move is applied, [`Board`](super::board::Board) pushes a
+//! Before a move is applied, [`Board`] pushes a
 //! `StateDelta` capturing the pieces of state that a move cannot reconstruct on
 //! its own — the captured piece, the previous en-passant target, castling
 //! rights, and the half-move clock — plus the Zobrist hash of the position
 //! *before* the move (used for repetition detection). `unmake_move` pops the
 //! stack and restores these fields.
+//!
+//! The same stack lets [`Board::outcome`] tell whether the game is over:
+//! checkmate, stalemate and insufficient material end it outright, while a
+//! threefold repetition or the fifty-move rule give either player a draw to
+//! claim.
+
+use std::fmt;
 
 use crate::chess_engine::{
-    bitboard::Bitboard, castle_rights::CastleRights, moves::Move, piece::Piece,
+    bitboard::Bitboard,
+    board::{Board, Turn, WHITE},
+    castle_rights::CastleRights,
+    computed_boards::ZOBRIST_TABLE,
+    moves::{Move, SpecialMove},
+    piece::Piece,
+    position::Position,
     zobrist::ZobristHash,
 };
 
@@ -33,6 +47,7 @@ pub struct StateDelta {
 
 impl StateDelta {
     /// Bundles the pre-move state into a [`StateDelta`].
+    #[must_use]
     pub const fn new(
         move_: Move,
         captured_piece: Option<Piece>,
@@ -51,3 +66,263 @@ impl StateDelta {
         }
     }
 }
+
+/// How a game ended, as recorded by a PGN termination marker and `Result` tag.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    /// `1-0`.
+    WhiteWins,
+    /// `0-1`.
+    BlackWins,
+    /// `1/2-1/2`.
+    Draw,
+    /// `*`: unfinished, or the result is unknown.
+    Unknown,
+}
+
+impl GameResult {
+    /// Parses a PGN termination marker, returning `None` for anything else.
+    #[must_use]
+    pub fn from_pgn(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(Self::WhiteWins),
+            "0-1" => Some(Self::BlackWins),
+            "1/2-1/2" => Some(Self::Draw),
+            "*" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the result as its PGN termination marker (e.g. `"1-0"`).
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Unknown => "*",
+        })
+    }
+}
+
+/// How the game stands in a position, as classified by [`Board::outcome`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    /// The game goes on.
+    Ongoing,
+    /// The side to move is checkmated.
+    Checkmate,
+    /// The side to move has no legal move but is not in check.
+    Stalemate,
+    /// Neither side has the material to mate (see
+    /// [`Board::is_insufficient_material`]).
+    InsufficientMaterial,
+    /// The position has occurred three times: a draw either player may claim.
+    ThreefoldRepetition,
+    /// A hundred half-moves have passed without a capture or pawn move: a draw
+    /// either player may claim.
+    FiftyMoveRule,
+}
+
+impl GameStatus {
+    /// Whether the game is over, counting the claimable draws as claimed.
+    #[must_use]
+    pub const fn is_over(self) -> bool {
+        !matches!(self, Self::Ongoing)
+    }
+
+    /// Whether the status is a draw a player must claim, rather than one the
+    /// rules impose.
+    #[must_use]
+    pub const fn is_claim(self) -> bool {
+        matches!(self, Self::ThreefoldRepetition | Self::FiftyMoveRule)
+    }
+
+    /// The game's result when `side_to_move` is to move;
+    /// [`Unknown`](GameResult::Unknown) while it goes on.
+    #[must_use]
+    pub const fn result(self, side_to_move: Turn) -> GameResult {
+        match self {
+            Self::Ongoing => GameResult::Unknown,
+            Self::Checkmate if side_to_move == WHITE => GameResult::BlackWins,
+            Self::Checkmate => GameResult::WhiteWins,
+            _ => GameResult::Draw,
+        }
+    }
+
+    /// A lowercase description, e.g. `threefold repetition`.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Ongoing => "ongoing",
+            Self::Checkmate => "checkmate",
+            Self::Stalemate => "stalemate",
+            Self::InsufficientMaterial => "insufficient material",
+            Self::ThreefoldRepetition => "threefold repetition",
+            Self::FiftyMoveRule => "fifty-move rule",
+        }
+    }
+}
+
+/// Formats the status as its [`description`](GameStatus::description).
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl Board {
+    /// Classifies the position: checkmate or stalemate first (a mate delivered
+    /// on the hundredth half-move still stands), then insufficient material,
+    /// threefold repetition and the fifty-move rule.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::board::Board;
+    /// use sabertooth::chess_engine::game_state::GameStatus;
+    /// use sabertooth::chess_engine::utils::init_tables;
+    ///
+    /// init_tables();
+    /// let mut board = Board::new_start_pos().unwrap();
+    /// for mv in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+    ///     assert_eq!(board.outcome(), GameStatus::Ongoing);
+    ///     assert!(board.play_string_move(mv));
+    /// }
+    /// assert_eq!(board.outcome(), GameStatus::Checkmate);
+    /// ```
+    pub fn outcome(&mut self) -> GameStatus {
+        if self.generate_moves(self.turn).is_empty() {
+            if self.in_check(self.turn) {
+                return GameStatus::Checkmate;
+            }
+            return GameStatus::Stalemate;
+        }
+        if self.is_insufficient_material() {
+            GameStatus::InsufficientMaterial
+        } else if self.is_threefold_repetition() {
+            GameStatus::ThreefoldRepetition
+        } else if self.halfmove_count >= 100 {
+            GameStatus::FiftyMoveRule
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    /// Whether the current position has occurred at least three times.
+    ///
+    /// Positions are the same when the same side is to move with the same
+    /// pieces, castling rights and en-passant *captures*: a double pawn step
+    /// that no pawn can take en passant does not make a position new, though
+    /// it sets the en-passant square. Only the positions since the last
+    /// capture or pawn move are compared, each found by unmaking the moves
+    /// played from this board and replaying them afterwards.
+    pub fn is_threefold_repetition(&mut self) -> bool {
+        let reversible = usize::from(self.halfmove_count).min(self.history.len());
+        let current = self.repetition_key();
+        let mut occurrences = 1;
+        let mut undone = Vec::with_capacity(reversible);
+        for _ in 0..reversible {
+            let Some(delta) = self.history.last() else {
+                break;
+            };
+            undone.push(delta.move_);
+            self.unmake_move();
+            if self.repetition_key() == current {
+                occurrences += 1;
+            }
+        }
+        for mv in undone.into_iter().rev() {
+            self.commit_verified_move(mv);
+        }
+        occurrences >= 3
+    }
+
+    /// The Zobrist key with the en-passant file left out when no legal en
+    /// passant capture exists.
+    pub(crate) fn repetition_key(&mut self) -> ZobristHash {
+        let Some(square) = self.en_passant.first_set_bit() else {
+            return self.zobrist_key;
+        };
+        let can_capture = self
+            .generate_moves(self.turn)
+            .iter()
+            .any(|mv| mv.get_special_move() == SpecialMove::EnPassant);
+        if can_capture {
+            self.zobrist_key
+        } else {
+            let (file, _) = Position::new(square).get_file_and_rank();
+            self.zobrist_key ^ ZOBRIST_TABLE.en_passant_file[file]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameResult, GameStatus};
+    use crate::chess_engine::{board::Board, utils::init_tables};
+
+    fn play(board: &mut Board, moves: &str) {
+        for mv in moves.split_whitespace() {
+            assert!(board.play_string_move(mv), "{mv} should be legal");
+        }
+    }
+
+    #[test]
+    fn classifies_terminal_positions() {
+        init_tables();
+        let status = |fen: &str| Board::from_fen(fen).unwrap().outcome();
+        assert_eq!(
+            status("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            GameStatus::Ongoing
+        );
+        assert_eq!(
+            status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+            GameStatus::Stalemate
+        );
+        assert_eq!(
+            status("8/8/4k3/8/8/4KB2/8/8 w - - 0 1"),
+            GameStatus::InsufficientMaterial
+        );
+        assert_eq!(
+            status("8/8/4k3/8/8/3QK3/8/8 w - - 100 80"),
+            GameStatus::FiftyMoveRule
+        );
+        // mate on the hundredth half-move is still mate
+        let mate = status("7k/6Q1/6K1/8/8/8/8/8 b - - 100 80");
+        assert_eq!(mate, GameStatus::Checkmate);
+        assert_eq!(mate.result(true), GameResult::WhiteWins);
+        assert!(mate.is_over() && !mate.is_claim());
+        assert!(GameStatus::FiftyMoveRule.is_claim());
+        assert_eq!(GameStatus::Ongoing.result(false), GameResult::Unknown);
+    }
+
+    #[test]
+    fn threefold_needs_three_occurrences() {
+        init_tables();
+        let mut board = Board::new_start_pos().unwrap();
+        play(&mut board, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1");
+        assert_eq!(board.outcome(), GameStatus::Ongoing);
+        play(&mut board, "f6g8");
+        let fen = board.to_fen();
+        assert_eq!(board.outcome(), GameStatus::ThreefoldRepetition);
+        // the moves unmade to look back were replayed
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(board.history.len(), 8);
+    }
+
+    #[test]
+    fn en_passant_square_counts_only_when_capturable() {
+        init_tables();
+        // after e2e4 the en-passant square is set, but no black pawn can take
+        // it, so the position recurs on the knight shuffles that follow
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K1N1 w - - 0 1").unwrap();
+        play(&mut board, "e2e4 e8d8 g1f3 d8e8 f3g1 e8d8 g1f3 d8e8 f3g1");
+        assert_eq!(board.outcome(), GameStatus::ThreefoldRepetition);
+
+        // with a black pawn beside it the capture is possible, so the first
+        // position differs and only two occurrences follow
+        let mut board = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K1N1 w - - 0 1").unwrap();
+        play(&mut board, "e2e4 e8d8 g1f3 d8e8 f3g1 e8d8 g1f3 d8e8 f3g1");
+        assert_eq!(board.outcome(), GameStatus::Ongoing);
+    }
+}
@@ -19,8 +19,8 @@ use crate::chess_engine::{
     board::{BLACK, Board, Turn, WHITE},
     computed_boards::ZOBRIST_TABLE,
     constants::{
-        B_KING_ROOK_START, B_KING_SIDE_BISHOP_START, B_QUEEN_ROOK_START, B_QUEEN_START, NORTH,
-        SOUTH, W_KING_ROOK_START, W_KING_SIDE_BISHOP_START, W_QUEEN_ROOK_START, W_QUEEN_START,
+        B_KING_CASTLE_DEST, B_KING_SIDE_BISHOP_START, B_QUEEN_CASTLE_DEST, B_QUEEN_START, NORTH,
+        SOUTH, W_KING_CASTLE_DEST, W_KING_SIDE_BISHOP_START, W_QUEEN_CASTLE_DEST, W_QUEEN_START,
     },
     game_state::StateDelta,
     moves::{Move, SpecialMove},
@@ -41,12 +41,14 @@ impl Board {
         // move should be verified before
 
         let (origin, destination) = move_.get_org_and_dest();
-        let captured_piece = if move_.get_special_move() == SpecialMove::EnPassant {
-            Some(Piece::Pawn)
-        } else if !self.empty_tiles.is_square_set(destination.into()) {
-            Some(self.get_piece_type_containing_position(destination))
-        } else {
-            None
+        let captured_piece = match move_.get_special_move() {
+            SpecialMove::EnPassant => Some(Piece::Pawn),
+            // a king-takes-rook castle lands on its own rook
+            SpecialMove::Castle => None,
+            _ if !self.empty_tiles.is_square_set(destination.into()) => {
+                Some(self.get_piece_type_containing_position(destination))
+            }
+            _ => None,
         };
 
         let moving_piece = self.get_piece_type_containing_position(origin);
@@ -81,11 +83,13 @@ impl Board {
             }
 
             SpecialMove::Castle => {
-                self.remove_piece(self.turn, moving_piece, origin);
-                self.add_piece(self.turn, moving_piece, destination);
-                let (rook_origin, rook_dest) =
-                    Self::get_castle_rook_origin_dest(self.turn, destination);
+                // in Chess960 the king and rook may land on each other's
+                // squares, so lift both before placing either
+                let (king_dest, rook_origin, rook_dest) =
+                    self.castle_squares(self.turn, origin, destination);
+                self.remove_piece(self.turn, Piece::King, origin);
                 self.remove_piece(self.turn, Piece::Rook, rook_origin);
+                self.add_piece(self.turn, Piece::King, king_dest);
                 self.add_piece(self.turn, Piece::Rook, rook_dest);
             }
 
@@ -108,15 +112,15 @@ impl Board {
         // update board state
         self.en_passant = Bitboard::new();
         match moving_piece {
-            Piece::Pawn => {
-                // en passant
-                if (origin.as_usize() as i8 - destination.as_usize() as i8).abs() == 2 * NORTH {
-                    // middle between des and origin
-                    let en_passant_pos =
-                        Position::new(usize::midpoint(origin.as_usize(), destination.as_usize()));
-                    self.en_passant.set_square(en_passant_pos.as_usize());
-                    self.xor_en_pass_from_zobrist(self.en_passant);
-                }
+            // en passant
+            Piece::Pawn
+                if (origin.as_usize() as i8 - destination.as_usize() as i8).abs() == 2 * NORTH =>
+            {
+                // middle between des and origin
+                let en_passant_pos =
+                    Position::new(usize::midpoint(origin.as_usize(), destination.as_usize()));
+                self.en_passant.set_square(en_passant_pos.as_usize());
+                self.xor_en_pass_from_zobrist(self.en_passant);
             }
             // castle rights
             Piece::King => {
@@ -125,15 +129,10 @@ impl Board {
             }
 
             Piece::Rook => {
-                if (origin == W_KING_ROOK_START && self.turn == WHITE)
-                    || (origin == B_KING_ROOK_START && self.turn == BLACK)
-                {
-                    self.remove_castle(self.turn, true);
-                }
-                if (origin == W_QUEEN_ROOK_START && self.turn == WHITE)
-                    || (origin == B_QUEEN_ROOK_START && self.turn == BLACK)
-                {
-                    self.remove_castle(self.turn, false);
+                for king_side in [true, false] {
+                    if origin == self.castling_rook(self.turn, king_side) {
+                        self.remove_castle(self.turn, king_side);
+                    }
                 }
             }
 
@@ -141,7 +140,7 @@ impl Board {
         }
 
         if Some(Piece::Rook) == captured_piece {
-            self.captured_rook_remove_castle_rights();
+            self.captured_rook_remove_castle_rights(destination);
         }
 
         if self.turn == BLACK {
@@ -203,26 +202,13 @@ impl Board {
         self.zobrist_key = delta.zobrist_hash;
     }
 
-    /// After a rook is captured, revokes the opponent's castling right on that
-    /// rook's side if the rook is no longer on its starting square.
-    fn captured_rook_remove_castle_rights(&mut self) {
-        // capture rook, remove castle right
-        let enemy_rooks = self.get_piece_bitboard(Piece::Rook, !self.turn);
-        let enemy_queen_rook = if self.turn == WHITE {
-            B_QUEEN_ROOK_START
-        } else {
-            W_QUEEN_ROOK_START
-        };
-        let enemy_king_rook = if self.turn == WHITE {
-            B_KING_ROOK_START
-        } else {
-            W_KING_ROOK_START
-        };
-        if !enemy_rooks.is_square_set(enemy_king_rook.as_usize()) {
-            self.remove_castle(!self.turn, true);
-        }
-        if !enemy_rooks.is_square_set(enemy_queen_rook.as_usize()) {
-            self.remove_castle(!self.turn, false);
+    /// After a rook is captured on `square`, revokes the opponent's castling
+    /// right on that rook's side if it was one of their castling rooks.
+    fn captured_rook_remove_castle_rights(&mut self, square: Position) {
+        for king_side in [true, false] {
+            if square == self.castling_rook(!self.turn, king_side) {
+                self.remove_castle(!self.turn, king_side);
+            }
         }
     }
 
@@ -274,10 +260,11 @@ impl Board {
             }
 
             SpecialMove::Castle => {
-                self.add_piece(self.turn, moving_piece, origin);
-                self.remove_piece(self.turn, moving_piece, dest);
-                let (rook_origin, rook_dest) = Self::get_castle_rook_origin_dest(self.turn, dest);
+                let (king_dest, rook_origin, rook_dest) =
+                    self.castle_squares(self.turn, origin, dest);
+                self.remove_piece(self.turn, Piece::King, king_dest);
                 self.remove_piece(self.turn, Piece::Rook, rook_dest);
+                self.add_piece(self.turn, Piece::King, origin);
                 self.add_piece(self.turn, Piece::Rook, rook_origin);
             }
         }
@@ -343,30 +330,42 @@ impl Board {
         self.make_input_move(origin, dest, promote)
     }
 
-    /// Returns the `(origin, destination)` squares of the rook involved in a
-    /// castle, inferred from the king's destination square `king_des`.
-    const fn get_castle_rook_origin_dest(turn: Turn, king_des: Position) -> (Position, Position) {
-        // return dest and origin of a rook that is moved during castle
-        let (file, _) = king_des.get_file_and_rank();
-        // queen side
-        if file == 2 {
-            if turn == WHITE {
-                return (W_QUEEN_ROOK_START, W_QUEEN_START);
-            }
-            return (B_QUEEN_ROOK_START, B_QUEEN_START);
-        }
-        // king side
-        else if turn == WHITE {
-            return (W_KING_ROOK_START, W_KING_SIDE_BISHOP_START);
-        }
-        (B_KING_ROOK_START, B_KING_SIDE_BISHOP_START)
+    /// Returns the king's destination and the rook's origin and destination
+    /// for a castle by `turn` whose king starts on `king_origin` and whose
+    /// move is encoded with destination `encoded_dest` — either the king's
+    /// two-square step or, king-takes-rook, the rook's square. Either way the
+    /// castle is king-side iff the encoded destination lies toward the h-file.
+    pub(crate) fn castle_squares(
+        &self,
+        turn: Turn,
+        king_origin: Position,
+        encoded_dest: Position,
+    ) -> (Position, Position, Position) {
+        let king_side = encoded_dest.get_file_and_rank().0 > king_origin.get_file_and_rank().0;
+        let (king_dest, rook_dest) = castle_destinations(turn, king_side);
+        (king_dest, self.castling_rook(turn, king_side), rook_dest)
+    }
+}
+
+/// Returns the `(king, rook)` destination squares of a castle: `c`/`d` for the
+/// queen side and `g`/`f` for the king side, whatever files the pieces start
+/// on.
+pub const fn castle_destinations(turn: Turn, king_side: bool) -> (Position, Position) {
+    match (turn == WHITE, king_side) {
+        (true, true) => (W_KING_CASTLE_DEST, W_KING_SIDE_BISHOP_START),
+        (true, false) => (W_QUEEN_CASTLE_DEST, W_QUEEN_START),
+        (false, true) => (B_KING_CASTLE_DEST, B_KING_SIDE_BISHOP_START),
+        (false, false) => (B_QUEEN_CASTLE_DEST, B_QUEEN_START),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::chess_engine::board::Board;
+    use crate::chess_engine::board::{BLACK, Board, WHITE};
     use crate::chess_engine::computed_boards::ZOBRIST_TABLE;
+    use crate::chess_engine::moves::SpecialMove;
+    use crate::chess_engine::piece::Piece;
+    use crate::chess_engine::position::Position;
 
     fn assert_incremental_hash_matches(board: &Board) {
         assert_eq!(
@@ -374,6 +373,11 @@ mod tests {
             ZOBRIST_TABLE.hash_position(board),
             "incremental zobrist key diverged from a full recompute"
         );
+        assert_eq!(
+            board.pawn_key,
+            ZOBRIST_TABLE.hash_pawns(board),
+            "incremental pawn key diverged from a full recompute"
+        );
     }
 
     fn play_and_check(board: &mut Board, moves: &[&str]) {
@@ -467,6 +471,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn chess960_castles_round_trip() {
+        // king b1 with rooks a1 and g1: queen-side the king and rook swap
+        // files, king-side the king travels five squares
+        let fen = "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w AGbg - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let original = board.clone();
+        let castles: Vec<String> = board
+            .generate_moves(board.turn)
+            .into_iter()
+            .filter(|mv| mv.get_special_move() == SpecialMove::Castle)
+            .map(|mv| mv.to_string())
+            .collect();
+        assert_eq!(castles, ["b1a1", "b1g1"]);
+
+        play_and_check(&mut board, &["b1a1"]);
+        assert_eq!(
+            board.get_piece_at(Position::from_file_and_rank(2, 0)),
+            Some((Piece::King, WHITE))
+        );
+        assert_eq!(
+            board.get_piece_at(Position::from_file_and_rank(3, 0)),
+            Some((Piece::Rook, WHITE))
+        );
+        // black castles king-side onto the square its rook already holds
+        play_and_check(&mut board, &["e8g8"]);
+        assert_eq!(
+            board.get_piece_at(Position::from_file_and_rank(6, 7)),
+            Some((Piece::King, BLACK))
+        );
+        assert_eq!(
+            board.to_fen(),
+            "1r3rk1/pppppppp/8/8/8/8/PPPPPPPP/2KR2R1 w - - 2 2"
+        );
+
+        board.unmake_move();
+        board.unmake_move();
+        assert_incremental_hash_matches(&board);
+        assert!(board == original, "unmake did not restore the castles");
+    }
+
+    #[test]
+    fn chess960_flag_switches_castle_notation() {
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert!(board.clone().play_string_move("e1g1"));
+        assert!(!board.clone().play_string_move("e1h1"));
+        board.chess960 = true;
+        assert!(!board.clone().play_string_move("e1g1"));
+        play_and_check(&mut board, &["e1h1", "e8a8"]);
+        assert_eq!(board.to_fen(), "2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2");
+    }
+
     #[test]
     fn repetition_is_detected() {
         let mut board = Board::new_start_pos().unwrap();
@@ -1,5 +1,4 @@
-//! Fixed [`Bitboard`] masks used during move generation: file/rank masks and
-//! the squares that must be empty for castling.
+//! Fixed [`Bitboard`] masks used during move generation: file and rank masks.
 
 use super::bitboard::Bitboard;
 
@@ -68,16 +67,3 @@ const fn generate_adjacent_file_masks() -> [Bitboard; 8] {
     }
     masks
 }
-
-// bits that should be empty when castling
-/// Squares (f8, g8) that must be empty for Black to castle king-side.
-pub const B_KING_CASTLE_EMPTY: Bitboard =
-    Bitboard(0b0110_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000);
-/// Squares (b8, c8, d8) that must be empty for Black to castle queen-side.
-pub const B_QUEEN_CASTLE_EMPTY: Bitboard =
-    Bitboard(0b0000_1110_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000);
-
-/// Squares (f1, g1) that must be empty for White to castle king-side.
-pub const W_KING_CASTLE_EMPTY: Bitboard = Bitboard(0b0110_0000);
-/// Squares (b1, c1, d1) that must be empty for White to castle queen-side.
-pub const W_QUEEN_CASTLE_EMPTY: Bitboard = Bitboard(0b0000_1110);
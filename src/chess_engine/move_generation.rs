@@ -1,37 +1,45 @@
 //! Move generation: turning a [`Board`] into the list of moves available to a
 //! side.
 //!
-//! Generation is two-phase. First, *pseudo-legal* moves are produced cheaply —
-//! pawns by bit-shifting masks, knights and the king by table lookup, and
-//! sliding pieces via the [magic bitboard](super::magic_tables) attack tables —
-//! without regard for whether they leave the mover's king in check. Then
+//! Moves are collected in a stack-allocated [`MoveList`], so generating them
+//! never touches the heap. Generation is two-phase. First, *pseudo-legal* moves
+//! are produced cheaply — pawns by bit-shifting masks, knights and the king by
+//! table lookup, and sliding pieces via the
+//! [magic bitboard](super::magic_tables) attack tables — without regard for
+//! whether they leave the mover's king in check. Then
 //! [`Board::generate_moves`] filters those down to *legal* moves with
 //! [`Board::would_check`].
 //!
-//! Castle generation lives in its own path so that check detection (which asks
-//! whether the king passes through an attacked square) does not recurse back
-//! into castle generation; that is why
-//! [`generate_pseudo_non_castle_moves`] exists separately from
-//! [`generate_pseudo_legal_moves`].
+//! Besides every legal move, the board offers each *category* on its own, so
+//! a caller that wants only some moves does not pay for the rest:
+//! [`Board::generate_captures`] (captures and queen promotions, what the
+//! quiescence search plays), [`Board::generate_quiets`] (everything else),
+//! [`Board::generate_evasions`] (the replies to a check) and
+//! [`Board::generate_quiet_checks`]. Captures and quiets partition the legal
+//! moves, each keeping the order [`Board::generate_moves`] gives them.
+//!
+//! Castle generation lives in its own path ([`castle_moves`]) so that check
+//! detection (which asks whether the king passes through an attacked square)
+//! does not recurse back into castle generation.
 
 use crate::chess_engine::computed_boards::{
     BISHOP_ATTACKS, BISHOP_BLOCKERS, BISHOP_MAGICS, ROOK_ATTACKS, ROOK_BLOCKERS, ROOK_MAGICS,
+    bishop_attacks, rook_attacks,
 };
 use crate::chess_engine::constants::{
-    B_KING_SIDE_BISHOP_START, B_KING_START, B_QUEEN_START, NORTH, NORTH_EAST, NORTH_WEST, SOUTH,
-    SOUTH_EAST, SOUTH_WEST, W_KING_SIDE_BISHOP_START, W_KING_START, W_QUEEN_START,
+    B_KING_ROOK_START, B_QUEEN_ROOK_START, NORTH, NORTH_EAST, NORTH_WEST, SOUTH, SOUTH_EAST,
+    SOUTH_WEST, W_KING_ROOK_START, W_QUEEN_ROOK_START,
 };
+use crate::chess_engine::make_move::castle_destinations;
 
 use super::bitboard::Bitboard;
 use super::board::{Board, Turn, WHITE};
 use super::computed_boards::{BETWEEN, BISHOP_RAYS, LINE, PAWN_ATTACKS, ROOK_RAYS};
 use super::computed_boards::{KING_RING_MOVES, KNIGHT_MOVES};
 use super::constants::EMPTY_BIT_B;
-use super::masks::{
-    B_KING_CASTLE_EMPTY, B_QUEEN_CASTLE_EMPTY, NOT_A_FILE, NOT_H_FILE, RANK_1, RANK_2, RANK_7,
-    RANK_8, W_KING_CASTLE_EMPTY, W_QUEEN_CASTLE_EMPTY,
-};
-use super::moves::{EN_PASSANT, Move, SpecialMove};
+use super::masks::{NOT_A_FILE, NOT_H_FILE, RANK_1, RANK_2, RANK_7, RANK_8};
+use super::move_list::MoveList;
+use super::moves::{CASTLING, EN_PASSANT, Move, SpecialMove};
 use super::piece::Piece;
 use super::position::Position;
 
@@ -50,19 +58,196 @@ impl Board {
     /// // twenty legal first moves: sixteen pawn pushes and four knight moves
     /// assert_eq!(board.generate_moves(WHITE).len(), 20);
     /// ```
-    pub fn generate_moves(&mut self, turn: Turn) -> Vec<Move> {
-        // Generate pseudo-legal moves, then keep the legal ones. Instead of a
-        // make/unmake check per move, a check mask and a pinned-piece set are
-        // computed once for the position (see [`check_and_pin_masks`]); most
-        // moves are then verified with O(1) bitboard tests. Only the irregular
-        // king, castle, and en-passant moves need a per-move attack test.
-        let mut moves = generate_pseudo_legal_moves(self, turn);
+    pub fn generate_moves(&mut self, turn: Turn) -> MoveList {
+        self.generate_legal(turn, GenType::All)
+    }
+
+    /// Returns `turn`'s legal captures (en passant and capturing promotions
+    /// included) and its non-capturing promotions to a queen.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::board::{Board, WHITE};
+    /// use sabertooth::chess_engine::utils::init_tables;
+    ///
+    /// init_tables();
+    /// let mut board = Board::from_fen("4k3/1P6/8/8/8/8/R6n/4K3 w - - 0 1").unwrap();
+    /// let captures: Vec<String> =
+    ///     board.generate_captures(WHITE).iter().map(|m| m.to_string()).collect();
+    /// // the rook takes the knight; the pawn promotes to a queen only
+    /// assert_eq!(captures, ["a2h2", "b7b8q"]);
+    /// ```
+    pub fn generate_captures(&mut self, turn: Turn) -> MoveList {
+        self.generate_legal(turn, GenType::Captures)
+    }
+
+    /// Returns `turn`'s legal moves that [`generate_captures`](Self::generate_captures)
+    /// leaves out: moves to empty squares, castles, and promotions to a knight,
+    /// bishop or rook without a capture.
+    pub fn generate_quiets(&mut self, turn: Turn) -> MoveList {
+        self.generate_legal(turn, GenType::Quiets)
+    }
+
+    /// Returns every legal move for `turn` when it is in check: king moves,
+    /// captures of a lone checker, and blocks of a sliding check. Cheaper than
+    /// [`generate_moves`](Self::generate_moves) there, since other moves are
+    /// never generated; out of check it returns the same moves.
+    pub fn generate_evasions(&mut self, turn: Turn) -> MoveList {
+        self.generate_legal(turn, GenType::Evasions)
+    }
+
+    /// Returns the [quiet moves](Self::generate_quiets) that give check,
+    /// directly or by discovery. `turn` must be the side to move, as each
+    /// move is made and unmade to test it.
+    pub fn generate_quiet_checks(&mut self, turn: Turn) -> MoveList {
+        debug_assert_eq!(turn, self.turn, "quiet checks are for the side to move");
+        let mut moves = self.generate_quiets(turn);
+        moves.retain(|&mv| {
+            self.commit_verified_move(mv);
+            let check = self.in_check(self.turn);
+            self.unmake_move();
+            check
+        });
+        moves
+    }
+
+    /// Returns `true` if `mv` is a pseudo-legal move for the side to move:
+    /// one that [`generate_moves`](Self::generate_moves) would produce, except
+    /// that it may leave the mover's king in check. Any 16-bit [`Move`] is
+    /// accepted, so a move from elsewhere — a transposition-table entry, a
+    /// killer slot, an opening book — can be checked without generating the
+    /// position's moves.
+    #[must_use]
+    pub fn is_pseudo_legal(&self, mv: Move) -> bool {
+        let turn = self.turn;
+        let (origin, dest) = mv.get_org_and_dest();
+        let (from, to) = (origin.as_usize(), dest.as_usize());
+        let Some((piece, owner)) = self.get_piece_at(origin) else {
+            return false;
+        };
+        let special = mv.get_special_move();
+        // only a promotion sets the promotion bits
+        if owner != turn
+            || (special != SpecialMove::Promotion && mv.get_promotion() != Piece::Queen)
+        {
+            return false;
+        }
+
+        match special {
+            SpecialMove::Castle => {
+                let mut castles = MoveList::new();
+                if piece == Piece::King {
+                    castle_moves(self, turn, &mut castles);
+                }
+                castles.contains(&mv)
+            }
+            SpecialMove::EnPassant => {
+                piece == Piece::Pawn
+                    && (PAWN_ATTACKS[usize::from(turn)][from] & self.en_passant).is_square_set(to)
+            }
+            SpecialMove::Promotion | SpecialMove::NormalMove => {
+                if self.player_boards[usize::from(turn)].is_square_set(to) {
+                    return false;
+                }
+                let occupied = !self.empty_tiles;
+                let attacks = match piece {
+                    Piece::Pawn => return self.is_pawn_move(mv, turn),
+                    Piece::Knight => KNIGHT_MOVES[from],
+                    Piece::King => KING_RING_MOVES[from],
+                    Piece::Bishop => bishop_attacks(from, occupied),
+                    Piece::Rook => rook_attacks(from, occupied),
+                    Piece::Queen => bishop_attacks(from, occupied) | rook_attacks(from, occupied),
+                    Piece::None => Bitboard::new(),
+                };
+                special == SpecialMove::NormalMove && attacks.is_square_set(to)
+            }
+        }
+    }
+
+    /// Returns `true` if `mv` is a legal move for the side to move. Like
+    /// [`is_pseudo_legal`](Self::is_pseudo_legal) it takes any 16-bit value,
+    /// and it answers as [`generate_moves`](Self::generate_moves) would, at the
+    /// cost of one move's checks rather than the whole position's.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::board::Board;
+    /// use sabertooth::chess_engine::moves::Move;
+    /// use sabertooth::chess_engine::position::Position;
+    /// use sabertooth::chess_engine::utils::init_tables;
+    ///
+    /// init_tables();
+    /// // the knight on e2 is pinned by the rook on e8
+    /// let mut board = Board::from_fen("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+    /// let (e2, c3, e5) = (Position::new(12), Position::new(18), Position::new(36));
+    /// assert!(board.is_pseudo_legal(Move::new_default(e2, c3)));
+    /// assert!(!board.is_legal(Move::new_default(e2, c3)));
+    /// // a knight never moves like a rook
+    /// assert!(!board.is_pseudo_legal(Move::new_default(e2, e5)));
+    /// ```
+    pub fn is_legal(&mut self, mv: Move) -> bool {
+        if !self.is_pseudo_legal(mv) {
+            return false;
+        }
+        let turn = self.turn;
+        let king_sq = self.get_piece_bitboard(Piece::King, turn).trailing_zeros();
+        let masks = self.check_and_pin_masks(turn, king_sq);
+        self.is_move_legal(mv, turn, king_sq, &masks)
+    }
+
+    /// Whether the pawn move (push, double push or capture, promoting or not)
+    /// `mv` is pseudo-legal for `turn`, whose pawn stands on its origin.
+    fn is_pawn_move(&self, mv: Move, turn: Turn) -> bool {
+        let (origin, dest) = mv.get_org_and_dest();
+        let to = dest.as_usize();
+        let promotion_rank = if turn == WHITE { RANK_8 } else { RANK_1 };
+        if (mv.get_special_move() == SpecialMove::Promotion) != promotion_rank.is_square_set(to) {
+            return false;
+        }
+        if self.player_boards[usize::from(!turn)].is_square_set(to) {
+            return PAWN_ATTACKS[usize::from(turn)][origin.as_usize()].is_square_set(to);
+        }
+        let direction = if turn == WHITE { NORTH } else { SOUTH };
+        let start_rank = if turn == WHITE { RANK_2 } else { RANK_7 };
+        let single = (origin.bitboard() << direction) & self.empty_tiles;
+        let double = ((single & (start_rank << direction)) << direction) & self.empty_tiles;
+        (single | double).is_square_set(to)
+    }
+
+    /// The legal moves of one category. Pseudo-legal moves are generated and
+    /// then filtered: instead of a make/unmake check per move, a check mask and
+    /// a pinned-piece set are computed once for the position (see
+    /// `check_and_pin_masks`), and most moves are then verified with O(1)
+    /// bitboard tests. Only the irregular king, castle, and en-passant moves
+    /// need a per-move attack test.
+    fn generate_legal(&mut self, turn: Turn, kind: GenType) -> MoveList {
         let king_sq = self.get_piece_bitboard(Piece::King, turn).trailing_zeros();
         let masks = self.check_and_pin_masks(turn, king_sq);
+        let mut moves = MoveList::new();
+        generate_pseudo_legal_moves(self, turn, kind, &masks, &mut moves);
         moves.retain(|m| self.is_move_legal(*m, turn, king_sq, &masks));
         moves
     }
 
+    /// The castle of `turn` on the given side, encoded king-takes-rook for
+    /// Chess960 (or whenever king or rook is off its standard square, where the
+    /// two-square step would be ambiguous) and as the king's two-square step
+    /// otherwise.
+    fn castle_move(&self, turn: Turn, king_side: bool) -> Move {
+        let king = Position::new(self.get_piece_bitboard(Piece::King, turn).trailing_zeros());
+        let rook = self.castling_rook(turn, king_side);
+        let standard = Move::new_castle(king_side, turn);
+        let standard_rook = match (turn == WHITE, king_side) {
+            (true, true) => W_KING_ROOK_START,
+            (true, false) => W_QUEEN_ROOK_START,
+            (false, true) => B_KING_ROOK_START,
+            (false, false) => B_QUEEN_ROOK_START,
+        };
+        if self.chess960 || king != standard.get_origin() || rook != standard_rook {
+            Move::new_special(king, rook, CASTLING)
+        } else {
+            standard
+        }
+    }
+
     /// Decides whether a single pseudo-legal move is legal, given the
     /// position's [`CheckPinMasks`] and the mover's `king_sq`.
     fn is_move_legal(
@@ -175,26 +360,52 @@ struct CheckPinMasks {
     num_checkers: u32,
 }
 
-/// Generates all pseudo-legal moves for `turn`, including castling. "Pseudo-legal"
-/// means the mover's king may be left in check — callers must filter those out.
-pub fn generate_pseudo_legal_moves(board: &mut Board, turn: Turn) -> Vec<Move> {
-    let mut moves = generate_pseudo_non_castle_moves(board, turn);
-    castle_moves(board, turn, &mut moves);
-    moves
+/// The categories of moves a generator can be asked for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GenType {
+    /// Every move.
+    All,
+    /// Captures and queen promotions.
+    Captures,
+    /// Every move that is not in [`Captures`](Self::Captures).
+    Quiets,
+    /// Moves that may answer a check: the king's, and other pieces' moves
+    /// onto the check mask.
+    Evasions,
 }
 
-/// Generates pseudo-legal moves *excluding* castling. Used by attack/check
-/// detection, where including castling would recurse infinitely.
-pub fn generate_pseudo_non_castle_moves(board: &Board, turn: Turn) -> Vec<Move> {
-    // skip castle moves, used when checking for checks to avoid inf loop
-    // capacity tuned to comfortably hold a typical position's move count so the
-    // sub-generators never reallocate mid-fill
-    let mut moves = Vec::with_capacity(48);
-    sliding_pieces_moves(board, turn, &mut moves);
-    knight_moves(board, turn, &mut moves);
-    king_ring_moves(board, turn, &mut moves);
-    pawn_moves(board, turn, &mut moves);
-    moves
+/// Appends `turn`'s pseudo-legal moves of category `kind` to `moves`.
+/// "Pseudo-legal" means the mover's king may be left in check — callers must
+/// filter those out. `masks` narrows evasions to the squares that can answer
+/// the check.
+fn generate_pseudo_legal_moves(
+    board: &Board,
+    turn: Turn,
+    kind: GenType,
+    masks: &CheckPinMasks,
+    moves: &mut MoveList,
+) {
+    let own_pieces = board.player_boards[usize::from(turn)];
+    let (targets, king_targets) = match kind {
+        GenType::All => (!own_pieces, !own_pieces),
+        GenType::Captures => {
+            let enemy = board.player_boards[usize::from(!turn)];
+            (enemy, enemy)
+        }
+        GenType::Quiets => (board.empty_tiles, board.empty_tiles),
+        // in double check only the king can move
+        GenType::Evasions if masks.num_checkers >= 2 => (Bitboard::new(), !own_pieces),
+        GenType::Evasions => (masks.check_mask & !own_pieces, !own_pieces),
+    };
+    sliding_pieces_moves(board, turn, targets, moves);
+    knight_moves(board, turn, targets, moves);
+    king_ring_moves(board, turn, king_targets, moves);
+    if kind != GenType::Evasions || masks.num_checkers < 2 {
+        pawn_moves(board, turn, kind, masks.check_mask, moves);
+    }
+    if matches!(kind, GenType::All | GenType::Quiets) {
+        castle_moves(board, turn, moves);
+    }
 }
 
 /// Walks the four `deltas` outward from `pos`, stopping each ray at the first
@@ -220,7 +431,7 @@ pub fn get_sliding_moves(deltas: &[(i8, i8); 4], pos: Position, blockers: Bitboa
 }
 
 /// Pushes one normal [`Move`] from `origin` to each set square in `bit_board`.
-fn extract_moves(mut bit_board: Bitboard, origin: usize, moves: &mut Vec<Move>) {
+fn extract_moves(mut bit_board: Bitboard, origin: usize, moves: &mut MoveList) {
     while bit_board.is_not_empty() {
         let dest = bit_board.trailing_zeros();
 
@@ -236,7 +447,7 @@ fn extract_moves(mut bit_board: Bitboard, origin: usize, moves: &mut Vec<Move>)
 /// Pushes one pawn [`Move`] per set destination square in `bit_board`,
 /// recovering each origin by subtracting `shift` from the destination index.
 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-fn extract_pawn_moves(mut bit_board: Bitboard, shift: i8, moves: &mut Vec<Move>) {
+fn extract_pawn_moves(mut bit_board: Bitboard, shift: i8, moves: &mut MoveList) {
     // Extract moves from bitboard representation
     // and push them to the moves vector
     while bit_board.is_not_empty() {
@@ -252,38 +463,68 @@ fn extract_pawn_moves(mut bit_board: Bitboard, shift: i8, moves: &mut Vec<Move>)
     }
 }
 
-/// Like [`extract_pawn_moves`], but emits all four promotion moves per
-/// destination square (used for pawns reaching the last rank).
+/// Like [`extract_pawn_moves`], but emits the promotion moves per destination
+/// square (used for pawns reaching the last rank): to a queen if `queen` is
+/// set, and to a knight, bishop and rook if `under` is.
 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-fn extract_promotions(mut bit_board: Bitboard, shift: i8, moves: &mut Vec<Move>) {
+fn extract_promotions(
+    mut bit_board: Bitboard,
+    shift: i8,
+    queen: bool,
+    under: bool,
+    moves: &mut MoveList,
+) {
     while bit_board.is_not_empty() {
         let dest = bit_board.trailing_zeros();
         let origin = (dest as i8 - shift) as usize;
-        moves.extend(Move::new_promote(
-            Position::new(origin),
-            Position::new(dest),
-        ));
+        moves.extend(
+            Move::new_promote(Position::new(origin), Position::new(dest))
+                .into_iter()
+                .filter(|mv| {
+                    if mv.get_promotion() == Piece::Queen {
+                        queen
+                    } else {
+                        under
+                    }
+                }),
+        );
         bit_board.reset_lsb();
     }
 }
 
-/// Generates all pawn moves for `turn`: single and double pushes, diagonal
-/// captures, en-passant captures, and promotions, computed with file-masked
-/// bit shifts.
+/// Generates `turn`'s pawn moves of category `kind`: single and double
+/// pushes, diagonal captures, en-passant captures, and promotions, computed
+/// with file-masked bit shifts. Evasions other than en passant are limited to
+/// `check_mask`.
 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-fn pawn_moves(board: &Board, turn: Turn, moves: &mut Vec<Move>) {
+fn pawn_moves(
+    board: &Board,
+    turn: Turn,
+    kind: GenType,
+    check_mask: Bitboard,
+    moves: &mut MoveList,
+) {
+    let captures = kind != GenType::Quiets;
+    let quiets = kind != GenType::Captures;
+    let targets = if kind == GenType::Evasions {
+        check_mask
+    } else {
+        Bitboard::full()
+    };
     let direction = if turn == WHITE { NORTH } else { SOUTH };
     let start_rank = if turn == WHITE { RANK_2 } else { RANK_7 };
     let promotion_rank = if turn == WHITE { RANK_8 } else { RANK_1 };
     let enemy_pieces = board.player_boards[usize::from(!turn)];
 
     let pawns = board.get_piece_bitboard(Piece::Pawn, turn);
-    let forward_moves = (pawns << direction) & board.empty_tiles;
+    let forward_moves = (pawns << direction) & board.empty_tiles & targets;
 
     // double move
     let start_pawns = pawns & start_rank;
-    let double_moves =
-        start_pawns << (2 * direction) & board.empty_tiles & (board.empty_tiles << direction);
+    let double_moves = start_pawns << (2 * direction)
+        & board.empty_tiles
+        & (board.empty_tiles << direction)
+        & targets;
 
     //capture moves
     let r_dir = if turn == WHITE {
@@ -296,12 +537,18 @@ fn pawn_moves(board: &Board, turn: Turn, moves: &mut Vec<Move>) {
     } else {
         SOUTH_WEST
     };
-    let right_captures = ((pawns & NOT_H_FILE) << r_dir) & enemy_pieces;
-    let left_captures = ((pawns & NOT_A_FILE) << l_dir) & enemy_pieces;
+    let right_captures = ((pawns & NOT_H_FILE) << r_dir) & enemy_pieces & targets;
+    let left_captures = ((pawns & NOT_A_FILE) << l_dir) & enemy_pieces & targets;
 
-    // en passant
-    let right_en_p = ((pawns & NOT_H_FILE) << r_dir) & board.en_passant;
-    let left_en_p = ((pawns & NOT_A_FILE) << l_dir) & board.en_passant;
+    // en passant (an evasion when it takes the checking pawn, which is off
+    // the destination square, so it is left to the legality filter)
+    let en_passant = if captures {
+        board.en_passant
+    } else {
+        Bitboard::new()
+    };
+    let right_en_p = ((pawns & NOT_H_FILE) << r_dir) & en_passant;
+    let left_en_p = ((pawns & NOT_A_FILE) << l_dir) & en_passant;
     // TODO maybe rewrite to separate function
     if left_en_p.is_not_empty() {
         let dest = left_en_p.trailing_zeros();
@@ -325,20 +572,28 @@ fn pawn_moves(board: &Board, turn: Turn, moves: &mut Vec<Move>) {
     }
 
     // extract moves
-    extract_pawn_moves(double_moves, 2 * direction, moves);
-    extract_pawn_moves(forward_moves & !promotion_rank, direction, moves);
-    extract_pawn_moves(right_captures & !promotion_rank, r_dir, moves);
-    extract_pawn_moves(left_captures & !promotion_rank, l_dir, moves);
-
-    // promotion handle separately
-    extract_promotions(forward_moves & promotion_rank, direction, moves);
-    extract_promotions(right_captures & promotion_rank, r_dir, moves);
-    extract_promotions(left_captures & promotion_rank, l_dir, moves);
+    if quiets {
+        extract_pawn_moves(double_moves, 2 * direction, moves);
+        extract_pawn_moves(forward_moves & !promotion_rank, direction, moves);
+    }
+    if captures {
+        extract_pawn_moves(right_captures & !promotion_rank, r_dir, moves);
+        extract_pawn_moves(left_captures & !promotion_rank, l_dir, moves);
+    }
+
+    // promotion handle separately; a push to a queen counts as a capture
+    let pushes = forward_moves & promotion_rank;
+    extract_promotions(pushes, direction, captures, quiets, moves);
+    if captures {
+        extract_promotions(right_captures & promotion_rank, r_dir, true, true, moves);
+        extract_promotions(left_captures & promotion_rank, l_dir, true, true, moves);
+    }
 }
 
-/// Generates rook, bishop, and queen moves for `turn` (the queen contributes to
-/// both the rook-like and bishop-like rays) using the magic attack tables.
-fn sliding_pieces_moves(board: &Board, turn: Turn, moves: &mut Vec<Move>) {
+/// Generates rook, bishop, and queen moves for `turn` onto `targets` (the
+/// queen contributes to both the rook-like and bishop-like rays) using the
+/// magic attack tables.
+fn sliding_pieces_moves(board: &Board, turn: Turn, targets: Bitboard, moves: &mut MoveList) {
     let queen_board = board.get_piece_bitboard(Piece::Queen, turn);
     // add queen board
     let mut rook_board = board.get_piece_bitboard(Piece::Rook, turn) | queen_board;
@@ -349,7 +604,7 @@ fn sliding_pieces_moves(board: &Board, turn: Turn, moves: &mut Vec<Move>) {
         let magic_entry = ROOK_MAGICS[origin];
         let moves_bb =
             ROOK_ATTACKS[magic_entry.magic_index(relevant_blockers) + magic_entry.offset];
-        let legal_bb = moves_bb & !board.player_boards[usize::from(turn)];
+        let legal_bb = moves_bb & targets;
         extract_moves(legal_bb, origin, moves);
         rook_board.reset_lsb();
     }
@@ -361,71 +616,72 @@ fn sliding_pieces_moves(board: &Board, turn: Turn, moves: &mut Vec<Move>) {
         let magic_entry = BISHOP_MAGICS[origin];
         let moves_bb =
             BISHOP_ATTACKS[magic_entry.magic_index(relevant_blockers) + magic_entry.offset];
-        let legal_bb = moves_bb & !board.player_boards[usize::from(turn)];
+        let legal_bb = moves_bb & targets;
         extract_moves(legal_bb, origin, moves);
         bishop_board.reset_lsb();
     }
 }
 
-/// Generates knight moves for `turn` from the precomputed [`KNIGHT_MOVES`] table.
-fn knight_moves(board: &Board, turn: Turn, moves: &mut Vec<Move>) {
+/// Generates knight moves for `turn` onto `targets` from the precomputed
+/// [`KNIGHT_MOVES`] table.
+fn knight_moves(board: &Board, turn: Turn, targets: Bitboard, moves: &mut MoveList) {
     let mut knight_board = board.get_piece_bitboard(Piece::Knight, turn);
-    let not_my_pieces = !board.player_boards[usize::from(turn)];
     while knight_board.is_not_empty() {
         let origin = knight_board.trailing_zeros();
-        let moves_bb = KNIGHT_MOVES[origin] & not_my_pieces;
+        let moves_bb = KNIGHT_MOVES[origin] & targets;
         extract_moves(moves_bb, origin, moves);
         knight_board.reset_lsb();
     }
 }
 
-/// Generates the king's one-square moves for `turn` from the precomputed
-/// [`KING_RING_MOVES`] table (castling is handled by [`castle_moves`]).
-fn king_ring_moves(board: &Board, turn: Turn, moves: &mut Vec<Move>) {
+/// Generates the king's one-square moves for `turn` onto `targets` from the
+/// precomputed [`KING_RING_MOVES`] table (castling is handled by
+/// [`castle_moves`]).
+fn king_ring_moves(board: &Board, turn: Turn, targets: Bitboard, moves: &mut MoveList) {
     let king_board = board.get_piece_bitboard(Piece::King, turn);
-    let not_my_pieces = !board.player_boards[usize::from(turn)];
     let origin = king_board.trailing_zeros();
 
-    let ring_moves = KING_RING_MOVES[origin] & not_my_pieces;
+    let ring_moves = KING_RING_MOVES[origin] & targets;
     extract_moves(ring_moves, origin, moves);
 }
 
-/// Adds castling moves for `turn` when legal: the right is still held, the
-/// squares between king and rook are empty, the king is not in check, and it
-/// does not pass through an attacked square.
-fn castle_moves(board: &mut Board, turn: Turn, moves: &mut Vec<Move>) {
-    let castle_data = [
-        (
-            false,
-            [W_QUEEN_START, B_QUEEN_START],
-            [W_QUEEN_CASTLE_EMPTY, B_QUEEN_CASTLE_EMPTY],
-        ),
-        (
-            true,
-            [W_KING_SIDE_BISHOP_START, B_KING_SIDE_BISHOP_START],
-            [W_KING_CASTLE_EMPTY, B_KING_CASTLE_EMPTY],
-        ),
-    ];
-    if !board.in_check(turn) {
-        for (king_side, move_over_tile, empty_tiles) in castle_data {
-            if !board.castle_rights.can_castle(turn, king_side) {
-                continue;
-            }
-            let empty_space = empty_tiles[usize::from(turn)];
-            // check that space between rook and king is empty
-            if (empty_space & board.empty_tiles) == empty_space {
-                // tile between king and destination square is not under attack
-                let king_pos = if turn == WHITE {
-                    W_KING_START
-                } else {
-                    B_KING_START
-                };
-                let slide_tile = move_over_tile[usize::from(turn)];
-                let slide_move = Move::new_default(king_pos, slide_tile);
-                if !board.would_check(slide_move) {
-                    moves.push(Move::new_castle(king_side, turn));
-                }
-            }
+/// Adds castling moves for `turn` when they are pseudo-legal: the right is
+/// still held, its rook is in place, every square the king and the rook cross
+/// or land on is empty (apart from the two pieces themselves), the king is not
+/// in check, and it does not pass through an attacked square. These are the
+/// Chess960 rules, which reduce to the usual ones for the standard setup. The
+/// king's destination is checked later, with the whole move made.
+fn castle_moves(board: &Board, turn: Turn, moves: &mut MoveList) {
+    if board.in_check(turn) {
+        return;
+    }
+    let king = board.get_piece_bitboard(Piece::King, turn).trailing_zeros();
+    let rooks = board.get_piece_bitboard(Piece::Rook, turn);
+    for king_side in [false, true] {
+        if !board.castle_rights.can_castle(turn, king_side) {
+            continue;
+        }
+        let rook = board.castling_rook(turn, king_side).as_usize();
+        if !rooks.is_square_set(rook) {
+            continue;
+        }
+        let (king_dest, rook_dest) = castle_destinations(turn, king_side);
+        let (king_dest, rook_dest) = (king_dest.as_usize(), rook_dest.as_usize());
+
+        let mut path = BETWEEN[king][king_dest] | BETWEEN[rook][rook_dest];
+        path.set_square(king_dest);
+        path.set_square(rook_dest);
+        path.clear_square(king);
+        path.clear_square(rook);
+        if (path & !board.empty_tiles).is_not_empty() {
+            continue;
+        }
+
+        let safe = BETWEEN[king][king_dest]
+            .iter_set_bits()
+            .all(|square| !board.is_square_attacked(square, !turn));
+        if safe {
+            moves.push(board.castle_move(turn, king_side));
         }
     }
 }
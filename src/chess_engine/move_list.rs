@@ -0,0 +1,208 @@
+//! [`MoveList`], the fixed-capacity list move generation fills.
+//!
+//! Moves are generated at every node of the search and of perft, so the list
+//! lives on the stack: an array of [`MAX_MOVES`] moves (no legal position has
+//! more than 218) and a length, rather than a heap-allocated `Vec`. Alongside
+//! each move it keeps an ordering score, which the search fills in and sorts
+//! by, so ordering needs no allocation either.
+//!
+//! The list dereferences to a slice of its moves, so `len`, `iter`,
+//! `contains`, indexing and the like work as on a `Vec`; moving elements
+//! through the slice leaves their scores behind, so reorder with
+//! [`sort_by_score`](MoveList::sort_by_score) instead.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use super::moves::Move;
+
+/// Capacity of a [`MoveList`]; at least the most moves any position has.
+pub const MAX_MOVES: usize = 256;
+
+/// A stack-allocated list of up to [`MAX_MOVES`] moves, each with an ordering
+/// score. See the [module documentation](self).
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    scores: [i32; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    /// Creates an empty list.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            moves: [Move::make_raw(0); MAX_MOVES],
+            scores: [0; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    /// Appends `mv` with a score of `0`.
+    ///
+    /// # Panics
+    /// Panics if the list is already full.
+    #[inline]
+    pub const fn push(&mut self, mv: Move) {
+        self.moves[self.len] = mv;
+        self.scores[self.len] = 0;
+        self.len += 1;
+    }
+
+    /// Keeps only the moves for which `keep` returns `true`, in their order,
+    /// with their scores.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Move) -> bool) {
+        let mut kept = 0;
+        for index in 0..self.len {
+            if keep(&self.moves[index]) {
+                self.moves[kept] = self.moves[index];
+                self.scores[kept] = self.scores[index];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+
+    /// Removes every move.
+    pub const fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// The ordering score of the move at `index`.
+    #[must_use]
+    pub fn score(&self, index: usize) -> i32 {
+        self.scores[..self.len][index]
+    }
+
+    /// Sets the ordering score of the move at `index`.
+    pub fn set_score(&mut self, index: usize, score: i32) {
+        self.scores[..self.len][index] = score;
+    }
+
+    /// Sorts the moves by descending score. The sort is stable, so moves of
+    /// equal score keep their generation order.
+    pub fn sort_by_score(&mut self) {
+        // insertion sort: lists are short, and it is stable and in place
+        for index in 1..self.len {
+            let (mv, score) = (self.moves[index], self.scores[index]);
+            let mut slot = index;
+            while slot > 0 && self.scores[slot - 1] < score {
+                self.moves[slot] = self.moves[slot - 1];
+                self.scores[slot] = self.scores[slot - 1];
+                slot -= 1;
+            }
+            self.moves[slot] = mv;
+            self.scores[slot] = score;
+        }
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Extend<Move> for MoveList {
+    fn extend<I: IntoIterator<Item = Move>>(&mut self, moves: I) {
+        for mv in moves {
+            self.push(mv);
+        }
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<I: IntoIterator<Item = Move>>(moves: I) -> Self {
+        let mut list = Self::new();
+        list.extend(moves);
+        list
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter {
+            list: self,
+            next: 0,
+        }
+    }
+}
+
+/// The moves of a [`MoveList`], by value, in order.
+pub struct IntoIter {
+    list: MoveList,
+    next: usize,
+}
+
+impl Iterator for IntoIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let mv = self.list.get(self.next).copied();
+        self.next += 1;
+        mv
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len().saturating_sub(self.next);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+#[cfg(test)]
+mod tests {
+    use super::MoveList;
+    use crate::chess_engine::moves::Move;
+
+    #[test]
+    fn retains_and_sorts_with_scores() {
+        let mut list: MoveList = (1..=6).map(Move::make_raw).collect();
+        for index in 0..list.len() {
+            list.set_score(index, [5, 1, 7, 5, 0, 7][index]);
+        }
+        list.retain(|mv| mv.get_raw() != 5);
+        assert_eq!(list.len(), 5);
+
+        list.sort_by_score();
+        let order: Vec<u16> = list.iter().map(Move::get_raw).collect();
+        // ties keep their order: 3 before 6, 1 before 4
+        assert_eq!(order, [3, 6, 1, 4, 2]);
+        assert_eq!(list.score(0), 7);
+        assert_eq!(list.into_iter().len(), 5);
+    }
+}
@@ -209,7 +209,9 @@ impl Move {
 
 /// Formats the move in long algebraic / UCI notation: origin and destination
 /// squares, with the promotion piece letter appended for promotions (e.g.
-/// `"e2e4"`, `"e7e8q"`).
+/// `"e2e4"`, `"e7e8q"`). SAN depends on the position, so it is written by
+/// [`Board::move_to_san`](crate::chess_engine::board::Board::move_to_san)
+/// instead.
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -0,0 +1,866 @@
+//! [Portable Game Notation][pgn] (PGN): reading and writing full game records.
+//!
+//! A PGN game is a *tag-pair section* (`[Event "..."]`, `[White "..."]`, …)
+//! followed by *movetext*: SAN moves with optional move numbers, `{...}` and
+//! `;` comments, numeric annotation glyphs (`$1`, or the `!`/`?` shorthands),
+//! and parenthesised *recursive annotation variations* that offer an
+//! alternative to the preceding move. A termination marker (`1-0`, `0-1`,
+//! `1/2-1/2`, `*`) ends the game.
+//!
+//! [`parse_pgn`] turns a file's worth of text into [`PgnGame`]s. Every move is
+//! resolved against the position with [`Board::parse_san`], so a parsed game is
+//! a tree of legal [`Move`]s: [`PgnGame::moves`] is the main line and each
+//! [`PgnNode`] carries its own alternative lines in
+//! [`variations`](PgnNode::variations). The main line replays on a [`Board`]
+//! with [`commit_verified_move`](Board::commit_verified_move); see
+//! [`PgnGame::end_board`].
+//!
+//! Writing goes through the [`Display`](fmt::Display) impl, which emits the
+//! tags, canonical SAN with move numbers, `$n` NAGs, comments, and variations,
+//! wrapped at 80 columns. Engine evaluations ([`PgnEval`]) are written in the
+//! common `{+0.35/12}` comment form (score in pawns from the mover's point of
+//! view, then the search depth) and recognised again when read back.
+//!
+//! [pgn]: https://www.chessprogramming.org/Portable_Game_Notation
+
+use std::fmt;
+
+use crate::chess_engine::{
+    board::{Board, WHITE},
+    engine::search::{MATE_SCORE, MATE_THRESHOLD},
+    moves::Move,
+};
+
+pub use crate::chess_engine::game_state::GameResult;
+
+/// Column at which written movetext is wrapped.
+const LINE_WIDTH: usize = 80;
+
+/// An engine evaluation attached to a move, written as a `{+0.35/12}` comment.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PgnEval {
+    /// The search score from the perspective of the side that played the move,
+    /// in centipawns or as a mate score (see [`MATE_SCORE`]).
+    pub score: i32,
+    /// The depth the search reached.
+    pub depth: u8,
+}
+
+impl PgnEval {
+    /// Parses the leading `+0.35/12` or `-M3/20` word of a comment.
+    #[allow(clippy::cast_possible_truncation)]
+    fn parse(word: &str) -> Option<Self> {
+        let (score, depth) = word.split_once('/')?;
+        let depth = depth.parse::<u8>().ok()?;
+        let (negative, magnitude) = match score.as_bytes().first()? {
+            b'-' => (true, &score[1..]),
+            b'+' => (false, &score[1..]),
+            _ => (false, score),
+        };
+        let score = if let Some(moves) = magnitude.strip_prefix('M') {
+            let moves = moves.parse::<i32>().ok()?;
+            // mate in n moves is 2n - 1 plies for the winner, 2n for the loser
+            if negative {
+                -(MATE_SCORE - 2 * moves)
+            } else {
+                MATE_SCORE - (2 * moves - 1)
+            }
+        } else {
+            if !magnitude.starts_with(|c: char| c.is_ascii_digit()) {
+                return None;
+            }
+            let pawns = magnitude.parse::<f64>().ok()?;
+            let centipawns = (pawns * 100.0).round() as i32;
+            if negative { -centipawns } else { centipawns }
+        };
+        Some(Self { score, depth })
+    }
+}
+
+/// Formats the evaluation as `+0.35/12`, or `+M3/12` for a forced mate.
+impl fmt::Display for PgnEval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.score < 0 { '-' } else { '+' };
+        let magnitude = self.score.abs();
+        if magnitude >= MATE_THRESHOLD {
+            let moves = (MATE_SCORE - magnitude + 1) / 2;
+            write!(f, "{sign}M{moves}/{}", self.depth)
+        } else {
+            write!(
+                f,
+                "{sign}{}.{:02}/{}",
+                magnitude / 100,
+                magnitude % 100,
+                self.depth
+            )
+        }
+    }
+}
+
+/// One move of a game tree together with its annotations.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PgnNode {
+    /// The move, legal in the position reached by the line leading up to it.
+    pub mv: Move,
+    /// Numeric annotation glyphs (`$1` = good move, `$2` = mistake, …).
+    pub nags: Vec<u8>,
+    /// A comment written before the move, if any. PGN has no way to escape a
+    /// `}` inside a `{...}` comment, so one holding a `}` is written as a `;`
+    /// comment running to the end of its line instead.
+    pub pre_comment: Option<String>,
+    /// A comment written after the move, if any (excluding the evaluation).
+    /// Written like [`pre_comment`](Self::pre_comment).
+    pub comment: Option<String>,
+    /// An engine evaluation recorded for the move.
+    pub eval: Option<PgnEval>,
+    /// Alternative lines, each starting from the position *before* this move.
+    pub variations: Vec<Vec<Self>>,
+}
+
+impl PgnNode {
+    /// A bare node holding just `mv`.
+    #[must_use]
+    pub const fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            nags: Vec::new(),
+            pre_comment: None,
+            comment: None,
+            eval: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// A complete game record: tag pairs, the move tree, and the result.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PgnGame {
+    /// The tag pairs, in file order. [`new`](Self::new) seeds the standard
+    /// seven-tag roster.
+    pub tags: Vec<(String, String)>,
+    /// The main line; alternatives hang off its nodes.
+    pub moves: Vec<PgnNode>,
+    /// The game's result, written as the termination marker.
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    /// Starts an empty game from `start`, with the seven-tag roster filled with
+    /// the PGN placeholders. A non-standard start position is recorded in the
+    /// `SetUp`/`FEN` tags.
+    ///
+    /// # Panics
+    ///
+    /// Never: the built-in start position always parses.
+    #[must_use]
+    pub fn new(start: &Board) -> Self {
+        let mut game = Self {
+            tags: [
+                ("Event", "?"),
+                ("Site", "?"),
+                ("Date", "????.??.??"),
+                ("Round", "?"),
+                ("White", "?"),
+                ("Black", "?"),
+                ("Result", "*"),
+            ]
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        };
+        let fen = start.to_fen();
+        if fen != Board::new_start_pos().expect("start position").to_fen() {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &fen);
+        }
+        game
+    }
+
+    /// Returns the value of the tag `name`, if present.
+    #[must_use]
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the tag `name`, replacing an existing value in place or appending a
+    /// new pair.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        if let Some(pair) = self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            pair.1 = value.to_string();
+        } else {
+            self.tags.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    /// Sets the result, keeping the `Result` tag in step.
+    pub fn set_result(&mut self, result: GameResult) {
+        self.result = result;
+        self.set_tag("Result", &result.to_string());
+    }
+
+    /// Appends `mv` (legal at the end of the main line) with an optional engine
+    /// evaluation; the building block for recording self-play games.
+    pub fn push_move(&mut self, mv: Move, eval: Option<PgnEval>) {
+        let mut node = PgnNode::new(mv);
+        node.eval = eval;
+        self.moves.push(node);
+    }
+
+    /// The position the game starts from: the `FEN` tag if present, otherwise
+    /// the standard start position.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the `FEN` tag does not parse.
+    pub fn start_board(&self) -> Result<Board, String> {
+        let board = self
+            .tag("FEN")
+            .map_or_else(Board::new_start_pos, Board::from_fen)?;
+        Ok(board)
+    }
+
+    /// Replays the main line from [`start_board`](Self::start_board) and returns
+    /// the final position, with every move on its undo history.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the start position does not parse.
+    pub fn end_board(&self) -> Result<Board, String> {
+        let mut board = self.start_board()?;
+        for node in &self.moves {
+            board.commit_verified_move(node.mv);
+        }
+        Ok(board)
+    }
+
+    /// Parses text holding exactly one game (see [`parse_pgn`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the text is not valid PGN, contains an illegal or
+    /// ambiguous move, or does not hold exactly one game.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::pgn::{GameResult, PgnGame};
+    /// use sabertooth::chess_engine::utils::init_tables;
+    ///
+    /// init_tables();
+    /// let game = PgnGame::from_pgn(
+    ///     "[White \"Morphy\"]\n\n1. e4 e5 2. Nf3 d6 (2... Nc6) 3. d4 {the Philidor} 1-0",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(game.tag("White"), Some("Morphy"));
+    /// assert_eq!(game.moves.len(), 5);
+    /// assert_eq!(game.moves[3].variations.len(), 1);
+    /// assert_eq!(game.result, GameResult::WhiteWins);
+    /// ```
+    pub fn from_pgn(text: &str) -> Result<Self, String> {
+        let mut games = parse_pgn(text)?;
+        if games.len() != 1 {
+            return Err(format!("expected one game, found {}", games.len()));
+        }
+        Ok(games.remove(0))
+    }
+}
+
+/// Writes the game in PGN export format: tags, a blank line, then the
+/// movetext wrapped at 80 columns and ended by the result.
+///
+/// # Panics
+///
+/// Panics if the `FEN` tag does not parse (a game built by [`PgnGame::new`] or
+/// [`parse_pgn`] always has a valid one).
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.tags {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{name} \"{escaped}\"]")?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(f)?;
+        }
+
+        let mut board = self.start_board().expect("game has an invalid FEN tag");
+        let mut tokens = Vec::new();
+        write_line(&mut board, &self.moves, &mut tokens);
+        tokens.push(self.result.to_string());
+
+        // join tokens with single spaces (none just inside parentheses),
+        // breaking lines before they exceed LINE_WIDTH
+        let mut line = String::new();
+        for token in tokens {
+            let glue = !(line.is_empty() || line.ends_with('(') || token == ")");
+            if glue && line.len() + 1 + token.len() > LINE_WIDTH {
+                writeln!(f, "{line}")?;
+                line.clear();
+            } else if glue {
+                line.push(' ');
+            }
+            line.push_str(&token);
+            // a `;` comment runs to the end of the line
+            if token.starts_with(';') {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+        }
+        writeln!(f, "{line}")
+    }
+}
+
+/// Appends the movetext tokens of `line` (played from `board`) to `tokens`,
+/// recursing into variations. `board` is restored before returning.
+fn write_line(board: &mut Board, line: &[PgnNode], tokens: &mut Vec<String>) {
+    // a black move needs an explicit "N..." number at the start of a line and
+    // after anything that interrupts the move sequence
+    let mut need_number = true;
+    for node in line {
+        if let Some(comment) = &node.pre_comment {
+            tokens.push(comment_token(comment));
+            need_number = true;
+        }
+        if board.turn == WHITE {
+            tokens.push(format!("{}.", board.fullmove_count));
+        } else if need_number {
+            tokens.push(format!("{}...", board.fullmove_count));
+        }
+        tokens.push(board.move_to_san(node.mv));
+        need_number = false;
+
+        tokens.extend(node.nags.iter().map(|nag| format!("${nag}")));
+        let comment = match (node.eval, &node.comment) {
+            (Some(eval), Some(text)) => Some(comment_token(&format!("{eval} {text}"))),
+            (Some(eval), None) => Some(comment_token(&eval.to_string())),
+            (None, Some(text)) => Some(comment_token(text)),
+            (None, None) => None,
+        };
+        if let Some(comment) = comment {
+            tokens.push(comment);
+            need_number = true;
+        }
+
+        // alternatives start from the position before this move
+        for variation in &node.variations {
+            tokens.push("(".to_string());
+            write_line(board, variation, tokens);
+            tokens.push(")".to_string());
+            need_number = true;
+        }
+        board.commit_verified_move(node.mv);
+    }
+    for _ in line {
+        board.unmake_move();
+    }
+}
+
+/// `text` as a `{...}` comment, or, if it holds a `}` (which would end a
+/// brace comment early and has no escape), as a `;` comment to the end of the
+/// line. Line breaks in `text` become spaces either way, as the reader
+/// normalises whitespace inside comments.
+fn comment_token(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.contains('}') {
+        format!(";{text}")
+    } else {
+        format!("{{{text}}}")
+    }
+}
+
+/// A lexical element of PGN text.
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    /// A `[Name "value"]` tag pair.
+    Tag(String, String),
+    /// A `{...}` or `;` comment.
+    Comment(String),
+    /// A numeric annotation glyph.
+    Nag(u8),
+    /// `(`: start of a variation.
+    Open,
+    /// `)`: end of a variation.
+    Close,
+    /// A termination marker.
+    Result(GameResult),
+    /// A move in SAN.
+    San(String),
+}
+
+/// Maps the traditional suffix annotations to their NAG numbers.
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Splits PGN text into [`Token`]s, dropping move numbers and `%` escape lines.
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut at_line_start = true;
+
+    while let Some(c) = chars.next() {
+        let line_start = at_line_start;
+        at_line_start = c == '\n';
+        match c {
+            // escape mechanism: the rest of the line is ignored
+            '%' if line_start => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        at_line_start = true;
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err("unterminated comment".to_string()),
+                    }
+                }
+                tokens.push(Token::Comment(
+                    comment.split_whitespace().collect::<Vec<_>>().join(" "),
+                ));
+            }
+            ';' => {
+                let mut comment = String::new();
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        at_line_start = true;
+                        break;
+                    }
+                    comment.push(c);
+                }
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '[' => tokens.push(read_tag(&mut chars)?),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    digits.push(d);
+                    chars.next();
+                }
+                let nag = digits
+                    .parse::<u8>()
+                    .map_err(|_| format!("invalid NAG: ${digits}"))?;
+                tokens.push(Token::Nag(nag));
+            }
+            _ => {
+                let mut symbol = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}()[];$".contains(next) {
+                        break;
+                    }
+                    symbol.push(next);
+                    chars.next();
+                }
+                push_symbol(&symbol, &mut tokens)?;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads the remainder of a tag pair after its opening `[`.
+fn read_tag(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Token, String> {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == '"' {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+    if chars.next() != Some('"') {
+        return Err(format!("tag {name} is missing its quoted value"));
+    }
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => match chars.next() {
+                Some(escaped) => value.push(escaped),
+                None => return Err(format!("unterminated value for tag {name}")),
+            },
+            Some('"') => break,
+            Some(c) => value.push(c),
+            None => return Err(format!("unterminated value for tag {name}")),
+        }
+    }
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+    if chars.next() != Some(']') {
+        return Err(format!("tag {name} is missing its closing ']'"));
+    }
+    Ok(Token::Tag(name, value))
+}
+
+/// Classifies a bare symbol: a termination marker, a move number (possibly
+/// glued to its move, as in `12.e4`), or a SAN move with optional `!`/`?`
+/// suffixes.
+fn push_symbol(symbol: &str, tokens: &mut Vec<Token>) -> Result<(), String> {
+    if let Some(result) = GameResult::from_pgn(symbol) {
+        tokens.push(Token::Result(result));
+        return Ok(());
+    }
+    if let Some(nag) = suffix_nag(symbol) {
+        tokens.push(Token::Nag(nag));
+        return Ok(());
+    }
+
+    // castling written with zeros also starts with a digit
+    let san = if symbol.starts_with("0-0") {
+        symbol
+    } else {
+        symbol
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .trim_start_matches('.')
+    };
+    if san.is_empty() {
+        return Ok(());
+    }
+
+    let move_text = san.trim_end_matches(['!', '?']);
+    if move_text.is_empty() {
+        return Err(format!("unexpected symbol: {symbol}"));
+    }
+    tokens.push(Token::San(move_text.to_string()));
+    let suffix = &san[move_text.len()..];
+    if !suffix.is_empty() {
+        let nag = suffix_nag(suffix).ok_or_else(|| format!("invalid annotation: {suffix}"))?;
+        tokens.push(Token::Nag(nag));
+    }
+    Ok(())
+}
+
+/// Parses every game in `text`.
+///
+/// Moves are resolved against the position with [`Board::parse_san`], so the
+/// returned trees contain only legal moves. A game's start position comes from
+/// its `FEN` tag, or is the standard one.
+///
+/// # Errors
+///
+/// Returns `Err` naming the game (1-based) if the text is not valid PGN, a
+/// start FEN does not parse, or a move is illegal or ambiguous.
+///
+/// ```
+/// use sabertooth::chess_engine::pgn::parse_pgn;
+/// use sabertooth::chess_engine::utils::init_tables;
+///
+/// init_tables();
+/// let games = parse_pgn("1. f3 e5 2. g4 Qh4# 0-1\n\n1. e4 *").unwrap();
+/// assert_eq!(games.len(), 2);
+/// assert!(games[0].end_board().unwrap().in_check(false)); // White is mated
+/// ```
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, String> {
+    let tokens = tokenize(text)?;
+    let mut games = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        let game_number = games.len() + 1;
+        let game =
+            parse_game(&tokens, &mut index).map_err(|e| format!("game {game_number}: {e}"))?;
+        games.push(game);
+    }
+    Ok(games)
+}
+
+/// Parses one game starting at `tokens[*index]`, advancing `index` past it.
+fn parse_game(tokens: &[Token], index: &mut usize) -> Result<PgnGame, String> {
+    let mut game = PgnGame {
+        tags: Vec::new(),
+        moves: Vec::new(),
+        result: GameResult::Unknown,
+    };
+    while let Some(Token::Tag(name, value)) = tokens.get(*index) {
+        game.tags.push((name.clone(), value.clone()));
+        *index += 1;
+    }
+
+    let mut board = game.start_board()?;
+    let mut moves = Vec::new();
+    let mut pending_comment = None;
+    parse_line(tokens, index, &mut board, &mut moves, &mut pending_comment)?;
+    game.moves = moves;
+
+    match tokens.get(*index) {
+        Some(Token::Result(result)) => {
+            game.result = *result;
+            *index += 1;
+        }
+        Some(Token::Close) => return Err("unmatched ')'".to_string()),
+        // a game cut short without a marker falls back on its Result tag
+        _ => {
+            game.result = game
+                .tag("Result")
+                .and_then(GameResult::from_pgn)
+                .unwrap_or(GameResult::Unknown);
+        }
+    }
+    Ok(game)
+}
+
+/// Parses moves and their annotations into `line` until a `)`, a termination
+/// marker, the next game's tags, or the end of input (none of which is
+/// consumed). `board` is left at the end of the line.
+fn parse_line(
+    tokens: &[Token],
+    index: &mut usize,
+    board: &mut Board,
+    line: &mut Vec<PgnNode>,
+    pending_comment: &mut Option<String>,
+) -> Result<(), String> {
+    while let Some(token) = tokens.get(*index) {
+        match token {
+            Token::San(san) => {
+                let mv = board.parse_san(san)?;
+                board.commit_verified_move(mv);
+                let mut node = PgnNode::new(mv);
+                node.pre_comment = pending_comment.take();
+                line.push(node);
+            }
+            Token::Nag(nag) => {
+                let node = line
+                    .last_mut()
+                    .ok_or_else(|| format!("NAG ${nag} before any move"))?;
+                node.nags.push(*nag);
+            }
+            Token::Comment(text) => match line.last_mut() {
+                Some(node) => attach_comment(node, text),
+                None => append_text(pending_comment, text),
+            },
+            Token::Open => {
+                *index += 1;
+                let Some(node) = line.last_mut() else {
+                    return Err("variation before any move".to_string());
+                };
+                // the variation replaces the last move: play it from before
+                board.unmake_move();
+                let mut variation = Vec::new();
+                let mut variation_comment = None;
+                parse_line(tokens, index, board, &mut variation, &mut variation_comment)?;
+                if tokens.get(*index) != Some(&Token::Close) {
+                    return Err("unterminated variation".to_string());
+                }
+                for _ in &variation {
+                    board.unmake_move();
+                }
+                board.commit_verified_move(node.mv);
+                if !variation.is_empty() {
+                    node.variations.push(variation);
+                }
+            }
+            Token::Close | Token::Result(_) | Token::Tag(..) => return Ok(()),
+        }
+        *index += 1;
+    }
+    Ok(())
+}
+
+/// Attaches a comment that follows `node`'s move, lifting a leading engine
+/// evaluation into [`PgnNode::eval`].
+fn attach_comment(node: &mut PgnNode, text: &str) {
+    let (first, rest) = text.split_once(' ').unwrap_or((text, ""));
+    if node.eval.is_none()
+        && let Some(eval) = PgnEval::parse(first)
+    {
+        node.eval = Some(eval);
+        if !rest.is_empty() {
+            append_text(&mut node.comment, rest);
+        }
+    } else {
+        append_text(&mut node.comment, text);
+    }
+}
+
+/// Appends `text` to an optional comment, separating with a space.
+fn append_text(comment: &mut Option<String>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match comment {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(text);
+        }
+        None => *comment = Some(text.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameResult, PgnEval, PgnGame, parse_pgn};
+    use crate::chess_engine::board::Board;
+    use crate::chess_engine::engine::search::MATE_SCORE;
+    use crate::chess_engine::utils::init_tables;
+
+    const IMMORTAL: &str = r#"[Event "London"]
+[Site "London ENG"]
+[Date "1851.06.21"]
+[Round "?"]
+[White "Adolf Anderssen"]
+[Black "Lionel Kieseritzky"]
+[Result "1-0"]
+
+1.e4 e5 2.f4 exf4 3.Bc4 Qh4+ 4.Kf1 b5 5.Bxb5 Nf6 6.Nf3 Qh6 7.d3 Nh5 8.Nh4 Qg5
+9.Nf5 c6 10.g4 Nf6 11.Rg1 cxb5 12.h4 Qg6 13.h5 Qg5 14.Qf3 Ng8 15.Bxf4 Qf6
+16.Nc3 Bc5 17.Nd5 Qxb2 18.Bd6 Bxg1 19.e5 Qxa1+ 20.Ke2 Na6 21.Nxg7+ Kd8
+22.Qf6+ Nxf6 23.Be7# 1-0
+"#;
+
+    #[test]
+    fn parses_tags_and_replays_the_main_line() {
+        init_tables();
+        let game = PgnGame::from_pgn(IMMORTAL).unwrap();
+        assert_eq!(game.tag("White"), Some("Adolf Anderssen"));
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.moves.len(), 45);
+
+        let mut end = game.end_board().unwrap();
+        assert!(end.in_check(end.turn));
+        assert!(end.generate_moves(end.turn).is_empty());
+    }
+
+    #[test]
+    fn parses_comments_nags_and_nested_variations() {
+        init_tables();
+        let text = "{Opening} 1. e4 $1 e5!? (1... c5 {Sicilian} 2. Nf3 (2. c3) d6) \
+                    ; rest of line\n2. Nf3 Nc6?? *";
+        let game = PgnGame::from_pgn(text).unwrap();
+        assert_eq!(game.result, GameResult::Unknown);
+        assert_eq!(game.moves.len(), 4);
+        assert_eq!(game.moves[0].pre_comment.as_deref(), Some("Opening"));
+        assert_eq!(game.moves[0].nags, [1]);
+        assert_eq!(game.moves[1].nags, [5]);
+        assert_eq!(game.moves[1].comment.as_deref(), Some("rest of line"));
+        assert_eq!(game.moves[3].nags, [4]);
+
+        let sicilian = &game.moves[1].variations[0];
+        assert_eq!(sicilian.len(), 3);
+        assert_eq!(sicilian[0].mv.to_string(), "c7c5");
+        assert_eq!(sicilian[0].comment.as_deref(), Some("Sicilian"));
+        assert_eq!(sicilian[1].variations[0][0].mv.to_string(), "c2c3");
+    }
+
+    #[test]
+    fn reads_and_writes_eval_comments() {
+        init_tables();
+        let game =
+            PgnGame::from_pgn("1. e4 {+0.35/12} e5 {-0.20/11 book} 2. Qh5 {+M3/9} *").unwrap();
+        assert_eq!(
+            game.moves[0].eval,
+            Some(PgnEval {
+                score: 35,
+                depth: 12
+            })
+        );
+        assert_eq!(game.moves[1].eval.map(|e| e.score), Some(-20));
+        assert_eq!(game.moves[1].comment.as_deref(), Some("book"));
+        assert_eq!(game.moves[2].eval.map(|e| e.score), Some(MATE_SCORE - 5));
+
+        let written = game.to_string();
+        assert!(written.contains("1. e4 {+0.35/12} 1... e5 {-0.20/11 book} 2. Qh5 {+M3/9} *"));
+    }
+
+    #[test]
+    fn written_games_parse_back_identically() {
+        init_tables();
+        let text = "[Event \"Test \\\"quoted\\\"\"]\n\n1. e4 e5 (1... c5 2. Nf3 (2. c3) d6) \
+                    2. Nf3 $14 {a comment} Nc6 3. Bb5 a6 1/2-1/2";
+        let game = PgnGame::from_pgn(text).unwrap();
+        let written = game.to_string();
+        assert_eq!(PgnGame::from_pgn(&written).unwrap(), game);
+        assert!(written.starts_with("[Event \"Test \\\"quoted\\\"\"]\n\n1. e4 e5 (1... c5 2. Nf3"));
+        assert!(written.contains("(2. c3) 2... d6) 2. Nf3 $14 {a comment} 2... Nc6"));
+
+        let immortal = PgnGame::from_pgn(IMMORTAL).unwrap();
+        let written = immortal.to_string();
+        assert!(written.lines().all(|line| line.len() <= 80));
+        assert_eq!(PgnGame::from_pgn(&written).unwrap(), immortal);
+    }
+
+    #[test]
+    fn comments_with_closing_braces_round_trip() {
+        init_tables();
+        let mut game = PgnGame::from_pgn("1. e4 {+0.35/12} e5 2. Nf3 *").unwrap();
+        game.moves[0].comment = Some("threatens {f7} soon".to_string());
+        game.moves[0].pre_comment = Some("{Open} game".to_string());
+        game.moves[2].comment = Some("main line}".to_string());
+
+        let written = game.to_string();
+        assert!(written.starts_with(";{Open} game\n1. e4 ;+0.35/12 threatens {f7} soon\n1... e5"));
+        let read = PgnGame::from_pgn(&written).unwrap();
+        assert_eq!(read, game);
+        assert_eq!(
+            read.moves[0].comment.as_deref(),
+            Some("threatens {f7} soon")
+        );
+        assert_eq!(read.moves[2].comment.as_deref(), Some("main line}"));
+    }
+
+    #[test]
+    fn builds_self_play_records_from_a_fen() {
+        init_tables();
+        let mut start = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut game = PgnGame::new(&start);
+        let mate = start.parse_san("Ra8#").unwrap();
+        game.push_move(
+            mate,
+            Some(PgnEval {
+                score: MATE_SCORE - 1,
+                depth: 2,
+            }),
+        );
+        game.set_result(GameResult::WhiteWins);
+
+        let written = game.to_string();
+        assert!(written.contains("[Result \"1-0\"]"));
+        assert!(written.contains("[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]"));
+        assert!(written.ends_with("1. Ra8# {+M1/2} 1-0\n"));
+        assert_eq!(PgnGame::from_pgn(&written).unwrap(), game);
+
+        // a black-to-move start numbers its first move "N..."
+        let black = Board::from_fen("4k3/8/8/8/8/4K3/8/7r b - - 0 40").unwrap();
+        let mut game = PgnGame::new(&black);
+        game.push_move(
+            Board::from_fen(&black.to_fen())
+                .unwrap()
+                .parse_san("Rh2")
+                .unwrap(),
+            None,
+        );
+        assert!(game.to_string().contains("\n\n40... Rh2 *"));
+    }
+
+    #[test]
+    fn parses_multiple_games_and_reports_errors() {
+        init_tables();
+        let games = parse_pgn(&format!("{IMMORTAL}\n{IMMORTAL}")).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let err = parse_pgn("1. e4 e5 *\n\n1. e4 e4 *").unwrap_err();
+        assert!(err.starts_with("game 2:"), "{err}");
+        assert!(parse_pgn("1. e4 (1. d4 *").is_err());
+        assert!(parse_pgn("1. e4 {never closed").is_err());
+        assert!(parse_pgn("[Event \"x\" 1. e4 *").is_err());
+    }
+}
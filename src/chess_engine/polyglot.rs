@@ -0,0 +1,404 @@
+//! [Polyglot][pg] opening books: the `.bin` format most engines and GUIs share.
+//!
+//! A book is a sequence of 16-byte big-endian entries — a 64-bit position key,
+//! a 16-bit move, a 16-bit weight and a 32-bit learn field — sorted by key, so
+//! all the moves recorded for one position sit next to each other and are
+//! found by binary search. A move packs the destination file and rank, the
+//! origin file and rank (three bits each) and a promotion piece; castles are
+//! written king-takes-rook (`e1h1`), whatever the variant.
+//!
+//! [`PolyglotBook`] reads and writes that format, builds a book from PGN games
+//! and turns a position's entries into legal [`Move`]s, picking either the
+//! best-weighted move or one at random in proportion to the weights.
+//!
+//! Positions are keyed by [`book_key`], the engine's own Zobrist hash, not by
+//! the fixed table of 781 random numbers Polyglot itself uses. That table is
+//! not part of this crate yet, so the books this module writes can be read
+//! back by the engine, but books written by other programs will not match.
+//!
+//! [pg]: http://hgm.nubati.net/book_format.html
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use rand::RngExt;
+
+use crate::chess_engine::{
+    board::{Board, WHITE},
+    moves::{Move, SpecialMove},
+    pgn::{GameResult, PgnGame, parse_pgn},
+    piece::Piece,
+    position::Position,
+};
+
+/// Size in bytes of one book entry.
+const ENTRY_SIZE: usize = 16;
+
+/// One book entry: a move recorded for the position with key `key`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    /// Key of the position; see [`book_key`].
+    pub key: u64,
+    /// The move in Polyglot encoding; see [`encode_move`].
+    pub raw_move: u16,
+    /// How good the move is considered; higher is played more often.
+    pub weight: u16,
+    /// Learning data, unused here but preserved.
+    pub learn: u32,
+}
+
+impl BookEntry {
+    /// Decodes one big-endian entry from a chunk of `ENTRY_SIZE` bytes.
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let field = |range: std::ops::Range<usize>| {
+            bytes[range]
+                .iter()
+                .fold(0u64, |value, &byte| value << 8 | u64::from(byte))
+        };
+        Self {
+            key: field(0..8),
+            raw_move: u16::try_from(field(8..10)).unwrap_or_default(),
+            weight: u16::try_from(field(10..12)).unwrap_or_default(),
+            learn: u32::try_from(field(12..16)).unwrap_or_default(),
+        }
+    }
+}
+
+/// An opening book: entries sorted by key.
+#[derive(Debug, Default, Clone)]
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+}
+
+impl PolyglotBook {
+    /// Builds a book from entries in any order.
+    #[must_use]
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by_key(|entry| (entry.key, std::cmp::Reverse(entry.weight)));
+        Self { entries }
+    }
+
+    /// Builds a book from the first `max_ply` moves of each game's main line.
+    ///
+    /// A move scores two points for the side that played it when that side
+    /// won, and one for a draw or an unknown result; its weight is its total
+    /// over all games (capped at `u16::MAX`). Moves that only ever lost are
+    /// left out.
+    ///
+    /// # Errors
+    /// Returns an error if a game's `FEN` tag does not parse.
+    pub fn from_games(games: &[PgnGame], max_ply: usize) -> Result<Self, String> {
+        let mut scores: HashMap<(u64, u16), u32> = HashMap::new();
+        for game in games {
+            let mut board = game.start_board()?;
+            for node in game.moves.iter().take(max_ply) {
+                let score = match (game.result, board.turn == WHITE) {
+                    (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => 2,
+                    (GameResult::WhiteWins | GameResult::BlackWins, _) => 0,
+                    (GameResult::Draw | GameResult::Unknown, _) => 1,
+                };
+                let key = (book_key(&mut board), encode_move(&board, node.mv));
+                *scores.entry(key).or_default() += score;
+                board.commit_verified_move(node.mv);
+            }
+        }
+        let entries = scores
+            .into_iter()
+            .filter(|&(_, score)| score > 0)
+            .map(|((key, raw_move), score)| BookEntry {
+                key,
+                raw_move,
+                weight: u16::try_from(score).unwrap_or(u16::MAX),
+                learn: 0,
+            })
+            .collect();
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Parses the contents of a `.bin` file.
+    ///
+    /// # Errors
+    /// Returns an error if the length is not a whole number of entries.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(format!(
+                "Polyglot book size {} is not a multiple of {ENTRY_SIZE} bytes",
+                bytes.len()
+            ));
+        }
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(BookEntry::from_bytes)
+            .collect();
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Reads a `.bin` file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Cannot read book {}: {e}", path.display()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The book in `.bin` form.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.raw_move.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Number of entries.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the book has no entries.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries recorded for `key`, best weight first.
+    #[must_use]
+    pub fn entries_for(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// The legal book moves for `board` (whose [`book_key`] is `key`) with
+    /// their weights. Entries that do not decode to a legal move are skipped.
+    pub fn moves(&self, board: &mut Board, key: u64) -> Vec<(Move, u16)> {
+        let legal = board.generate_moves(board.turn);
+        self.entries_for(key)
+            .iter()
+            .filter_map(|entry| {
+                legal
+                    .iter()
+                    .find(|&&m| encode_move(board, m) == entry.raw_move)
+                    .map(|&m| (m, entry.weight))
+            })
+            .collect()
+    }
+
+    /// The highest-weighted legal book move, if any.
+    pub fn best_move(&self, board: &mut Board, key: u64) -> Option<Move> {
+        self.moves(board, key)
+            .into_iter()
+            .max_by_key(|&(_, weight)| weight)
+            .map(|(m, _)| m)
+    }
+
+    /// A legal book move chosen at random in proportion to the weights, or
+    /// uniformly if every weight is zero.
+    pub fn weighted_move<R: RngExt + ?Sized>(
+        &self,
+        board: &mut Board,
+        key: u64,
+        rng: &mut R,
+    ) -> Option<Move> {
+        let moves = self.moves(board, key);
+        let total: u32 = moves.iter().map(|&(_, weight)| u32::from(weight)).sum();
+        if total == 0 {
+            return (!moves.is_empty()).then(|| moves[rng.random_range(0..moves.len())].0);
+        }
+        let mut pick = rng.random_range(0..total);
+        moves.into_iter().find_map(|(m, weight)| {
+            if pick < u32::from(weight) {
+                Some(m)
+            } else {
+                pick -= u32::from(weight);
+                None
+            }
+        })
+    }
+}
+
+/// How many plies of each game the `book` subcommand records by default.
+const DEFAULT_BOOK_PLIES: usize = 20;
+
+/// The `book` subcommand: builds a book from the games in a PGN file.
+///
+/// Usage: `book <games.pgn> <book.bin> [plies <n>]`; see
+/// [`PolyglotBook::from_games`] for how moves are weighted.
+///
+/// # Errors
+///
+/// Returns `Err` if the arguments are malformed, the PGN does not parse or a
+/// file cannot be read or written.
+pub fn book_command(args: &[String]) -> Result<(), String> {
+    let usage = "usage: book <games.pgn> <book.bin> [plies <n>]";
+    let [pgn_path, book_path, options @ ..] = args else {
+        return Err(usage.to_string());
+    };
+    let max_ply = match options {
+        [] => DEFAULT_BOOK_PLIES,
+        [option, value] if option == "plies" => {
+            value.parse().map_err(|e| format!("{option}: {e}"))?
+        }
+        _ => return Err(usage.to_string()),
+    };
+    let text = fs::read_to_string(pgn_path).map_err(|e| format!("{pgn_path}: {e}"))?;
+    let games = parse_pgn(&text)?;
+    let book = PolyglotBook::from_games(&games, max_ply)?;
+    fs::write(book_path, book.to_bytes()).map_err(|e| format!("{book_path}: {e}"))?;
+    println!(
+        "wrote {} entries from {} games to {book_path}",
+        book.len(),
+        games.len()
+    );
+    Ok(())
+}
+
+/// The key a book stores `board` under: its Zobrist hash, with the en passant
+/// file counted only when an en passant capture is legal (as Polyglot does),
+/// so transpositions share their entries.
+pub fn book_key(board: &mut Board) -> u64 {
+    board.repetition_key()
+}
+
+/// Encodes a move played on `board` in Polyglot form.
+///
+/// The destination file and rank go in bits 0–5, the origin file and rank in
+/// bits 6–11, and the promotion piece (1 knight … 4 queen) in bits 12–14.
+/// Castles are written as the king capturing its own rook.
+#[must_use]
+pub fn encode_move(board: &Board, m: Move) -> u16 {
+    let (origin, mut dest) = m.get_org_and_dest();
+    let mut promotion = 0;
+    match m.get_special_move() {
+        SpecialMove::Castle => dest = board.castle_squares(board.turn, origin, dest).1,
+        SpecialMove::Promotion => {
+            promotion = match m.get_promotion() {
+                Piece::Knight => 1,
+                Piece::Bishop => 2,
+                Piece::Rook => 3,
+                _ => 4,
+            };
+        }
+        _ => (),
+    }
+    square_bits(dest) | square_bits(origin) << 6 | promotion << 12
+}
+
+/// A square as Polyglot's `rank * 8 + file`.
+fn square_bits(square: Position) -> u16 {
+    let (file, rank) = square.get_file_and_rank();
+    u16::try_from(rank * 8 + file).expect("square index fits in 6 bits")
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64Mcg;
+
+    use super::{BookEntry, PolyglotBook, book_key, encode_move};
+    use crate::chess_engine::{board::Board, pgn::parse_pgn, utils::init_tables};
+
+    /// Polyglot encoding of a move given as `from` and `to` square indices.
+    const fn raw(from: u16, to: u16) -> u16 {
+        from << 6 | to
+    }
+
+    const fn entry(key: u64, raw_move: u16, weight: u16) -> BookEntry {
+        BookEntry {
+            key,
+            raw_move,
+            weight,
+            learn: 0,
+        }
+    }
+
+    #[test]
+    fn round_trips_the_binary_format_and_groups_by_key() {
+        let book = PolyglotBook::from_entries(vec![
+            entry(7, raw(12, 28), 10),
+            entry(3, raw(6, 21), 1),
+            entry(7, raw(11, 27), 30),
+        ]);
+        let bytes = book.to_bytes();
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(&bytes[..8], &3u64.to_be_bytes());
+
+        let read = PolyglotBook::from_bytes(&bytes).unwrap();
+        assert_eq!(read.len(), 3);
+        let sevens = read.entries_for(7);
+        assert_eq!(sevens.len(), 2);
+        assert_eq!(sevens[0].weight, 30);
+        assert!(read.entries_for(5).is_empty());
+        assert!(PolyglotBook::from_bytes(&bytes[..20]).is_err());
+    }
+
+    #[test]
+    fn decodes_castles_promotions_and_picks_by_weight() {
+        init_tables();
+        let mut board = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let moves = board.generate_moves(board.turn);
+        let encoded = |uci: &str| {
+            let m = *moves.iter().find(|m| m.to_string() == uci).unwrap();
+            encode_move(&board, m)
+        };
+        // e1h1 and e1a1, b7a8=N
+        assert_eq!(encoded("e1g1"), raw(4, 7));
+        assert_eq!(encoded("e1c1"), raw(4, 0));
+        assert_eq!(encoded("b7a8n"), raw(49, 56) | 1 << 12);
+
+        let book = PolyglotBook::from_entries(vec![
+            entry(1, raw(4, 7), 1),
+            entry(1, raw(49, 56) | 4 << 12, 9),
+            entry(1, raw(12, 28), 100),
+        ]);
+        // the e2e4 entry is not legal here and is skipped
+        let found = book.moves(&mut board, 1);
+        assert_eq!(found.len(), 2);
+        assert_eq!(book.best_move(&mut board, 1).unwrap().to_string(), "b7a8q");
+
+        let mut rng = Pcg64Mcg::seed_from_u64(1);
+        let picks: Vec<String> = (0..200)
+            .map(|_| {
+                book.weighted_move(&mut board, 1, &mut rng)
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        let castles = picks.iter().filter(|m| *m == "e1g1").count();
+        assert!(castles > 0 && castles < 60);
+        assert!(book.weighted_move(&mut board, 2, &mut rng).is_none());
+    }
+
+    #[test]
+    fn builds_a_book_from_games_weighted_by_result() {
+        init_tables();
+        let games = parse_pgn(
+            "1. e4 e5 2. Nf3 1-0\n\n1. e4 c5 1/2-1/2\n\n1. d4 d5 0-1\n\n1. Nf3 d5 2. d4 *",
+        )
+        .unwrap();
+        let book = PolyglotBook::from_games(&games, 2).unwrap();
+        // e4 (2 + 1), Nf3, c5 and d5 twice; d4 and e5 lost and are left out
+        assert_eq!(book.len(), 5);
+
+        let mut board = Board::new_start_pos().unwrap();
+        let key = book_key(&mut board);
+        let weights: Vec<(String, u16)> = book
+            .moves(&mut board, key)
+            .into_iter()
+            .map(|(m, weight)| (m.to_string(), weight))
+            .collect();
+        assert_eq!(weights, [("e2e4".to_string(), 3), ("g1f3".to_string(), 1)]);
+
+        assert!(board.play_string_move("d2d4"));
+        let key = book_key(&mut board);
+        assert_eq!(book.best_move(&mut board, key).unwrap().to_string(), "d7d5");
+    }
+}
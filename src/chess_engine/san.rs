@@ -0,0 +1,335 @@
+//! [Standard Algebraic Notation][san] (SAN) for [`Move`]s on a [`Board`].
+//!
+//! SAN names a move by the moving piece's letter (none for pawns) and its
+//! destination square, e.g. `Nf3`, `exd5`, `O-O`, `e8=Q`. Because it leaves
+//! the origin out, it only makes sense against a position: a file, rank, or
+//! full origin square is inserted when two pieces of the same type could reach
+//! the same square (`Nbd2`, `R1a3`), and a `+`/`#` suffix marks check/mate.
+//!
+//! [`Board::move_to_san`] writes canonical SAN. [`Board::parse_san`] is
+//! deliberately lenient, as real-world game records and test suites are: the
+//! `x` capture marker and check/annotation suffixes are optional, castling may
+//! use zeros (`0-0`), the `=` before a promotion piece may be omitted, and
+//! redundant disambiguation is accepted.
+//!
+//! [san]: https://www.chessprogramming.org/Algebraic_Chess_Notation#Standard_Algebraic_Notation_.28SAN.29
+
+use crate::chess_engine::{
+    board::Board,
+    moves::{Move, SpecialMove},
+    piece::Piece,
+    position::Position,
+};
+
+impl Board {
+    /// Returns the SAN of the legal move `mv` in this position, including the
+    /// minimal origin disambiguation and a `+`/`#` suffix for check/mate.
+    ///
+    /// `mv` must be legal here (e.g. from
+    /// [`generate_moves`](Self::generate_moves)); the board is left unchanged.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::board::Board;
+    /// use sabertooth::chess_engine::utils::init_tables;
+    ///
+    /// init_tables();
+    /// let mut board = Board::new_start_pos().unwrap();
+    /// let mv = board.parse_san("Nf3").unwrap();
+    /// assert_eq!(mv.to_string(), "g1f3");
+    /// assert_eq!(board.move_to_san(mv), "Nf3");
+    /// ```
+    pub fn move_to_san(&mut self, mv: Move) -> String {
+        let mut san = self.san_body(mv);
+        self.commit_verified_move(mv);
+        if self.in_check(self.turn) {
+            san.push(if self.generate_moves(self.turn).is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        self.unmake_move();
+        san
+    }
+
+    /// SAN of `mv` without the check/mate suffix.
+    fn san_body(&mut self, mv: Move) -> String {
+        let (origin, dest) = mv.get_org_and_dest();
+        let special = mv.get_special_move();
+
+        if special == SpecialMove::Castle {
+            // king-side iff the king heads toward the h-file
+            return if dest.get_file_and_rank().0 > origin.get_file_and_rank().0 {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            };
+        }
+
+        let piece = self.get_piece_type_containing_position(origin);
+        let is_capture =
+            special == SpecialMove::EnPassant || !self.empty_tiles.is_square_set(dest.into());
+        let mut san = String::new();
+
+        if piece == Piece::Pawn {
+            // pawn captures name the origin file; pushes need no prefix
+            if is_capture {
+                san.push_str(&origin.algebraic_notation()[..1]);
+            }
+        } else {
+            san.push_str(&piece.to_notation().to_uppercase());
+
+            // other pieces of the same type that could also land on `dest`
+            let rivals: Vec<Position> = self
+                .generate_moves(self.turn)
+                .into_iter()
+                .filter(|other| {
+                    other.get_dest() == dest
+                        && other.get_origin() != origin
+                        && self.get_piece_type_containing_position(other.get_origin()) == piece
+                })
+                .map(|other| other.get_origin())
+                .collect();
+            if !rivals.is_empty() {
+                let (file, rank) = origin.get_file_and_rank();
+                let square = origin.algebraic_notation();
+                if rivals.iter().all(|r| r.get_file_and_rank().0 != file) {
+                    san.push_str(&square[..1]);
+                } else if rivals.iter().all(|r| r.get_file_and_rank().1 != rank) {
+                    san.push_str(&square[1..]);
+                } else {
+                    san.push_str(&square);
+                }
+            }
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&dest.algebraic_notation());
+        if special == SpecialMove::Promotion {
+            san.push('=');
+            san.push_str(&mv.get_promotion().to_notation().to_uppercase());
+        }
+        san
+    }
+
+    /// Parses a move written in SAN and returns the matching legal move,
+    /// without playing it. The `x` capture marker, check/annotation suffixes,
+    /// and the `=` before a promotion piece are optional, castling may be
+    /// written with zeros (`0-0`), and redundant disambiguation is accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` with a human-readable message if the text is not
+    /// well-formed SAN, matches no legal move, or is ambiguous (matches more
+    /// than one).
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::board::Board;
+    /// use sabertooth::chess_engine::utils::init_tables;
+    ///
+    /// init_tables();
+    /// let mut board = Board::new_start_pos().unwrap();
+    /// assert_eq!(board.parse_san("e4").unwrap().to_string(), "e2e4");
+    /// assert!(board.parse_san("Ke2").is_err()); // the e2 pawn is in the way
+    /// ```
+    pub fn parse_san(&mut self, san: &str) -> Result<Move, String> {
+        let token = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let legal = self.generate_moves(self.turn);
+
+        // castling
+        let castle_side = match token {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(king_side) = castle_side {
+            return legal
+                .into_iter()
+                .find(|mv| {
+                    mv.get_special_move() == SpecialMove::Castle
+                        && (mv.get_dest().get_file_and_rank().0
+                            > mv.get_origin().get_file_and_rank().0)
+                            == king_side
+                })
+                .ok_or_else(|| format!("castling is not legal here: {san}"));
+        }
+
+        let mut chars: Vec<char> = token.chars().filter(|&c| c != 'x').collect();
+
+        // moving piece: an uppercase letter, or none for a pawn
+        let piece = match chars.first() {
+            Some(&c) if "NBRQK".contains(c) => {
+                chars.remove(0);
+                piece_from_char(c)
+            }
+            Some(_) => Piece::Pawn,
+            None => return Err("empty SAN move".to_string()),
+        };
+
+        // promotion suffix, with or without the '='
+        let mut promotion = None;
+        if let Some(&last) = chars.last()
+            && "NBRQnbrq".contains(last)
+        {
+            promotion = Some(piece_from_char(last.to_ascii_uppercase()));
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(format!("missing destination square: {san}"));
+        }
+        let dest_str: String = chars[chars.len() - 2..].iter().collect();
+        let dest = Position::try_from(dest_str.as_str())
+            .map_err(|()| format!("invalid destination square: {san}"))?;
+
+        // anything before the destination is origin disambiguation
+        let mut file_hint = None;
+        let mut rank_hint = None;
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' => file_hint = Some(c as usize - 'a' as usize),
+                '1'..='8' => rank_hint = Some(c as usize - '1' as usize),
+                _ => return Err(format!("unexpected character '{c}' in SAN move: {san}")),
+            }
+        }
+
+        let mut candidates = legal.into_iter().filter(|mv| {
+            let (origin_file, origin_rank) = mv.get_origin().get_file_and_rank();
+            let mv_promotion =
+                (mv.get_special_move() == SpecialMove::Promotion).then(|| mv.get_promotion());
+            mv.get_dest() == dest
+                && mv.get_special_move() != SpecialMove::Castle
+                && self.get_piece_type_containing_position(mv.get_origin()) == piece
+                && mv_promotion == promotion
+                && file_hint.is_none_or(|f| f == origin_file)
+                && rank_hint.is_none_or(|r| r == origin_rank)
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Ok(mv),
+            (None, _) => Err(format!("no legal move matches: {san}")),
+            (Some(_), Some(_)) => Err(format!("ambiguous SAN move: {san}")),
+        }
+    }
+}
+
+/// Maps an uppercase SAN piece letter to its [`Piece`].
+fn piece_from_char(c: char) -> Piece {
+    Piece::try_from(c.to_string().as_str()).unwrap_or(Piece::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_engine::board::Board;
+    use crate::chess_engine::utils::init_tables;
+
+    /// SAN of the UCI move `uci` in `fen`.
+    fn san_of(fen: &str, uci: &str) -> String {
+        init_tables();
+        let mut board = Board::from_fen(fen).unwrap();
+        let mv = board
+            .generate_moves(board.turn)
+            .into_iter()
+            .find(|m| m.to_string() == uci)
+            .expect("move should be legal in the test position");
+        board.move_to_san(mv)
+    }
+
+    #[test]
+    fn writes_plain_moves_and_captures() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san_of(start, "e2e4"), "e4");
+        assert_eq!(san_of(start, "b1c3"), "Nc3");
+        let open = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        assert_eq!(san_of(open, "e4d5"), "exd5");
+        // en passant is written as an ordinary pawn capture
+        let ep = "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3";
+        assert_eq!(san_of(ep, "e5d6"), "exd6");
+    }
+
+    #[test]
+    fn disambiguates_by_file_then_rank_then_square() {
+        // knights on b1 and f3 can both reach d2
+        let file = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+        assert_eq!(san_of(file, "b1d2"), "Nbd2");
+        // rooks on a1 and a5 share a file
+        let rank = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san_of(rank, "a1a3"), "R1a3");
+        // queens on h4, e4 and h1 all reach e1... and h4 shares a file with h1
+        // and a rank with e4
+        let square = "8/8/1k6/8/4Q2Q/8/8/K6Q w - - 0 1";
+        assert_eq!(san_of(square, "h4e1"), "Qh4e1");
+    }
+
+    #[test]
+    fn writes_castling_promotion_check_and_mate() {
+        let castle = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san_of(castle, "e1g1"), "O-O");
+        assert_eq!(san_of(castle, "e1c1"), "O-O-O");
+        let promo = "1r5k/P7/8/8/8/8/8/K7 w - - 0 1";
+        assert_eq!(san_of(promo, "a7b8q"), "axb8=Q+");
+        assert_eq!(san_of(promo, "a7a8n"), "a8=N");
+        let mate = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        assert_eq!(san_of(mate, "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn parses_lenient_san() {
+        init_tables();
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(board.parse_san("0-0").unwrap().to_string(), "e1g1");
+        assert_eq!(board.parse_san("O-O-O").unwrap().to_string(), "e1c1");
+        // capture marker and check suffix are optional
+        assert_eq!(board.parse_san("Rxa8+").unwrap().to_string(), "a1a8");
+        assert_eq!(board.parse_san("Ra8").unwrap().to_string(), "a1a8");
+
+        let mut promo = Board::from_fen("1r5k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(promo.parse_san("axb8=Q+").unwrap().to_string(), "a7b8q");
+        assert_eq!(promo.parse_san("a8N").unwrap().to_string(), "a7a8n");
+    }
+
+    #[test]
+    fn rejects_illegal_ambiguous_and_malformed_san() {
+        init_tables();
+        let mut board = Board::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        assert!(board.parse_san("Nd2").is_err()); // ambiguous
+        assert!(board.parse_san("Nd5").is_err()); // no knight reaches d5
+        assert!(board.parse_san("O-O").is_err()); // no castling rights
+        assert!(board.parse_san("Nz9").is_err());
+        assert!(board.parse_san("").is_err());
+        let mut promo = Board::from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert!(promo.parse_san("a8").is_err()); // promotion piece required
+    }
+
+    /// Every legal move at every node to `depth` plies survives a
+    /// write-then-parse round trip.
+    fn assert_san_round_trips(board: &mut Board, depth: u32) {
+        for mv in board.generate_moves(board.turn) {
+            let san = board.move_to_san(mv);
+            assert_eq!(board.parse_san(&san), Ok(mv), "{san} in {}", board.to_fen());
+            if depth > 1 {
+                board.commit_verified_move(mv);
+                assert_san_round_trips(board, depth - 1);
+                board.unmake_move();
+            }
+        }
+    }
+
+    #[test]
+    fn san_round_trips_across_perft_positions() {
+        init_tables();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            let mut board = Board::from_fen(fen).unwrap();
+            assert_san_round_trips(&mut board, 2);
+        }
+    }
+}
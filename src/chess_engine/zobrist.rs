@@ -12,7 +12,7 @@
 
 use crate::chess_engine::{
     board::{Board, PLAYER_COUNT, WHITE},
-    piece::PIECE_COUNT,
+    piece::{PIECE_COUNT, Piece},
     position::Position,
 };
 use rand::{RngExt, SeedableRng};
@@ -96,6 +96,19 @@ impl ZobristTable {
 
         hash
     }
+
+    /// Computes the hash of the pawns alone: the XOR of the piece-square keys
+    /// of every pawn of both colours. Positions with the same pawn structure
+    /// share it, which makes it the key of the pawn hash table.
+    pub fn hash_pawns(&self, board: &Board) -> ZobristHash {
+        let mut hash: ZobristHash = 0;
+        for pos in 0..Position::MAX_POS {
+            if let Some((Piece::Pawn, color)) = board.get_piece_at(Position::new(pos)) {
+                hash ^= self.piece_square[usize::from(color)][Piece::Pawn as usize][pos];
+            }
+        }
+        hash
+    }
 }
 
 #[cfg(test)]
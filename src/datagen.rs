@@ -0,0 +1,566 @@
+//! Training-data generation: self-play games whose positions are labelled for
+//! the [tuner](crate::tuning) or an NNUE trainer.
+//!
+//! Each worker thread plays games against itself at a fixed node budget, with
+//! its own transposition table. A game starts from the initial position
+//! followed by a few uniformly random plies, so every game is different; an
+//! opening the first search scores as lopsided is thrown away. Games end by the
+//! rules or by [`Adjudication`], as in a [match](crate::match_runner).
+//!
+//! Only *quiet* positions are kept, so the static evaluation can be fitted to
+//! them: the side to move is not in check, the move the search chose is not a
+//! capture or promotion, the score is not a mate, and a quiescence search finds
+//! nothing better than standing pat. Each is recorded with the search score and
+//! the game's final result, both from White's point of view, in one of two
+//! formats:
+//!
+//! - *text*, one position per line as `FEN | score | result`, with the result
+//!   written `1.0`, `0.5` or `0.0` — the layout common NNUE trainers read, and
+//!   one the [`tune`](crate::tuning::tune_command) subcommand accepts too:
+//!
+//!   ```text
+//!   r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4 | 35 | 0.5
+//!   ```
+//!
+//! - *binary*, a fixed [`RECORD_SIZE`]-byte record per position (see
+//!   [`TrainingPosition::to_bytes`]).
+//!
+//! [`datagen_command`] wraps this as the binary's `datagen` subcommand:
+//!
+//! ```text
+//! sabertooth datagen data.txt games 10000 nodes 5000 threads 8
+//! sabertooth datagen data.bin format binary random 10 seed 7
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Instant;
+
+use rand::{RngExt, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+
+use crate::{
+    chess_engine::{
+        board::{Board, WHITE},
+        engine::search::{MATE_THRESHOLD, QuietCheck, SearchLimits, is_tactical, search_position},
+        engine::transposition::TranspositionTable,
+        pgn::GameResult,
+        piece::Piece,
+        position::Position,
+    },
+    match_runner::{Adjudication, rules_verdict},
+};
+
+/// Games the `datagen` subcommand plays when not told otherwise.
+const DEFAULT_GAMES: usize = 100;
+/// Search nodes per move when not told otherwise.
+const DEFAULT_NODES: u64 = 5000;
+/// Random plies played from the initial position when not told otherwise.
+const DEFAULT_RANDOM_PLIES: usize = 8;
+/// Openings the first search scores beyond this many centipawns are discarded.
+const MAX_OPENING_SCORE: i32 = 400;
+/// Transposition-table size of each worker, in MiB.
+const WORKER_HASH_MB: usize = 16;
+/// Size of the table each worker's quiet-position check runs on, in MiB.
+const QUIET_HASH_MB: usize = 1;
+
+/// Bytes in one binary record.
+pub const RECORD_SIZE: usize = 32;
+/// Piece letters by their 3-bit code in a binary record (see
+/// [`TrainingPosition::to_bytes`]).
+const PIECE_CODES: &str = "PNBRQK";
+/// Castling letters by their bit in a binary record, in castle-index order.
+const CASTLE_LETTERS: &str = "KQkq";
+
+/// How the generated positions are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    /// One `FEN | score | result` line per position.
+    Text,
+    /// One fixed-size binary record per position.
+    Binary,
+}
+
+/// A position labelled with its search score and its game's result.
+pub struct TrainingPosition {
+    /// The position.
+    pub board: Board,
+    /// The search score in centipawns, from White's point of view.
+    pub score: i16,
+    /// The game result from White's point of view: 1, 0.5 or 0.
+    pub result: f64,
+}
+
+impl TrainingPosition {
+    /// The position as a text line (without the newline):
+    /// `FEN | score | result`.
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        format!("{} | {} | {:.1}", self.board, self.score, self.result)
+    }
+
+    /// The position as a binary record, all integers little-endian:
+    ///
+    /// | Bytes    | Contents                                                |
+    /// |----------|---------------------------------------------------------|
+    /// | `0..8`   | occupancy: bit `n` set if square `n` (a1 = 0) is taken  |
+    /// | `8..24`  | the pieces in square order, a nibble each               |
+    /// | `24`     | side to move (bit 7) and en-passant square (bits 0–6)   |
+    /// | `25`     | castling rights, bits 0–3 for `K`, `Q`, `k`, `q`        |
+    /// | `26`     | halfmove clock                                          |
+    /// | `27`     | result: 0 a Black win, 1 a draw, 2 a White win          |
+    /// | `28..30` | score (`i16`)                                           |
+    /// | `30..32` | fullmove number (`u16`)                                 |
+    ///
+    /// Piece nibbles are packed low nibble first: bits 0–2 index `PNBRQK` and
+    /// bit 3 is set for Black. Bit 7 of byte 24 is set if Black is to move,
+    /// and an en-passant square of 64 means there is none.
+    ///
+    /// Castling rights are stored as flags only, so Chess960 rook files are
+    /// not kept.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        let mut occupancy = 0u64;
+        let mut count = 0;
+        for square in 0..64 {
+            if let Some((piece, color)) = self.board.get_piece_at(Position::new(square)) {
+                occupancy |= 1 << square;
+                let code = match piece {
+                    Piece::Pawn => 0,
+                    Piece::Knight => 1,
+                    Piece::Bishop => 2,
+                    Piece::Rook => 3,
+                    Piece::Queen => 4,
+                    _ => 5,
+                } | if color == WHITE { 0 } else { 8 };
+                bytes[8 + count / 2] |= code << (4 * (count % 2));
+                count += 1;
+            }
+        }
+        bytes[..8].copy_from_slice(&occupancy.to_le_bytes());
+
+        let en_passant = self.board.en_passant.first_set_bit().unwrap_or(64) as u8;
+        bytes[24] = en_passant | if self.board.turn == WHITE { 0 } else { 0x80 };
+        bytes[25] = (0..4)
+            .filter(|&index| self.board.castle_rights.castle_at_index(index))
+            .fold(0, |rights, index| rights | 1 << index);
+        bytes[26] = self.board.halfmove_count;
+        bytes[27] = (2.0 * self.result).round() as u8;
+        bytes[28..30].copy_from_slice(&self.score.to_le_bytes());
+        bytes[30..32].copy_from_slice(&self.board.fullmove_count.to_le_bytes());
+        bytes
+    }
+
+    /// Parses a binary record written by [`to_bytes`](Self::to_bytes).
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` is not a well-formed record.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let bytes: &[u8; RECORD_SIZE] = bytes
+            .try_into()
+            .map_err(|_| format!("a record is {RECORD_SIZE} bytes, not {}", bytes.len()))?;
+        let occupancy = u64::from_le_bytes(bytes[..8].try_into().unwrap_or_default());
+        if occupancy.count_ones() > 32 {
+            return Err("more than 32 pieces in a record".to_string());
+        }
+
+        let mut squares = [None; 64];
+        for (count, square) in (0..64)
+            .filter(|&square| occupancy & (1 << square) != 0)
+            .enumerate()
+        {
+            let code = (bytes[8 + count / 2] >> (4 * (count % 2))) & 0xf;
+            let letter = PIECE_CODES
+                .chars()
+                .nth(usize::from(code & 7))
+                .ok_or_else(|| format!("invalid piece code {code}"))?;
+            squares[square] = Some(if code & 8 == 0 {
+                letter
+            } else {
+                letter.to_ascii_lowercase()
+            });
+        }
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty_run = 0;
+            for file in 0..8 {
+                if let Some(letter) = squares[rank * 8 + file] {
+                    if empty_run > 0 {
+                        placement.push_str(&empty_run.to_string());
+                        empty_run = 0;
+                    }
+                    placement.push(letter);
+                } else {
+                    empty_run += 1;
+                }
+            }
+            if empty_run > 0 {
+                placement.push_str(&empty_run.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        let turn = if bytes[24] & 0x80 == 0 { 'w' } else { 'b' };
+        let en_passant = match usize::from(bytes[24] & 0x7f) {
+            64 => "-".to_string(),
+            square @ 0..64 => Position::new(square).algebraic_notation(),
+            square => return Err(format!("invalid en-passant square {square}")),
+        };
+        let castling: String = CASTLE_LETTERS
+            .chars()
+            .enumerate()
+            .filter(|&(index, _)| bytes[25] & (1 << index) != 0)
+            .map(|(_, letter)| letter)
+            .collect();
+        let castling = if castling.is_empty() {
+            "-".to_string()
+        } else {
+            castling
+        };
+        let fullmove = u16::from_le_bytes([bytes[30], bytes[31]]);
+        let board = Board::from_fen(&format!(
+            "{placement} {turn} {castling} {en_passant} {} {fullmove}",
+            bytes[26]
+        ))?;
+
+        let result = match bytes[27] {
+            0 => 0.0,
+            1 => 0.5,
+            2 => 1.0,
+            other => return Err(format!("invalid result {other}")),
+        };
+        Ok(Self {
+            board,
+            score: i16::from_le_bytes([bytes[28], bytes[29]]),
+            result,
+        })
+    }
+}
+
+/// What the generator plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatagenSettings {
+    /// Games to play, across all threads.
+    pub games: usize,
+    /// Search nodes per move.
+    pub nodes: u64,
+    /// Worker threads, each playing whole games.
+    pub threads: usize,
+    /// Random plies played from the initial position.
+    pub random_plies: usize,
+    /// Seed of the random openings; worker `i` uses `seed + i`.
+    pub seed: u64,
+    /// When games are cut short.
+    pub adjudication: Adjudication,
+}
+
+impl Default for DatagenSettings {
+    fn default() -> Self {
+        Self {
+            games: DEFAULT_GAMES,
+            nodes: DEFAULT_NODES,
+            threads: 1,
+            random_plies: DEFAULT_RANDOM_PLIES,
+            seed: 0,
+            adjudication: Adjudication::default(),
+        }
+    }
+}
+
+/// The initial position followed by `plies` random legal moves, or `None` if
+/// the game ended along the way.
+fn random_opening(rng: &mut Pcg64Mcg, plies: usize) -> Option<Board> {
+    let mut board = Board::new_start_pos().ok()?;
+    for _ in 0..plies {
+        let moves = board.generate_moves(board.turn);
+        if moves.is_empty() {
+            return None;
+        }
+        board.commit_verified_move(moves[rng.random_range(0..moves.len())]);
+    }
+    Some(board)
+}
+
+/// Plays one self-play game from a random opening and returns its quiet
+/// positions, labelled with the result; `None` if the opening was rejected.
+///
+/// `tt` is cleared first, so a game depends only on `rng`'s state;
+/// `quiet` is the worker's check for quiet positions.
+fn play_training_game(
+    rng: &mut Pcg64Mcg,
+    settings: &DatagenSettings,
+    tt: &TranspositionTable,
+    quiet: &mut QuietCheck,
+) -> Option<Vec<TrainingPosition>> {
+    let mut board = random_opening(rng, settings.random_plies)?;
+    tt.clear();
+    let stop = AtomicBool::new(false);
+    let limits = SearchLimits::node_budget(settings.nodes);
+    let mut positions = Vec::new();
+    let mut white_scores = Vec::new();
+    let result = loop {
+        if let Some((result, _)) = rules_verdict(&mut board) {
+            break result;
+        }
+        let searched = search_position(&mut board, &limits, &stop, tt, 1, false);
+        let mv = searched.best_move?;
+        if white_scores.is_empty() && searched.score.abs() > MAX_OPENING_SCORE {
+            return None;
+        }
+        let white_score = if board.turn == WHITE {
+            searched.score
+        } else {
+            -searched.score
+        };
+        if searched.score.abs() < MATE_THRESHOLD
+            && !is_tactical(&board, mv)
+            && quiet.is_quiet(&mut board)
+        {
+            let mut snapshot = board.clone();
+            snapshot.history.clear();
+            let score = i16::try_from(white_score).unwrap_or_default();
+            positions.push((snapshot, score));
+        }
+        white_scores.push(Some(white_score));
+        board.commit_verified_move(mv);
+        if let Some((result, _)) = settings.adjudication.judge(&white_scores) {
+            break result;
+        }
+    };
+
+    let result = match result {
+        GameResult::WhiteWins => 1.0,
+        GameResult::BlackWins => 0.0,
+        GameResult::Draw | GameResult::Unknown => 0.5,
+    };
+    Some(
+        positions
+            .into_iter()
+            .map(|(board, score)| TrainingPosition {
+                board,
+                score,
+                result,
+            })
+            .collect(),
+    )
+}
+
+/// Plays `settings.games` self-play games on `settings.threads` threads.
+///
+/// Each game's positions go to `on_game`, with the number of games finished
+/// so far. An error from `on_game` stops the generator once the games in
+/// progress are over.
+///
+/// # Errors
+/// Returns the first error `on_game` returned.
+///
+/// # Panics
+/// Panics if a worker thread panics.
+pub fn generate(
+    settings: &DatagenSettings,
+    mut on_game: impl FnMut(usize, &[TrainingPosition]) -> Result<(), String>,
+) -> Result<(), String> {
+    let next_game = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for worker in 0..settings.threads.max(1) {
+            let sender = sender.clone();
+            let next_game = &next_game;
+            scope.spawn(move || {
+                let mut rng = Pcg64Mcg::seed_from_u64(settings.seed.wrapping_add(worker as u64));
+                let tt = TranspositionTable::with_size_mb(WORKER_HASH_MB);
+                let quiet_tt = TranspositionTable::with_size_mb(QUIET_HASH_MB);
+                let mut quiet = QuietCheck::new(&quiet_tt);
+                while next_game.fetch_add(1, Ordering::Relaxed) < settings.games {
+                    let positions = loop {
+                        if let Some(positions) =
+                            play_training_game(&mut rng, settings, &tt, &mut quiet)
+                        {
+                            break positions;
+                        }
+                    };
+                    if sender.send(positions).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for (index, positions) in receiver.iter().enumerate() {
+            if let Err(error) = on_game(index + 1, &positions) {
+                // start no more games; those in progress finish unread
+                next_game.store(settings.games, Ordering::Relaxed);
+                return Err(error);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// The `datagen` subcommand: writes self-play training data.
+///
+/// Usage: `datagen <file> [games <n>] [nodes <n>] [threads <n>] [random
+/// <plies>] [seed <n>] [format text|binary]`. Threads default to every
+/// available core. Prints a progress line every ten games and at the end.
+///
+/// # Errors
+///
+/// Returns `Err` if the arguments are malformed or the file cannot be written.
+pub fn datagen_command(args: &[String]) -> Result<(), String> {
+    let usage = "usage: datagen <file> [games <n>] [nodes <n>] [threads <n>] \
+                 [random <plies>] [seed <n>] [format text|binary]";
+    let Some(path) = args.first() else {
+        return Err(usage.to_string());
+    };
+
+    let mut settings = DatagenSettings {
+        threads: std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get),
+        ..DatagenSettings::default()
+    };
+    let mut format = DataFormat::Text;
+    let mut iter = args[1..].iter();
+    while let Some(option) = iter.next() {
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("{option} needs a value; {usage}"))?;
+        let count = || value.parse::<u64>().map_err(|e| format!("{option}: {e}"));
+        let size = || count().map(|n| usize::try_from(n).unwrap_or(usize::MAX));
+        match option.as_str() {
+            "games" => settings.games = size()?,
+            "nodes" => settings.nodes = count()?,
+            "threads" => settings.threads = size()?.max(1),
+            "random" => settings.random_plies = size()?,
+            "seed" => settings.seed = count()?,
+            "format" => {
+                format = match value.as_str() {
+                    "text" => DataFormat::Text,
+                    "binary" => DataFormat::Binary,
+                    _ => return Err(format!("unknown format {value}; {usage}")),
+                };
+            }
+            _ => return Err(format!("unknown option {option}; {usage}")),
+        }
+    }
+
+    let file = File::create(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut out = BufWriter::new(file);
+    let begin = Instant::now();
+    let mut total = 0;
+    generate(&settings, |games, positions| {
+        for position in positions {
+            match format {
+                DataFormat::Text => writeln!(out, "{}", position.to_text()),
+                DataFormat::Binary => out.write_all(&position.to_bytes()),
+            }
+            .map_err(|e| format!("{path}: {e}"))?;
+        }
+        total += positions.len();
+        if games % 10 == 0 || games == settings.games {
+            let seconds = begin.elapsed().as_secs_f64();
+            #[allow(clippy::cast_precision_loss)]
+            let rate = total as f64 / seconds.max(1e-3);
+            println!(
+                "{games:>6}/{} games {total:>9} positions {rate:>8.1} positions/s {seconds:>8.1}s",
+                settings.games
+            );
+        }
+        Ok(())
+    })?;
+    out.flush().map_err(|e| format!("{path}: {e}"))?;
+    println!("Wrote {total} positions to {path}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DatagenSettings, RECORD_SIZE, TrainingPosition, generate};
+    use crate::{
+        chess_engine::{board::Board, utils::init_tables},
+        match_runner::Adjudication,
+        tuning::LabelledPosition,
+    };
+
+    #[test]
+    fn records_round_trip_through_both_formats() {
+        init_tables();
+        for (fen, score, result) in [
+            (
+                "r3k2r/pp3ppp/8/3pP3/8/8/PP3PPP/R3K2R w Kq d6 0 14",
+                -215,
+                0.0,
+            ),
+            ("8/8/4k3/8/8/3QK3/8/8 b - - 37 61", 1200, 1.0),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                20,
+                0.5,
+            ),
+        ] {
+            let position = TrainingPosition {
+                board: Board::from_fen(fen).unwrap(),
+                score,
+                result,
+            };
+            let bytes = position.to_bytes();
+            let decoded = TrainingPosition::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.board.to_fen(), fen);
+            assert_eq!((decoded.score, decoded.result), (score, result));
+
+            let line = position.to_text();
+            assert_eq!(line, format!("{fen} | {score} | {result:.1}"));
+            let labelled = LabelledPosition::parse(&line).unwrap();
+            assert_eq!(labelled.board.to_fen(), fen);
+            assert!((labelled.result - result).abs() < f64::EPSILON);
+        }
+        assert!(TrainingPosition::from_bytes(&[0; RECORD_SIZE - 1]).is_err());
+        let mut bad_result = [0; RECORD_SIZE];
+        bad_result[24] = 64;
+        bad_result[27] = 3;
+        assert!(TrainingPosition::from_bytes(&bad_result).is_err());
+    }
+
+    #[test]
+    fn self_play_records_quiet_labelled_positions() {
+        init_tables();
+        let settings = DatagenSettings {
+            games: 3,
+            nodes: 300,
+            threads: 2,
+            random_plies: 6,
+            seed: 11,
+            adjudication: Adjudication {
+                max_plies: 60,
+                ..Adjudication::default()
+            },
+        };
+        let mut finished = Vec::new();
+        let mut total = 0;
+        generate(&settings, |games, positions| {
+            finished.push(games);
+            total += positions.len();
+            let result = positions.first().map_or(0.5, |position| position.result);
+            for position in positions {
+                let mut board = position.board.clone();
+                assert!(!board.in_check(board.turn));
+                assert!(!board.generate_moves(board.turn).is_empty());
+                // every position of a game carries that game's result
+                assert!((position.result - result).abs() < f64::EPSILON);
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(finished, [1, 2, 3]);
+        assert!(total > 0);
+
+        // an error from the sink stops the generator and is passed on
+        let error = generate(&settings, |_, _| Err("disk full".to_string()));
+        assert_eq!(error, Err("disk full".to_string()));
+    }
+}
@@ -0,0 +1,437 @@
+//! [Extended Position Description][epd] (EPD) test suites: parsing and running.
+//!
+//! An EPD record is the first four FEN fields followed by `;`-terminated
+//! *operations*, e.g.
+//!
+//! ```text
+//! 2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";
+//! ```
+//!
+//! [`parse_epd`] understands the opcodes test suites such as WAC, STS and ECM
+//! use: `bm` (best moves, in SAN), `am` (moves to avoid), `dm` (direct mate in
+//! *n* moves), `id` and `c0` (a comment), plus `hmvc`/`fmvn` for the move
+//! counters. Other opcodes are ignored.
+//!
+//! [`run_suite`] searches every position with [`search_position`] under a
+//! [`Budget`] (depth, nodes, or time) and reports each [`EpdOutcome`]; a
+//! position is solved when the chosen move is one of its `bm` moves, none of
+//! its `am` moves, and — for `dm` — the score proves a mate that fast.
+//! [`epd_command`] wraps this as the binary's `epd` subcommand:
+//!
+//! ```text
+//! sabertooth epd utils/wac.txt depth 9
+//! sabertooth epd sts.epd movetime 500 threads 4
+//! ```
+//!
+//! [epd]: https://www.chessprogramming.org/Extended_Position_Description
+
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use crate::chess_engine::{
+    board::Board,
+    engine::search::{MATE_SCORE, MAX_DEPTH, SearchLimits, SearchResult, search_position},
+    engine::transposition::TranspositionTable,
+    moves::Move,
+};
+
+/// Search depth used by [`epd_command`] when no budget is given (the depth the
+/// WAC integration test runs at).
+const DEFAULT_DEPTH: u8 = 9;
+
+/// One EPD record: a position and the operations used to judge a search of it.
+pub struct EpdEntry {
+    /// The position as a full FEN (the counters come from `hmvc`/`fmvn`, or
+    /// default to `0 1`).
+    pub fen: String,
+    /// `bm`: the search should choose one of these moves.
+    pub best_moves: Vec<Move>,
+    /// `am`: the search should choose none of these moves.
+    pub avoid_moves: Vec<Move>,
+    /// `dm`: the side to move mates in this many moves.
+    pub direct_mate: Option<u32>,
+    /// `id`: the position's name within its suite.
+    pub id: Option<String>,
+    /// `c0`: a free-text comment.
+    pub comment: Option<String>,
+}
+
+impl EpdEntry {
+    /// Parses a single EPD record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the position does not parse, an operation is malformed
+    /// (e.g. an unterminated string), or a `bm`/`am` move is not legal in the
+    /// position.
+    ///
+    /// ```
+    /// use sabertooth::chess_engine::utils::init_tables;
+    /// use sabertooth::epd::EpdEntry;
+    ///
+    /// init_tables();
+    /// let entry = EpdEntry::parse(
+    ///     r#"6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; dm 1; id "back rank";"#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(entry.best_moves[0].to_string(), "a1a8");
+    /// assert_eq!(entry.direct_mate, Some(1));
+    /// assert_eq!(entry.id.as_deref(), Some("back rank"));
+    /// ```
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let mut fields = Vec::new();
+        let mut rest = line;
+        for _ in 0..4 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("EPD record has fewer than four fields: {line}"));
+            }
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+
+        let operations = split_operations(rest)?;
+        let counter = |opcode: &str, default: &'static str| {
+            operations
+                .iter()
+                .find(|(op, _)| op == opcode)
+                .and_then(|(_, operands)| operands.first().cloned())
+                .unwrap_or_else(|| default.to_string())
+        };
+        let fen = format!(
+            "{} {} {}",
+            fields.join(" "),
+            counter("hmvc", "0"),
+            counter("fmvn", "1")
+        );
+        let mut board = Board::from_fen(&fen)?;
+
+        let mut entry = Self {
+            fen,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            direct_mate: None,
+            id: None,
+            comment: None,
+        };
+        for (opcode, operands) in operations {
+            match opcode.as_str() {
+                "bm" | "am" => {
+                    let moves = operands
+                        .iter()
+                        .map(|san| board.parse_san(san))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if opcode == "bm" {
+                        entry.best_moves = moves;
+                    } else {
+                        entry.avoid_moves = moves;
+                    }
+                }
+                "dm" => {
+                    let mate = operands
+                        .first()
+                        .and_then(|n| n.parse::<u32>().ok())
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("invalid dm operand: {operands:?}"))?;
+                    entry.direct_mate = Some(mate);
+                }
+                "id" => entry.id = Some(operands.join(" ")),
+                "c0" => entry.comment = Some(operands.join(" ")),
+                _ => (),
+            }
+        }
+        Ok(entry)
+    }
+
+    /// The record's position.
+    ///
+    /// # Panics
+    ///
+    /// Never: [`parse`](Self::parse) has already validated the FEN.
+    #[must_use]
+    pub fn board(&self) -> Board {
+        Board::from_fen(&self.fen).expect("EPD entry holds a validated FEN")
+    }
+
+    /// Whether `result` satisfies the record's `bm`, `am` and `dm` operations.
+    /// A record with none of them has nothing to check and is always solved.
+    #[must_use]
+    pub fn is_solved_by(&self, result: &SearchResult) -> bool {
+        let Some(best_move) = result.best_move else {
+            return false;
+        };
+        let best_ok = self.best_moves.is_empty() || self.best_moves.contains(&best_move);
+        let avoid_ok = !self.avoid_moves.contains(&best_move);
+        // mate in n moves is 2n - 1 plies, scored MATE_SCORE - plies
+        let mate_ok = self.direct_mate.is_none_or(|moves| {
+            i64::from(result.score) >= i64::from(MATE_SCORE) - (2 * i64::from(moves) - 1)
+        });
+        best_ok && avoid_ok && mate_ok
+    }
+}
+
+/// Splits the operation section of a record into `(opcode, operands)` pairs,
+/// honouring `"..."` strings (which may contain `;`).
+fn split_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut operations = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                if !words.is_empty() {
+                    let opcode = words.remove(0);
+                    operations.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unterminated string in: {text}")),
+                    }
+                }
+                words.push(word);
+            }
+            c if c.is_whitespace() => (),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == ';' || next == '"' {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+    // tolerate a final operation without its terminating ';'
+    if !words.is_empty() {
+        let opcode = words.remove(0);
+        operations.push((opcode, words));
+    }
+    Ok(operations)
+}
+
+/// Parses every record in `text`, one per line; blank lines are skipped.
+///
+/// # Errors
+///
+/// Returns `Err` naming the (1-based) line of the first record that fails to
+/// parse.
+pub fn parse_epd(text: &str) -> Result<Vec<EpdEntry>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| EpdEntry::parse(line).map_err(|e| format!("line {}: {e}", index + 1)))
+        .collect()
+}
+
+/// How much search each position gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// A fixed iterative-deepening depth.
+    Depth(u8),
+    /// Roughly this many nodes (see [`SearchLimits::nodes`]).
+    Nodes(u64),
+    /// A fixed amount of thinking time.
+    Time(Duration),
+}
+
+impl Budget {
+    /// The limits for a search starting now.
+    pub(crate) fn limits(self) -> SearchLimits {
+        match self {
+            Self::Depth(depth) => SearchLimits::depth(depth),
+            Self::Nodes(nodes) => SearchLimits::node_budget(nodes),
+            Self::Time(time) => SearchLimits {
+                deadline: Some(Instant::now() + time),
+                ..SearchLimits::infinite()
+            },
+        }
+    }
+}
+
+/// The result of searching one EPD position.
+#[derive(Debug)]
+pub struct EpdOutcome {
+    /// Whether the search satisfied the record (see [`EpdEntry::is_solved_by`]).
+    pub solved: bool,
+    /// What the search found.
+    pub result: SearchResult,
+    /// Wall-clock time spent on the position.
+    pub elapsed: Duration,
+}
+
+/// Searches `entry`'s position under `budget` with a cleared `tt`, so each
+/// position is judged independently of the ones before it.
+#[must_use]
+pub fn run_position(
+    entry: &EpdEntry,
+    budget: Budget,
+    tt: &TranspositionTable,
+    threads: usize,
+) -> EpdOutcome {
+    let stop = AtomicBool::new(false);
+    let mut board = entry.board();
+    tt.clear();
+    let start = Instant::now();
+    let result = search_position(&mut board, &budget.limits(), &stop, tt, threads, false);
+    EpdOutcome {
+        solved: entry.is_solved_by(&result),
+        result,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Runs every entry in order (see [`run_position`]), calling `on_outcome` as
+/// each finishes, and returns the outcomes in entry order.
+pub fn run_suite(
+    entries: &[EpdEntry],
+    budget: Budget,
+    threads: usize,
+    mut on_outcome: impl FnMut(usize, &EpdEntry, &EpdOutcome),
+) -> Vec<EpdOutcome> {
+    let tt = TranspositionTable::new();
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let outcome = run_position(entry, budget, &tt, threads);
+            on_outcome(index, entry, &outcome);
+            outcome
+        })
+        .collect()
+}
+
+/// The `epd` subcommand: `epd <file> [depth <n> | nodes <n> | movetime <ms>]
+/// [threads <n>]`. Prints one line per position and a solved/failed summary;
+/// the budget defaults to a fixed depth.
+///
+/// # Errors
+///
+/// Returns `Err` if the arguments are malformed, or the file cannot be read or
+/// parsed.
+pub fn epd_command(args: &[String]) -> Result<(), String> {
+    let usage = "usage: epd <file> [depth <n> | nodes <n> | movetime <ms>] [threads <n>]";
+    let Some(path) = args.first() else {
+        return Err(usage.to_string());
+    };
+
+    let mut budget = Budget::Depth(DEFAULT_DEPTH);
+    let mut threads = 1;
+    let mut iter = args[1..].iter();
+    while let Some(option) = iter.next() {
+        let value = iter
+            .next()
+            .and_then(|v| v.parse::<u64>().ok())
+            .ok_or_else(|| format!("{option} needs a numeric value; {usage}"))?;
+        match option.as_str() {
+            "depth" => budget = Budget::Depth(u8::try_from(value).unwrap_or(MAX_DEPTH)),
+            "nodes" => budget = Budget::Nodes(value),
+            "movetime" => budget = Budget::Time(Duration::from_millis(value)),
+            "threads" => threads = usize::try_from(value).unwrap_or(1).max(1),
+            _ => return Err(format!("unknown option {option}; {usage}")),
+        }
+    }
+
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let entries = parse_epd(&text).map_err(|e| format!("{path}: {e}"))?;
+
+    let start = Instant::now();
+    let mut failed = Vec::new();
+    let outcomes = run_suite(&entries, budget, threads, |index, entry, outcome| {
+        let id = entry
+            .id
+            .clone()
+            .unwrap_or_else(|| format!("#{}", index + 1));
+        let played = outcome
+            .result
+            .best_move
+            .map_or_else(|| "(none)".to_string(), |mv| entry.board().move_to_san(mv));
+        println!(
+            "{id:<12} {:<6} {played:<8} depth {:>2} nodes {:>10} {:>8.3}s",
+            if outcome.solved { "solved" } else { "failed" },
+            outcome.result.depth,
+            outcome.result.nodes,
+            outcome.elapsed.as_secs_f64()
+        );
+        if !outcome.solved {
+            failed.push(id);
+        }
+    });
+
+    let solved = outcomes.iter().filter(|outcome| outcome.solved).count();
+    println!(
+        "Solved {solved}/{} in {:.3}s",
+        outcomes.len(),
+        start.elapsed().as_secs_f64()
+    );
+    if !failed.is_empty() {
+        println!("Failed: {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Budget, EpdEntry, parse_epd, run_suite};
+    use crate::chess_engine::utils::init_tables;
+
+    #[test]
+    fn parses_operations_and_counters() {
+        init_tables();
+        let entry = EpdEntry::parse(
+            r#"8/1p6/p5R1/k7/Prpp4/K7/1NP5/8 w - - am Rd6; bm Rb6 Rg5+; c0 "Rb6; or Rg5+"; hmvc 7; fmvn 42; id "WAC.274";"#,
+        )
+        .unwrap();
+        assert_eq!(entry.fen, "8/1p6/p5R1/k7/Prpp4/K7/1NP5/8 w - - 7 42");
+        let best: Vec<String> = entry.best_moves.iter().map(ToString::to_string).collect();
+        assert_eq!(best, ["g6b6", "g6g5"]);
+        assert_eq!(entry.avoid_moves[0].to_string(), "g6d6");
+        assert_eq!(entry.comment.as_deref(), Some("Rb6; or Rg5+"));
+        assert_eq!(entry.id.as_deref(), Some("WAC.274"));
+        assert_eq!(entry.direct_mate, None);
+    }
+
+    #[test]
+    fn reports_malformed_records() {
+        init_tables();
+        assert!(EpdEntry::parse("8/8/8/8 w -").is_err());
+        assert!(EpdEntry::parse("4k3/8/8/8/8/8/8/4K3 w - - bm e4;").is_err());
+        assert!(EpdEntry::parse(r#"4k3/8/8/8/8/8/8/4K3 w - - id "open"#).is_err());
+        assert!(EpdEntry::parse("4k3/8/8/8/8/8/8/4K3 w - - dm 0;").is_err());
+
+        let Err(err) = parse_epd("4k3/8/8/8/8/8/8/4K3 w - - id \"a\";\n\nbad") else {
+            panic!("a truncated record should not parse");
+        };
+        assert!(err.starts_with("line 3:"), "{err}");
+    }
+
+    #[test]
+    fn judges_best_avoid_and_mate_operations() {
+        init_tables();
+        let suite = parse_epd(
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"mate\";\n\
+             6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra8#; id \"avoid\";\n\
+             6k1/5ppp/8/8/8/8/8/R5K1 w - - dm 1; id \"dm\";\n\
+             6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Kf1; id \"wrong\";",
+        )
+        .unwrap();
+
+        for budget in [Budget::Depth(3), Budget::Nodes(50_000)] {
+            let mut seen = Vec::new();
+            let outcomes = run_suite(&suite, budget, 1, |index, _, _| seen.push(index));
+            assert_eq!(seen, [0, 1, 2, 3]);
+            let solved: Vec<bool> = outcomes.iter().map(|outcome| outcome.solved).collect();
+            assert_eq!(solved, [true, false, true, false], "{budget:?}");
+        }
+    }
+}
@@ -1,11 +1,15 @@
 //! A UCI-compatible chess engine.
 //!
-//! The crate is organised into three modules:
+//! The crate is organised into seven modules:
 //!
 //! - [`chess_engine`] — all the core logic: the [`Board`](chess_engine::board::Board)
 //!   position type, move generation, make/unmake, and the search/evaluation
 //!   [`engine`](chess_engine::engine).
 //! - [`perft`] — node-count testing, the correctness oracle for move generation.
+//! - [`datagen`] — self-play generation of labelled training positions.
+//! - [`epd`] — EPD test suites (WAC, STS, …): parsing and a solved/failed runner.
+//! - [`match_runner`] — engine-vs-engine matches with Elo and SPRT reporting.
+//! - [`tuning`] — Texel tuning of the evaluation weights against game results.
 //! - [`uci`] — the [Universal Chess Interface] protocol loop that drives the
 //!   engine over stdin/stdout.
 //!
@@ -32,5 +36,9 @@
 #![warn(missing_docs)]
 
 pub mod chess_engine;
+pub mod datagen;
+pub mod epd;
+pub mod match_runner;
 pub mod perft;
+pub mod tuning;
 pub mod uci;
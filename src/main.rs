@@ -1,11 +1,39 @@
 //! Binary entry point: initialise the lookup tables, then hand control to the
-//! UCI command loop. See the [`chess_engine`] library crate for the engine
-//! itself.
+//! UCI command loop, or run an EPD test suite when invoked as
+//! `sabertooth epd <file> ...`, the evaluation tuner as
+//! `sabertooth tune <dataset> ...`, a match between two engines as
+//! `sabertooth match <engine1> <engine2> ...`, the training-data generator as
+//! `sabertooth datagen <file> ...`, or the opening-book builder as
+//! `sabertooth book <games.pgn> <book.bin> ...`. See the [`chess_engine`]
+//! library crate for the engine itself.
 
-use sabertooth::{chess_engine::utils::init_tables, uci::uci_protocol};
+use sabertooth::{
+    chess_engine::{polyglot::book_command, utils::init_tables},
+    datagen::datagen_command,
+    epd::epd_command,
+    match_runner::match_command,
+    tuning::tune_command,
+    uci::uci_protocol,
+};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     init_tables();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|command| command == "epd") {
+        return Ok(epd_command(&args[1..])?);
+    }
+    if args.first().is_some_and(|command| command == "tune") {
+        return Ok(tune_command(&args[1..])?);
+    }
+    if args.first().is_some_and(|command| command == "match") {
+        return Ok(match_command(&args[1..])?);
+    }
+    if args.first().is_some_and(|command| command == "datagen") {
+        return Ok(datagen_command(&args[1..])?);
+    }
+    if args.first().is_some_and(|command| command == "book") {
+        return Ok(book_command(&args[1..])?);
+    }
     // the command loop replies to `uci` with the id/uciok handshake and
     // handles everything else (position, go, stop, quit, ...)
     uci_protocol()
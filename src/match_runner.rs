@@ -0,0 +1,1241 @@
+//! Engine-vs-engine matches: playing two configurations against each other and
+//! judging which is stronger.
+//!
+//! Each side of a match is an [`EngineSpec`]: this engine run in-process
+//! through [`search_position`] (with its own hash size, thread count, optional
+//! NNUE network and optional evaluation weights), or any UCI engine run as a
+//! subprocess (such as an older build of this one). Games start from an
+//! openings file, and every opening is played twice with colours reversed, so
+//! neither side profits from a lopsided opening. A game ends by the rules —
+//! checkmate, stalemate, insufficient material, threefold repetition, the
+//! fifty-move rule — or is *adjudicated* once both engines' scores agree the
+//! game is decided (resign) or dead level (draw), or when it reaches a move
+//! limit. An engine that returns no legal move, or overruns a `movetime`
+//! budget by more than a second, forfeits.
+//!
+//! The result is reported as the first engine's [`MatchScore`]: its Elo
+//! difference with a 95% error bar, and the log-likelihood ratio of a
+//! [sequential probability ratio test][sprt] (SPRT) of `elo1` against `elo0`.
+//! The match stops as soon as the SPRT accepts either hypothesis.
+//! [`match_command`] wraps this as the binary's `match` subcommand:
+//!
+//! ```text
+//! sabertooth match self uci:./sabertooth-old openings book.epd games 2000 nodes 20000
+//! sabertooth match self,evalfile=net.bin self movetime 100 sprt 0 10 pgnout games.pgn
+//! ```
+//!
+//! [sprt]: https://www.chessprogramming.org/Sequential_Probability_Ratio_Test
+
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    chess_engine::{
+        board::{Board, WHITE},
+        engine::evaluation::EvalWeights,
+        engine::nnue::Network,
+        engine::search::{MATE_SCORE, MAX_DEPTH, search_position},
+        engine::transposition::{DEFAULT_HASH_MB, TranspositionTable},
+        moves::Move,
+        pgn::{GameResult, PgnEval, PgnGame},
+    },
+    epd::Budget,
+};
+
+/// Games the `match` subcommand plays when not told otherwise.
+const DEFAULT_GAMES: usize = 100;
+/// Thinking time per move when no budget is given, in milliseconds.
+const DEFAULT_MOVETIME_MS: u64 = 100;
+/// Score (centipawns) both engines must agree on for a resign adjudication.
+const DEFAULT_RESIGN_SCORE: i32 = 1000;
+/// Largest score (centipawns) both engines may report for a draw adjudication.
+const DEFAULT_DRAW_SCORE: i32 = 10;
+/// Plies after which a game is called a draw.
+const DEFAULT_MAX_PLIES: usize = 400;
+/// Consecutive moves *per side* the resign condition must hold for.
+const RESIGN_MOVES: usize = 3;
+/// Consecutive moves per side the draw condition must hold for.
+const DRAW_MOVES: usize = 8;
+/// Plies that must have been played before a draw can be adjudicated.
+const DRAW_AFTER_PLIES: usize = 80;
+/// Two-sided 95% quantile of the normal distribution.
+const Z_95: f64 = 1.959_964;
+/// How long a UCI engine may take to answer `uci` or `isready`.
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time a UCI engine may overrun a `movetime` budget by before it forfeits.
+const MOVE_TIME_MARGIN: Duration = Duration::from_secs(1);
+
+/// One side of a match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineSpec {
+    /// This engine, searched in-process.
+    Internal {
+        /// Transposition-table size in MiB.
+        hash_mb: usize,
+        /// Search threads.
+        threads: usize,
+        /// NNUE network file to evaluate with, if any.
+        eval_file: Option<String>,
+        /// Classical evaluation weights file to play with, if any.
+        weights_file: Option<String>,
+    },
+    /// A UCI engine run as a subprocess.
+    Uci {
+        /// The program to run.
+        command: String,
+        /// `setoption` name/value pairs sent after the handshake.
+        options: Vec<(String, String)>,
+    },
+}
+
+impl EngineSpec {
+    /// Parses `self` with any of the settings `hash=<mb>`, `threads=<n>`,
+    /// `evalfile=<path>` and `weights=<path>` (comma-separated), or
+    /// `uci:<command>[,<option>=<value>...]`.
+    ///
+    /// # Errors
+    /// Returns an error for any other form or a malformed setting.
+    ///
+    /// ```
+    /// use sabertooth::match_runner::EngineSpec;
+    ///
+    /// let spec = EngineSpec::parse("uci:./old,Hash=16").unwrap();
+    /// assert_eq!(
+    ///     spec,
+    ///     EngineSpec::Uci {
+    ///         command: "./old".to_string(),
+    ///         options: vec![("Hash".to_string(), "16".to_string())],
+    ///     }
+    /// );
+    /// assert!(EngineSpec::parse("self,hash=lots").is_err());
+    /// ```
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(',');
+        let head = parts.next().unwrap_or_default();
+        let mut settings = Vec::new();
+        for part in parts {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected <name>=<value> in engine {spec}, got {part}"))?;
+            settings.push((name.to_string(), value.to_string()));
+        }
+
+        if let Some(command) = head.strip_prefix("uci:") {
+            if command.is_empty() {
+                return Err(format!("no command in engine {spec}"));
+            }
+            return Ok(Self::Uci {
+                command: command.to_string(),
+                options: settings,
+            });
+        }
+        if head != "self" {
+            return Err(format!(
+                "unknown engine {spec}; expected self[,...] or uci:<command>[,...]"
+            ));
+        }
+        let mut hash_mb = DEFAULT_HASH_MB;
+        let mut threads = 1;
+        let mut eval_file = None;
+        let mut weights_file = None;
+        for (name, value) in settings {
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("{name} needs a positive number, got {value}"))
+            };
+            match name.as_str() {
+                "hash" => hash_mb = number()?,
+                "threads" => threads = number()?,
+                "evalfile" => eval_file = Some(value),
+                "weights" => weights_file = Some(value),
+                _ => return Err(format!("unknown setting {name} in engine {spec}")),
+            }
+        }
+        Ok(Self::Internal {
+            hash_mb,
+            threads,
+            eval_file,
+            weights_file,
+        })
+    }
+
+    /// Starts the engine, named `name` in the game records.
+    ///
+    /// # Errors
+    /// Returns an error if a network or weights file cannot be loaded, or a UCI
+    /// engine cannot be started or fails its handshake.
+    pub fn start(&self, name: &str) -> Result<Box<dyn Player>, String> {
+        match self {
+            Self::Internal {
+                hash_mb,
+                threads,
+                eval_file,
+                weights_file,
+            } => {
+                let network = eval_file
+                    .as_ref()
+                    .map(|path| Network::load(Path::new(path)).map(Arc::new))
+                    .transpose()?;
+                let weights = weights_file
+                    .as_ref()
+                    .map(|path| EvalWeights::open(Path::new(path)).map(Arc::new))
+                    .transpose()?;
+                Ok(Box::new(InternalPlayer {
+                    name: name.to_string(),
+                    tt: TranspositionTable::with_size_mb(*hash_mb),
+                    threads: *threads,
+                    network,
+                    weights,
+                }))
+            }
+            Self::Uci { command, options } => {
+                Ok(Box::new(UciPlayer::start(name, command, options)?))
+            }
+        }
+    }
+}
+
+/// What an engine answered when asked to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reply {
+    /// The move chosen, or `None` if the engine gave no legal move.
+    pub mv: Option<Move>,
+    /// The engine's score for the side to move, if it reported one.
+    pub score: Option<i32>,
+    /// The depth the engine reported reaching.
+    pub depth: u8,
+    /// Whether the engine ran out of time without choosing a move.
+    pub timed_out: bool,
+}
+
+/// An engine that can play games.
+pub trait Player {
+    /// The name recorded in games.
+    fn name(&self) -> &str;
+
+    /// Prepares for a new game.
+    ///
+    /// # Errors
+    /// Returns an error if the engine stopped responding.
+    fn new_game(&mut self) -> Result<(), String>;
+
+    /// Chooses a move in the position reached by playing `moves` from `start`.
+    ///
+    /// # Errors
+    /// Returns an error if the engine stopped responding.
+    fn play(&mut self, start: &Board, moves: &[Move], budget: Budget) -> Result<Reply, String>;
+}
+
+/// This engine, searching in-process with its own transposition table.
+struct InternalPlayer {
+    name: String,
+    tt: TranspositionTable,
+    threads: usize,
+    network: Option<Arc<Network>>,
+    weights: Option<Arc<EvalWeights>>,
+}
+
+impl Player for InternalPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        self.tt.clear();
+        Ok(())
+    }
+
+    fn play(&mut self, start: &Board, moves: &[Move], budget: Budget) -> Result<Reply, String> {
+        let mut board = start.clone();
+        for &mv in moves {
+            board.commit_verified_move(mv);
+        }
+        board.set_network(self.network.clone());
+        board.set_weights(self.weights.clone());
+        let stop = AtomicBool::new(false);
+        let result = search_position(
+            &mut board,
+            &budget.limits(),
+            &stop,
+            &self.tt,
+            self.threads,
+            false,
+        );
+        Ok(Reply {
+            mv: result.best_move,
+            score: Some(result.score),
+            depth: result.depth,
+            timed_out: false,
+        })
+    }
+}
+
+/// A UCI engine subprocess.
+struct UciPlayer {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    /// The engine's output, line by line, read on a thread of its own so that
+    /// waiting for it can time out.
+    lines: Receiver<String>,
+    /// The `UCI_Chess960` value last sent.
+    chess960: bool,
+}
+
+impl UciPlayer {
+    /// Runs `command`, completes the `uci` handshake and sends `options`.
+    fn start(name: &str, command: &str, options: &[(String, String)]) -> Result<Self, String> {
+        let mut child = Command::new(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Cannot start {command}: {e}"))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(format!("Cannot talk to {command}"));
+        };
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut player = Self {
+            name: name.to_string(),
+            child,
+            stdin,
+            lines,
+            chess960: false,
+        };
+        player.send("uci")?;
+        player.expect("uciok", READY_TIMEOUT)?;
+        for (option, value) in options {
+            player.send(&format!("setoption name {option} value {value}"))?;
+        }
+        player.send("isready")?;
+        player.expect("readyok", READY_TIMEOUT)?;
+        Ok(player)
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{line}")
+            .and_then(|()| self.stdin.flush())
+            .map_err(|e| format!("{}: {e}", self.name))
+    }
+
+    /// Reads lines until one starting with `keyword`, returning them all, or
+    /// `None` if `timeout` passes first.
+    ///
+    /// # Errors
+    /// Returns an error if the engine exits first.
+    fn read_until(
+        &self,
+        keyword: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<String>>, String> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut lines = Vec::new();
+        loop {
+            let line = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    match self.lines.recv_timeout(left) {
+                        Ok(line) => line,
+                        Err(RecvTimeoutError::Timeout) => return Ok(None),
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match self.lines.recv() {
+                    Ok(line) => line,
+                    Err(_) => break,
+                },
+            };
+            let line = line.trim().to_string();
+            let done = line.split_whitespace().next() == Some(keyword);
+            lines.push(line);
+            if done {
+                return Ok(Some(lines));
+            }
+        }
+        Err(format!("{} exited while waiting for {keyword}", self.name))
+    }
+
+    /// Like [`read_until`](Self::read_until), but with a timeout an engine
+    /// must answer within.
+    fn expect(&self, keyword: &str, timeout: Duration) -> Result<Vec<String>, String> {
+        self.read_until(keyword, Some(timeout))?
+            .ok_or_else(|| format!("{} did not send {keyword} in time", self.name))
+    }
+}
+
+impl Player for UciPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        // a bestmove left over from a move that timed out is skipped here
+        self.expect("readyok", READY_TIMEOUT).map(drop)
+    }
+
+    fn play(&mut self, start: &Board, moves: &[Move], budget: Budget) -> Result<Reply, String> {
+        if start.chess960 != self.chess960 {
+            self.send(&format!(
+                "setoption name UCI_Chess960 value {}",
+                start.chess960
+            ))?;
+            self.chess960 = start.chess960;
+        }
+        let mut position = format!("position fen {}", start.to_fen());
+        if !moves.is_empty() {
+            position.push_str(" moves");
+            for mv in moves {
+                position.push(' ');
+                position.push_str(&mv.to_string());
+            }
+        }
+        self.send(&position)?;
+        self.send(&match budget {
+            Budget::Depth(depth) => format!("go depth {depth}"),
+            Budget::Nodes(nodes) => format!("go nodes {nodes}"),
+            Budget::Time(time) => format!("go movetime {}", time.as_millis()),
+        })?;
+
+        let mut reply = Reply {
+            mv: None,
+            score: None,
+            depth: 0,
+            timed_out: false,
+        };
+        // depth and node budgets have no clock to hold the engine to
+        let timeout = match budget {
+            Budget::Time(time) => Some(time + MOVE_TIME_MARGIN),
+            Budget::Depth(_) | Budget::Nodes(_) => None,
+        };
+        let Some(lines) = self.read_until("bestmove", timeout)? else {
+            self.send("stop")?;
+            reply.timed_out = true;
+            return Ok(reply);
+        };
+        for line in &lines[..lines.len() - 1] {
+            if let Some((depth, score)) = parse_info(line) {
+                reply.depth = depth;
+                reply.score = Some(score);
+            }
+        }
+        let best = lines[lines.len() - 1].split_whitespace().nth(1);
+        let mut board = start.clone();
+        for &mv in moves {
+            board.commit_verified_move(mv);
+        }
+        reply.mv = board
+            .generate_moves(board.turn)
+            .into_iter()
+            .find(|mv| best == Some(mv.to_string().as_str()));
+        Ok(reply)
+    }
+}
+
+impl Drop for UciPlayer {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.wait();
+    }
+}
+
+/// The depth and score of an `info` line that carries both.
+fn parse_info(line: &str) -> Option<(u8, i32)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.first() != Some(&"info") {
+        return None;
+    }
+    let after = |key: &str| {
+        words
+            .iter()
+            .position(|&word| word == key)
+            .and_then(|index| words.get(index + 1))
+    };
+    let depth = after("depth")?.parse::<u8>().ok()?;
+    let score_index = words.iter().position(|&word| word == "score")?;
+    let value = words.get(score_index + 2)?.parse::<i32>().ok()?;
+    let score = match *words.get(score_index + 1)? {
+        "cp" => value,
+        // mate in n moves is 2n - 1 plies for the winner, 2n for the loser
+        "mate" if value > 0 => MATE_SCORE - (2 * value - 1),
+        "mate" => -(MATE_SCORE + 2 * value),
+        _ => return None,
+    };
+    Some((depth.min(MAX_DEPTH), score))
+}
+
+/// When games are cut short before the rules end them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adjudication {
+    /// A game is won once both engines have agreed for `RESIGN_MOVES` moves
+    /// each that one side is at least this many centipawns ahead.
+    pub resign_score: Option<i32>,
+    /// After `DRAW_AFTER_PLIES` plies, a game is drawn once both engines have
+    /// scored it within this many centipawns of level for `DRAW_MOVES` moves
+    /// each.
+    pub draw_score: Option<i32>,
+    /// A game reaching this many plies is drawn.
+    pub max_plies: usize,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            resign_score: Some(DEFAULT_RESIGN_SCORE),
+            draw_score: Some(DEFAULT_DRAW_SCORE),
+            max_plies: DEFAULT_MAX_PLIES,
+        }
+    }
+}
+
+impl Adjudication {
+    /// The adjudicated result, if any, given every ply's reported score from
+    /// White's point of view.
+    pub(crate) fn judge(&self, white_scores: &[Option<i32>]) -> Option<(GameResult, &'static str)> {
+        let last = |moves: usize| {
+            white_scores
+                .len()
+                .checked_sub(2 * moves)
+                .map(|from| &white_scores[from..])
+        };
+        if let (Some(resign), Some(scores)) = (self.resign_score, last(RESIGN_MOVES)) {
+            if scores
+                .iter()
+                .all(|score| score.is_some_and(|s| s >= resign))
+            {
+                return Some((GameResult::WhiteWins, "adjudicated win"));
+            }
+            if scores
+                .iter()
+                .all(|score| score.is_some_and(|s| s <= -resign))
+            {
+                return Some((GameResult::BlackWins, "adjudicated win"));
+            }
+        }
+        if let (Some(draw), Some(scores)) = (self.draw_score, last(DRAW_MOVES))
+            && white_scores.len() >= DRAW_AFTER_PLIES
+            && scores
+                .iter()
+                .all(|score| score.is_some_and(|s| s.abs() <= draw))
+        {
+            return Some((GameResult::Draw, "adjudicated draw"));
+        }
+        (white_scores.len() >= self.max_plies).then_some((GameResult::Draw, "move limit"))
+    }
+}
+
+/// The result, if the rules have ended the game in `board`'s position (see
+/// [`Board::outcome`]), with what ended it.
+pub(crate) fn rules_verdict(board: &mut Board) -> Option<(GameResult, &'static str)> {
+    let status = board.outcome();
+    status
+        .is_over()
+        .then(|| (status.result(board.turn), status.description()))
+}
+
+/// A finished game and why it ended.
+#[derive(Debug, Clone)]
+pub struct PlayedGame {
+    /// The game record, with each move's reported evaluation.
+    pub pgn: PgnGame,
+    /// What ended the game, e.g. `checkmate` or `adjudicated draw`.
+    pub reason: &'static str,
+}
+
+/// Plays one game from `opening` and returns its record.
+///
+/// # Errors
+/// Returns an error if either engine stops responding.
+pub fn play_game(
+    white: &mut dyn Player,
+    black: &mut dyn Player,
+    opening: &Board,
+    budget: Budget,
+    adjudication: &Adjudication,
+) -> Result<PlayedGame, String> {
+    white.new_game()?;
+    black.new_game()?;
+    let mut pgn = PgnGame::new(opening);
+    pgn.set_tag("White", white.name());
+    pgn.set_tag("Black", black.name());
+
+    let mut board = opening.clone();
+    let mut moves = Vec::new();
+    let mut white_scores = Vec::new();
+    let (result, reason) = loop {
+        if let Some(verdict) = rules_verdict(&mut board) {
+            break verdict;
+        }
+
+        let reply = if board.turn == WHITE {
+            white.play(opening, &moves, budget)
+        } else {
+            black.play(opening, &moves, budget)
+        }?;
+        let Some(mv) = reply.mv else {
+            let winner = if board.turn == WHITE {
+                GameResult::BlackWins
+            } else {
+                GameResult::WhiteWins
+            };
+            break (
+                winner,
+                if reply.timed_out {
+                    "time forfeit"
+                } else {
+                    "illegal move"
+                },
+            );
+        };
+        pgn.push_move(
+            mv,
+            reply.score.map(|score| PgnEval {
+                score,
+                depth: reply.depth,
+            }),
+        );
+        white_scores.push(if board.turn == WHITE {
+            reply.score
+        } else {
+            reply.score.map(|score| -score)
+        });
+        board.commit_verified_move(mv);
+        moves.push(mv);
+        if let Some(verdict) = adjudication.judge(&white_scores) {
+            break verdict;
+        }
+    };
+    pgn.set_result(result);
+    pgn.set_tag("Termination", reason);
+    Ok(PlayedGame { pgn, reason })
+}
+
+/// The first engine's results in a match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    /// Games won.
+    pub wins: u32,
+    /// Games drawn.
+    pub draws: u32,
+    /// Games lost.
+    pub losses: u32,
+}
+
+/// Elo difference implied by an expected score.
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Expected score implied by an Elo difference.
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl MatchScore {
+    /// Games played.
+    #[must_use]
+    pub const fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game: 1 for a win, ½ for a draw.
+    #[must_use]
+    pub fn score(&self) -> f64 {
+        f64::from(2 * self.wins + self.draws) / f64::from(2 * self.games().max(1))
+    }
+
+    /// Mean and variance of a single game's points, estimated with one extra
+    /// win and one extra loss as a prior. Without it a clean sweep (or a run of
+    /// draws) would have no variance, leaving the LLR and error bar undefined.
+    fn regularised(&self) -> (f64, f64) {
+        let wins = f64::from(self.wins) + 1.0;
+        let draws = f64::from(self.draws);
+        let losses = f64::from(self.losses) + 1.0;
+        let games = wins + draws + losses;
+        let score = draws.mul_add(0.5, wins) / games;
+        let variance = wins.mul_add(
+            (1.0 - score).powi(2),
+            draws.mul_add((0.5 - score).powi(2), losses * score.powi(2)),
+        ) / games;
+        (score, variance)
+    }
+
+    /// The Elo difference the results imply (infinite for a clean sweep).
+    #[must_use]
+    pub fn elo(&self) -> f64 {
+        elo_from_score(self.score())
+    }
+
+    /// Half-width of the 95% confidence interval around [`elo`](Self::elo),
+    /// carried over from the score by the slope of the Elo curve.
+    #[must_use]
+    pub fn elo_error(&self) -> f64 {
+        let (score, variance) = self.regularised();
+        let margin = Z_95 * (variance / f64::from(self.games().max(1))).sqrt();
+        margin * 400.0 / (std::f64::consts::LN_10 * score * (1.0 - score))
+    }
+
+    /// Log-likelihood ratio of the results under `elo1` against `elo0`, by the
+    /// normal approximation to the generalised SPRT.
+    #[must_use]
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let (score, variance) = self.regularised();
+        let (score0, score1) = (score_from_elo(elo0), score_from_elo(elo1));
+        f64::from(self.games()) * (score1 - score0) * (2.0f64.mul_add(score, -score0) - score1)
+            / (2.0 * variance)
+    }
+
+    /// Records a game's result for the first engine, which played White if
+    /// `first_is_white`.
+    const fn record(&mut self, result: GameResult, first_is_white: bool) {
+        match (result, first_is_white) {
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => self.wins += 1,
+            (GameResult::WhiteWins, false) | (GameResult::BlackWins, true) => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+}
+
+/// A sequential probability ratio test of `elo1` (the change gains that much)
+/// against `elo0`, with error rates `alpha` and `beta`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    /// Elo difference of the null hypothesis.
+    pub elo0: f64,
+    /// Elo difference of the alternative hypothesis.
+    pub elo1: f64,
+    /// Chance of accepting `elo1` when `elo0` holds.
+    pub alpha: f64,
+    /// Chance of accepting `elo0` when `elo1` holds.
+    pub beta: f64,
+}
+
+/// Where an SPRT stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtVerdict {
+    /// The LLR crossed the upper bound: `elo1` holds.
+    H1Accepted,
+    /// The LLR crossed the lower bound: `elo0` holds.
+    H0Accepted,
+    /// More games are needed.
+    Continue,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// The `(lower, upper)` LLR bounds.
+    #[must_use]
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// The test's verdict on `score`.
+    #[must_use]
+    pub fn verdict(&self, score: &MatchScore) -> SprtVerdict {
+        let llr = score.llr(self.elo0, self.elo1);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtVerdict::H1Accepted
+        } else if llr <= lower {
+            SprtVerdict::H0Accepted
+        } else {
+            SprtVerdict::Continue
+        }
+    }
+}
+
+/// How a match is played.
+#[derive(Debug, Clone)]
+pub struct MatchSettings {
+    /// Most games to play; the match stops sooner if the SPRT decides.
+    pub games: usize,
+    /// Search budget per move.
+    pub budget: Budget,
+    /// When games are cut short.
+    pub adjudication: Adjudication,
+    /// The test run alongside the match.
+    pub sprt: Sprt,
+}
+
+/// Plays a match between `first` and `second` and returns `first`'s score.
+///
+/// Game `i` starts from opening `i / 2` (cycling through `openings`), with
+/// `first` White in even games and Black in odd ones. `on_game` hears each
+/// game's number, record and the running score.
+///
+/// # Errors
+/// Returns an error if `openings` is empty or an engine stops responding.
+pub fn run_match(
+    first: &mut dyn Player,
+    second: &mut dyn Player,
+    openings: &[Board],
+    settings: &MatchSettings,
+    mut on_game: impl FnMut(usize, &PlayedGame, &MatchScore),
+) -> Result<MatchScore, String> {
+    if openings.is_empty() {
+        return Err("no openings to play".to_string());
+    }
+    let mut score = MatchScore::default();
+    for index in 0..settings.games {
+        let opening = &openings[(index / 2) % openings.len()];
+        let first_is_white = index % 2 == 0;
+        let mut game = if first_is_white {
+            play_game(
+                first,
+                second,
+                opening,
+                settings.budget,
+                &settings.adjudication,
+            )
+        } else {
+            play_game(
+                second,
+                first,
+                opening,
+                settings.budget,
+                &settings.adjudication,
+            )
+        }?;
+        game.pgn.set_tag("Round", &(index + 1).to_string());
+        score.record(game.pgn.result, first_is_white);
+        on_game(index, &game, &score);
+        if settings.sprt.verdict(&score) != SprtVerdict::Continue {
+            break;
+        }
+    }
+    Ok(score)
+}
+
+/// Parses an openings file: one position per line, as a FEN, an EPD record
+/// (whose operations are ignored) or `startpos`. Blank lines and `#` comments
+/// are skipped.
+///
+/// # Errors
+/// Returns the first malformed line's error, prefixed with its line number.
+pub fn parse_openings(text: &str) -> Result<Vec<Board>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| parse_opening(line).map_err(|e| format!("line {}: {e}", index + 1)))
+        .collect()
+}
+
+/// Parses one line of an openings file.
+fn parse_opening(line: &str) -> Result<Board, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields == ["startpos"] {
+        return Ok(Board::new_start_pos()?);
+    }
+    // a full FEN, or the four position fields of an EPD line
+    let counters = fields.len() >= 6
+        && fields[4..6]
+            .iter()
+            .all(|field| field.parse::<u16>().is_ok());
+    let position = fields
+        .get(..if counters { 6 } else { 4 })
+        .ok_or_else(|| format!("too few fields for a position: {line}"))?;
+    Ok(Board::from_fen_lenient(&position.join(" "))?)
+}
+
+/// Renders a possibly infinite Elo figure.
+fn format_elo(elo: f64) -> String {
+    if elo.is_finite() {
+        // adding zero turns a negative zero into a positive one
+        format!("{:+.1}", elo + 0.0)
+    } else if elo > 0.0 {
+        "+inf".to_string()
+    } else {
+        "-inf".to_string()
+    }
+}
+
+/// The `match` subcommand: plays two engines against each other.
+///
+/// Usage: `match <engine1> <engine2> [games <n>] [openings <file>] [depth <n>
+/// | nodes <n> | movetime <ms>] [resign <cp>] [draw <cp>] [maxplies <n>]
+/// [sprt <elo0> <elo1>] [alpha <a>] [beta <b>] [pgnout <file>]`. Engines are
+/// given as in [`EngineSpec::parse`]; `resign 0` or `draw 0` turns that
+/// adjudication off. Prints a line per game with the running score, Elo and
+/// LLR, then the final verdict.
+///
+/// # Errors
+///
+/// Returns `Err` if the arguments are malformed, a file cannot be read or
+/// written, or an engine fails.
+#[allow(clippy::too_many_lines)]
+pub fn match_command(args: &[String]) -> Result<(), String> {
+    let usage = "usage: match <engine1> <engine2> [games <n>] [openings <file>] \
+                 [depth <n> | nodes <n> | movetime <ms>] [resign <cp>] [draw <cp>] \
+                 [maxplies <n>] [sprt <elo0> <elo1>] [alpha <a>] [beta <b>] [pgnout <file>]";
+    let [first_spec, second_spec, rest @ ..] = args else {
+        return Err(usage.to_string());
+    };
+    let first_spec = EngineSpec::parse(first_spec)?;
+    let second_spec = EngineSpec::parse(second_spec)?;
+
+    let mut settings = MatchSettings {
+        games: DEFAULT_GAMES,
+        budget: Budget::Time(Duration::from_millis(DEFAULT_MOVETIME_MS)),
+        adjudication: Adjudication::default(),
+        sprt: Sprt::default(),
+    };
+    let mut openings = vec![Board::new_start_pos()?];
+    let mut pgn_out = None;
+    let mut iter = rest.iter();
+    while let Some(option) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("{option} needs a value; {usage}"))
+        };
+        let number = |value: &String| value.parse::<f64>().map_err(|e| format!("{option}: {e}"));
+        let count = |value: &String| value.parse::<u64>().map_err(|e| format!("{option}: {e}"));
+        let centipawns = |value: &String| {
+            value
+                .parse::<i32>()
+                .map(|cp| (cp > 0).then_some(cp))
+                .map_err(|e| format!("{option}: {e}"))
+        };
+        match option.as_str() {
+            "games" => settings.games = usize::try_from(count(value()?)?).unwrap_or(usize::MAX),
+            "openings" => {
+                let path = value()?;
+                let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+                openings = parse_openings(&text).map_err(|e| format!("{path}: {e}"))?;
+            }
+            "depth" => {
+                settings.budget =
+                    Budget::Depth(u8::try_from(count(value()?)?).unwrap_or(MAX_DEPTH));
+            }
+            "nodes" => settings.budget = Budget::Nodes(count(value()?)?),
+            "movetime" => settings.budget = Budget::Time(Duration::from_millis(count(value()?)?)),
+            "resign" => settings.adjudication.resign_score = centipawns(value()?)?,
+            "draw" => settings.adjudication.draw_score = centipawns(value()?)?,
+            "maxplies" => {
+                settings.adjudication.max_plies =
+                    usize::try_from(count(value()?)?).unwrap_or(usize::MAX);
+            }
+            "sprt" => {
+                settings.sprt.elo0 = number(value()?)?;
+                settings.sprt.elo1 = number(value()?)?;
+            }
+            "alpha" => settings.sprt.alpha = number(value()?)?,
+            "beta" => settings.sprt.beta = number(value()?)?,
+            "pgnout" => pgn_out = Some(value()?.clone()),
+            _ => return Err(format!("unknown option {option}; {usage}")),
+        }
+    }
+
+    let mut first = first_spec.start(&args[0])?;
+    let mut second = second_spec.start(&args[1])?;
+    if let Some(path) = &pgn_out {
+        fs::write(path, "").map_err(|e| format!("{path}: {e}"))?;
+    }
+    let (lower, upper) = settings.sprt.bounds();
+    let mut write_error = None;
+    let score = run_match(
+        first.as_mut(),
+        second.as_mut(),
+        &openings,
+        &settings,
+        |index, game, score| {
+            println!(
+                "Game {:>4}: {} vs {} {} ({})  +{} ={} -{}  Elo {} ± {:.1}  LLR {:.2} [{lower:.2}, {upper:.2}]",
+                index + 1,
+                game.pgn.tag("White").unwrap_or("?"),
+                game.pgn.tag("Black").unwrap_or("?"),
+                game.pgn.result,
+                game.reason,
+                score.wins,
+                score.draws,
+                score.losses,
+                format_elo(score.elo()),
+                score.elo_error(),
+                score.llr(settings.sprt.elo0, settings.sprt.elo1),
+            );
+            if let Some(path) = &pgn_out {
+                let written = OpenOptions::new()
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| writeln!(file, "{}", game.pgn));
+                if let Err(e) = written {
+                    write_error.get_or_insert_with(|| format!("{path}: {e}"));
+                }
+            }
+        },
+    )?;
+    if let Some(error) = write_error {
+        return Err(error);
+    }
+
+    println!(
+        "{} vs {}: +{} ={} -{} in {} games, score {:.1}%, Elo {} ± {:.1}",
+        args[0],
+        args[1],
+        score.wins,
+        score.draws,
+        score.losses,
+        score.games(),
+        100.0 * score.score(),
+        format_elo(score.elo()),
+        score.elo_error()
+    );
+    let sprt = settings.sprt;
+    println!(
+        "SPRT elo0 {} elo1 {} (alpha {}, beta {}): LLR {:.2} [{lower:.2}, {upper:.2}], {}",
+        sprt.elo0,
+        sprt.elo1,
+        sprt.alpha,
+        sprt.beta,
+        score.llr(sprt.elo0, sprt.elo1),
+        match sprt.verdict(&score) {
+            SprtVerdict::H1Accepted => "H1 accepted",
+            SprtVerdict::H0Accepted => "H0 accepted",
+            SprtVerdict::Continue => "inconclusive",
+        }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Adjudication, EngineSpec, MatchScore, MatchSettings, Sprt, SprtVerdict, parse_openings,
+        play_game, run_match,
+    };
+    use crate::chess_engine::{board::Board, pgn::GameResult, utils::init_tables};
+    use crate::epd::Budget;
+
+    fn internal() -> Box<dyn super::Player> {
+        EngineSpec::parse("self,hash=1")
+            .unwrap()
+            .start("self")
+            .unwrap()
+    }
+
+    #[test]
+    fn elo_error_bars_and_sprt() {
+        let even = MatchScore {
+            wins: 30,
+            draws: 40,
+            losses: 30,
+        };
+        assert!(even.elo().abs() < 1e-9);
+        let ahead = MatchScore {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        assert!((ahead.score() - 0.7).abs() < 1e-9);
+        assert!((ahead.elo() - 147.19).abs() < 0.01);
+        // more games at the same score narrow the error bar
+        let more = MatchScore {
+            wins: 600,
+            draws: 200,
+            losses: 200,
+        };
+        assert!(more.elo_error() < ahead.elo_error() / 3.0);
+        assert!(ahead.elo_error() > 0.0);
+
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 0.001 && (lower + 2.944).abs() < 0.001);
+        assert_eq!(sprt.verdict(&MatchScore::default()), SprtVerdict::Continue);
+        assert_eq!(sprt.verdict(&more), SprtVerdict::H1Accepted);
+        let level = MatchScore {
+            wins: 6000,
+            draws: 8000,
+            losses: 6000,
+        };
+        assert_eq!(sprt.verdict(&level), SprtVerdict::H0Accepted);
+        assert_eq!(sprt.verdict(&even), SprtVerdict::Continue);
+
+        // a clean sweep has finite error bars and is decided before long
+        let sweep = |wins, losses| MatchScore {
+            wins,
+            draws: 0,
+            losses,
+        };
+        assert!(sweep(10, 0).elo_error().is_finite());
+        assert!(sweep(0, 10).elo_error().is_finite());
+        assert_eq!(sprt.verdict(&sweep(5, 0)), SprtVerdict::Continue);
+        assert_eq!(sprt.verdict(&sweep(40, 0)), SprtVerdict::H1Accepted);
+        assert_eq!(sprt.verdict(&sweep(0, 40)), SprtVerdict::H0Accepted);
+        let drawn = MatchScore {
+            wins: 0,
+            draws: 10,
+            losses: 0,
+        };
+        assert!(drawn.llr(0.0, 5.0).is_finite() && drawn.elo_error().is_finite());
+    }
+
+    #[test]
+    fn parses_engines_and_openings() {
+        init_tables();
+        assert_eq!(
+            EngineSpec::parse("self,threads=2,evalfile=net.bin,weights=tuned.txt").unwrap(),
+            EngineSpec::Internal {
+                hash_mb: 64,
+                threads: 2,
+                eval_file: Some("net.bin".to_string()),
+                weights_file: Some("tuned.txt".to_string()),
+            }
+        );
+        assert!(EngineSpec::parse("stockfish").is_err());
+        assert!(EngineSpec::parse("uci:").is_err());
+        assert!(EngineSpec::parse("self,ponder=on").is_err());
+
+        let text = "\
+# a comment
+startpos
+rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1
+4k3/8/8/8/8/8/8/4K2R w K - id \"rook up\";
+";
+        let openings = parse_openings(text).unwrap();
+        assert_eq!(openings.len(), 3);
+        assert_eq!(openings[2].to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        let error = parse_openings("startpos\n8/8 w").err().unwrap();
+        assert!(error.starts_with("line 2:"), "{error}");
+    }
+
+    #[test]
+    fn games_end_by_rules_and_adjudication() {
+        init_tables();
+        let (mut white, mut black) = (internal(), internal());
+        let mut play = |fen: &str, adjudication: &Adjudication| {
+            let opening = Board::from_fen(fen).unwrap();
+            play_game(
+                white.as_mut(),
+                black.as_mut(),
+                &opening,
+                Budget::Depth(3),
+                adjudication,
+            )
+            .unwrap()
+        };
+        let rules_only = Adjudication {
+            resign_score: None,
+            draw_score: None,
+            max_plies: 200,
+        };
+
+        let mate = play("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1", &rules_only);
+        assert_eq!(
+            (mate.pgn.result, mate.reason),
+            (GameResult::WhiteWins, "checkmate")
+        );
+        assert_eq!(mate.pgn.moves.len(), 1);
+        assert_eq!(mate.pgn.tag("Termination"), Some("checkmate"));
+
+        let bare = play("4k3/8/8/8/8/8/8/4K3 w - - 0 1", &rules_only);
+        assert_eq!(
+            (bare.pgn.result, bare.reason),
+            (GameResult::Draw, "insufficient material")
+        );
+
+        // a queen up: both engines agree for three moves each
+        let resign = play(
+            "4k3/8/8/8/8/8/8/3QK3 b - - 0 1",
+            &Adjudication {
+                resign_score: Some(500),
+                ..rules_only
+            },
+        );
+        assert_eq!(
+            (resign.pgn.result, resign.reason),
+            (GameResult::WhiteWins, "adjudicated win")
+        );
+        assert_eq!(resign.pgn.moves.len(), 6);
+        assert!(
+            resign.pgn.moves[0]
+                .eval
+                .is_some_and(|eval| eval.score < -500)
+        );
+
+        let limit = play(
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1",
+            &Adjudication {
+                max_plies: 3,
+                ..rules_only
+            },
+        );
+        assert_eq!(
+            (limit.pgn.result, limit.reason),
+            (GameResult::Draw, "move limit")
+        );
+        assert_eq!(limit.pgn.moves.len(), 3);
+    }
+
+    #[test]
+    fn match_alternates_colours_and_stops_on_a_verdict() {
+        init_tables();
+        let (mut first, mut second) = (internal(), internal());
+        let openings = parse_openings("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1").unwrap();
+        let mut settings = MatchSettings {
+            games: 4,
+            budget: Budget::Depth(2),
+            adjudication: Adjudication::default(),
+            sprt: Sprt::default(),
+        };
+        let mut rounds = Vec::new();
+        let score = run_match(
+            first.as_mut(),
+            second.as_mut(),
+            &openings,
+            &settings,
+            |_, game, _| rounds.push(game.pgn.tag("Round").unwrap().to_string()),
+        )
+        .unwrap();
+        // White mates at once, so the engines trade wins
+        assert_eq!(rounds, ["1", "2", "3", "4"]);
+        assert_eq!((score.wins, score.draws, score.losses), (2, 0, 2));
+
+        // a single win already makes "800 Elo weaker" unlikely enough for a
+        // loose test, which ends the match
+        settings.sprt = Sprt {
+            elo0: -800.0,
+            elo1: 0.0,
+            alpha: 0.4,
+            beta: 0.4,
+        };
+        let score = run_match(
+            first.as_mut(),
+            second.as_mut(),
+            &openings,
+            &settings,
+            |_, _, _| {},
+        )
+        .unwrap();
+        assert_eq!((score.wins, score.games()), (1, 1));
+    }
+}
@@ -0,0 +1,328 @@
+//! [Texel tuning][texel]: fitting the classical evaluation's weights to game
+//! results.
+//!
+//! The dataset is a list of positions, each labelled with the result of the
+//! game it was taken from (1 for a White win, ½ for a draw, 0 for a loss).
+//! A centipawn score `s` predicts a result through the sigmoid
+//! `1 / (1 + 10^(-k·s/400))`, and the tuner looks for the [`EvalWeights`]
+//! that minimise the mean squared difference between prediction and label.
+//! [`find_k`] first fits `k` to the starting weights so the sigmoid matches
+//! the engine's scale; [`tune`] then runs the classic Texel local search,
+//! nudging each weight by one centipawn up or down and keeping any change that
+//! lowers the error, pass after pass until none does.
+//!
+//! Positions are scored with the static evaluation, so the dataset should hold
+//! *quiet* positions (no captures pending), such as `quiet-labeled.epd`. Each
+//! line is a FEN followed by the result, in any of the common spellings:
+//!
+//! ```text
+//! rnbqkb1r/pp3ppp/4pn2/2pp4/3P4/2P1PN2/PP3PPP/RNBQKB1R w KQkq - 0 5 [0.5]
+//! 8/8/4k3/8/8/3QK3/8/8 w - - c9 "1-0";
+//! 8/8/4k3/8/8/3QK3/8/8 w - - 0 1; 1/2-1/2
+//! 8/8/4k3/8/8/3QK3/8/8 w - - 0 1 | 870 | 1.0
+//! ```
+//!
+//! The last form, with a search score in the middle, is what the
+//! [`datagen`](crate::datagen) subcommand writes.
+//!
+//! [`tune_command`] wraps this as the binary's `tune` subcommand, which writes
+//! the tuned weights in the [`EvalWeights::save`] format:
+//!
+//! ```text
+//! sabertooth tune quiet-labeled.epd passes 10 out tuned.txt
+//! ```
+//!
+//! [texel]: https://www.chessprogramming.org/Texel%27s_Tuning_Method
+
+use std::{path::Path, time::Instant};
+
+use crate::chess_engine::{
+    board::{Board, WHITE},
+    engine::evaluation::{EvalWeights, evaluate_with},
+};
+
+/// Passes the `tune` subcommand runs when not told otherwise.
+const DEFAULT_PASSES: usize = 10;
+
+/// A position and the result of the game it came from.
+pub struct LabelledPosition {
+    /// The position.
+    pub board: Board,
+    /// The game result from White's point of view: 1, 0.5 or 0.
+    pub result: f64,
+}
+
+impl LabelledPosition {
+    /// Parses one dataset line: a FEN (the move counters may be left out)
+    /// followed by the result as `1-0`/`0-1`/`1/2-1/2` or a number, optionally
+    /// quoted, bracketed, after a `c9` opcode, after a `;`, or as the last of
+    /// `|`-separated fields.
+    ///
+    /// # Errors
+    /// Returns an error if the line has no recognisable result or FEN.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim().trim_end_matches(';');
+        let (fen, label) = if let Some((fen, rest)) = line.split_once('|') {
+            // `FEN | score | result`: the score is not needed
+            let (_, label) = rest.rsplit_once('|').unwrap_or(("", rest));
+            (fen, label.trim())
+        } else {
+            line.rsplit_once(char::is_whitespace)
+                .ok_or_else(|| format!("No result in: {line}"))?
+        };
+        let label = label.trim_matches(|c| matches!(c, '"' | '[' | ']' | ';'));
+        let result = match label {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            "1/2-1/2" => 0.5,
+            number => number
+                .parse::<f64>()
+                .ok()
+                .filter(|result| (0.0..=1.0).contains(result))
+                .ok_or_else(|| format!("Invalid result: {label}"))?,
+        };
+
+        let fen = fen.trim().trim_end_matches(';').trim();
+        let fen = fen.strip_suffix("c9").unwrap_or(fen).trim();
+        let board = Board::from_fen_lenient(fen)?;
+        Ok(Self { board, result })
+    }
+}
+
+/// Parses a dataset, one labelled position per line; blank lines and `#`
+/// comments are skipped.
+///
+/// # Errors
+/// Returns the first malformed line's error, prefixed with its line number.
+pub fn load_dataset(text: &str) -> Result<Vec<LabelledPosition>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            LabelledPosition::parse(line).map_err(|e| format!("line {}: {e}", index + 1))
+        })
+        .collect()
+}
+
+/// The result a centipawn score (from White's side) predicts.
+fn sigmoid(score: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * f64::from(score) / 400.0))
+}
+
+/// Mean squared error of `weights`' predictions over `positions`, computed on
+/// every available core.
+///
+/// # Panics
+/// Panics if evaluating a position panics.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn mean_error(positions: &[LabelledPosition], weights: &EvalWeights, k: f64) -> f64 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+    let threads = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+    let chunk_size = positions.len().div_ceil(threads);
+    let total: f64 = std::thread::scope(|scope| {
+        // spawn every worker before joining any
+        #[allow(clippy::needless_collect)]
+        let workers: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|position| {
+                            let score = evaluate_with(&position.board, weights);
+                            let white_score = if position.board.turn == WHITE {
+                                score
+                            } else {
+                                -score
+                            };
+                            (position.result - sigmoid(white_score, k)).powi(2)
+                        })
+                        .sum::<f64>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("error worker panicked"))
+            .sum()
+    });
+    total / positions.len() as f64
+}
+
+/// The sigmoid scale `k` that best fits `weights` to the dataset, found by
+/// scanning ever finer steps around the best value so far.
+#[must_use]
+pub fn find_k(positions: &[LabelledPosition], weights: &EvalWeights) -> f64 {
+    let mut best_k = 1.0;
+    let mut best_error = mean_error(positions, weights, best_k);
+    for step in [0.1_f64, 0.01, 0.001] {
+        let center = best_k;
+        for i in -10..=10 {
+            let k = step.mul_add(f64::from(i), center);
+            if k <= 0.0 {
+                continue;
+            }
+            let error = mean_error(positions, weights, k);
+            if error < best_error {
+                best_k = k;
+                best_error = error;
+            }
+        }
+    }
+    best_k
+}
+
+/// Texel local search from `start`, returning the tuned weights.
+///
+/// Each pass tries every weight one centipawn up, then down, keeping whichever
+/// move lowers the error. Stops after `max_passes` passes or a pass that
+/// changes nothing; `on_pass` hears each pass's number and resulting error.
+#[must_use]
+pub fn tune(
+    positions: &[LabelledPosition],
+    start: &EvalWeights,
+    k: f64,
+    max_passes: usize,
+    mut on_pass: impl FnMut(usize, f64),
+) -> EvalWeights {
+    let mut weights = start.clone();
+    let mut params = weights.to_vec();
+    let mut best_error = mean_error(positions, &weights, k);
+    for pass in 1..=max_passes {
+        let mut improved = false;
+        for index in 0..params.len() {
+            for delta in [1, -1] {
+                params[index] += delta;
+                weights.set_from_slice(&params);
+                let error = mean_error(positions, &weights, k);
+                if error < best_error {
+                    best_error = error;
+                    improved = true;
+                    break;
+                }
+                params[index] -= delta;
+            }
+        }
+        weights.set_from_slice(&params);
+        on_pass(pass, best_error);
+        if !improved {
+            break;
+        }
+    }
+    weights
+}
+
+/// The `tune` subcommand: fits the evaluation weights to a dataset.
+///
+/// Usage: `tune <dataset> [passes <n>] [k <k>] [weights <file>] [out
+/// <file>]`. Starts from the engine's weights (or a saved
+/// weights file), fits `k` unless given, runs the local search and writes the
+/// result (to `tuned_weights.txt` by default).
+///
+/// # Errors
+///
+/// Returns `Err` if the arguments are malformed, or a file cannot be read,
+/// parsed or written.
+pub fn tune_command(args: &[String]) -> Result<(), String> {
+    let usage = "usage: tune <dataset> [passes <n>] [k <k>] [weights <file>] [out <file>]";
+    let Some(path) = args.first() else {
+        return Err(usage.to_string());
+    };
+
+    let mut passes = DEFAULT_PASSES;
+    let mut k = None;
+    let mut start = EvalWeights::DEFAULT;
+    let mut out = "tuned_weights.txt".to_string();
+    let mut iter = args[1..].iter();
+    while let Some(option) = iter.next() {
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("{option} needs a value; {usage}"))?;
+        match option.as_str() {
+            "passes" => passes = value.parse().map_err(|e| format!("passes: {e}"))?,
+            "k" => k = Some(value.parse().map_err(|e| format!("k: {e}"))?),
+            "weights" => {
+                start = EvalWeights::open(Path::new(value))?;
+            }
+            "out" => out.clone_from(value),
+            _ => return Err(format!("unknown option {option}; {usage}")),
+        }
+    }
+
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let positions = load_dataset(&text).map_err(|e| format!("{path}: {e}"))?;
+    let begin = Instant::now();
+    let k = k.unwrap_or_else(|| find_k(&positions, &start));
+    println!(
+        "{} positions, k = {k:.3}, error {:.6}",
+        positions.len(),
+        mean_error(&positions, &start, k)
+    );
+    let tuned = tune(&positions, &start, k, passes, |pass, error| {
+        println!(
+            "pass {pass:>3} error {error:.6} {:>8.1}s",
+            begin.elapsed().as_secs_f64()
+        );
+    });
+    std::fs::write(&out, tuned.save()).map_err(|e| format!("{out}: {e}"))?;
+    println!("Wrote {out}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LabelledPosition, find_k, load_dataset, mean_error, tune};
+    use crate::chess_engine::{board::BLACK, engine::evaluation::EvalWeights, utils::init_tables};
+
+    #[test]
+    fn parses_the_common_dataset_formats() {
+        let text = "\
+# comment
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 [0.5]
+8/8/4k3/8/8/3QK3/8/8 b - - c9 \"1-0\";
+
+8/8/4k3/8/8/3QK3/8/8 w - - 0 1; 0-1
+8/8/4k3/8/8/3QK3/8/8 w - - 0 1 | -35 | 0.5
+";
+        let positions = load_dataset(text).unwrap();
+        let results: Vec<f64> = positions.iter().map(|p| p.result).collect();
+        assert_eq!(results, [0.5, 1.0, 0.0, 0.5]);
+        assert_eq!(positions[1].board.turn, BLACK);
+
+        assert!(LabelledPosition::parse("8/8/4k3/8/8/3QK3/8/8 w - - 0 1 2-0").is_err());
+        let error = load_dataset("\n8/8/4k3/8/8/3QK3/8/8 w - - 0 1 draw\n")
+            .err()
+            .unwrap();
+        assert!(error.starts_with("line 2:"), "{error}");
+    }
+
+    #[test]
+    fn tuning_lowers_the_error() {
+        init_tables();
+        // queen-up positions that were only drawn: the tuner should learn that
+        // material here is worth less than the hand-set weights say
+        let text = "\
+8/8/4k3/8/8/3QK3/8/8 w - - 0 1 [0.5]
+8/8/3k4/8/2Q5/4K3/8/8 b - - 0 1 [0.5]
+8/3q4/4k3/8/8/4K3/8/8 w - - 0 1 [0.5]
+8/8/4k3/8/8/4K3/8/8 w - - 0 1 [0.5]
+";
+        let positions = load_dataset(text).unwrap();
+        let start = EvalWeights::DEFAULT;
+        assert!(find_k(&positions, &start) > 0.0);
+        let k = 1.0;
+        let before = mean_error(&positions, &start, k);
+        let mut passes = 0;
+        let tuned = tune(&positions, &start, k, 2, |_, _| passes += 1);
+        let after = mean_error(&positions, &tuned, k);
+        assert!(after < before, "{after} >= {before}");
+        assert!(passes >= 1);
+        assert!(
+            tuned.material_mg[4] + tuned.material_eg[4]
+                < start.material_mg[4] + start.material_eg[4]
+        );
+    }
+}
@@ -1,16 +1,27 @@
 //! The [Universal Chess Interface][uci] (UCI) protocol loop.
 //!
-//! [`uci_protocol`] reads commands from stdin and replies on stdout, holding the
-//! current [`Board`] between commands. Supported commands: `uci`, `isready`,
-//! `ucinewgame`, `position` (`startpos`/`fen`, with optional `moves`), `go`
-//! (`depth`, `movetime`, `wtime`/`btime`/`winc`/`binc`, `infinite`, or
-//! `perft N`), `stop`, `d` (print the board), and `quit`.
+//! [`uci_protocol`] reads commands from stdin and replies on stdout, holding
+//! the current [`Board`] between commands. Supported commands: `uci`,
+//! `isready`, `ucinewgame`, `position` (`startpos`/`fen`, with optional
+//! `moves`), `go` (`depth`, `nodes`, `mate`, `movetime`,
+//! `wtime`/`btime`/`winc`/`binc`/`movestogo`, `searchmoves`, `infinite`,
+//! `ponder`, or `perft N`), `ponderhit`, `stop`, `d` (print the board, its FEN
+//! and its [`GameStatus`]), and `quit`.
 //!
 //! Searches run on a background thread so `stop` can interrupt them; the search
-//! result is reported as a `bestmove` line when it finishes. This module also
-//! owns time allocation: it converts the clock into a per-move budget of
-//! roughly `clock/25 + inc/2`, capped at half the clock, less a small
-//! `MOVE_OVERHEAD_MS` safety margin.
+//! result is reported as a `bestmove` line (with a `ponder` move when the PV
+//! has one) when it finishes. A `go ponder` search thinks on the opponent's
+//! time without a deadline and holds back its `bestmove` until `ponderhit`,
+//! which starts the clock budget that came with the `go ponder`, or `stop`.
+//! With `OwnBook` on, a `go` (other than `go ponder`, `go infinite` or one
+//! with `searchmoves`) answers at once with a move from the `BookFile` book
+//! when it has one for the position.
+//! A `go` from a position where the game is over (or a draw can be claimed)
+//! first says so in an `info string`, since the `bestmove` of a mated or
+//! stalemated side is the null move `0000`.
+//! Clock parameters (including `movestogo`) are handed to the
+//! [`time_manager`](crate::chess_engine::engine::time_manager), which turns them
+//! into the soft and hard limits the search obeys.
 //!
 //! [uci]: https://www.chessprogramming.org/UCI
 
@@ -23,8 +34,14 @@ use std::time::{Duration, Instant};
 use crate::{
     chess_engine::{
         board::{Board, WHITE},
+        engine::evaluation::EvalWeights,
+        engine::nnue::Network,
         engine::search::{SearchLimits, search_position},
-        engine::transposition::TranspositionTable,
+        engine::time_manager::{PonderClock, TimeBudget},
+        engine::transposition::{DEFAULT_HASH_MB, MAX_HASH_MB, MIN_HASH_MB, TranspositionTable},
+        game_state::GameStatus,
+        moves::Move,
+        polyglot::{PolyglotBook, book_key},
     },
     perft::perft_divide,
 };
@@ -34,13 +51,30 @@ const ENGINE_NAME: &str = "SaberTooth";
 /// Engine author reported in the `uci` handshake.
 const ENGINE_AUTHOR: &str = "Samuel Burger";
 
-/// Safety margin subtracted from the clock so the engine never flags
-/// because of I/O latency.
-const MOVE_OVERHEAD_MS: u64 = 30;
+/// The parameters of `go`; a `searchmoves` list runs until one of them.
+const GO_KEYWORDS: [&str; 13] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+    "perft",
+];
 
 /// Upper bound advertised (and enforced) for the `Threads` option.
 const MAX_THREADS: usize = 256;
 
+/// Upper bound advertised (and enforced) for the `MultiPV` option; no position
+/// has more legal moves.
+const MAX_MULTI_PV: usize = 256;
+
 /// The default thread count: the number of logical cores, or 1 if that cannot be
 /// determined.
 fn default_threads() -> usize {
@@ -48,26 +82,66 @@ fn default_threads() -> usize {
 }
 
 /// The mutable state the protocol loop carries between commands: the current
-/// position, the shared stop flag, and the handle of any running search thread.
+/// position, the running search's stop and ponder flags, and the handle of its
+/// thread.
 struct EngineState {
     board: Board,
+    /// Stop flag of the current search; each search gets a fresh one.
     stop: Arc<AtomicBool>,
+    /// Set while a `go ponder` search waits for `ponderhit`; the search thread
+    /// holds back its `bestmove` until it clears.
+    pondering: Arc<AtomicBool>,
+    /// The clock of a `go ponder` search with a time budget, started on
+    /// `ponderhit`.
+    ponder_clock: Option<Arc<PonderClock>>,
     search_thread: Option<JoinHandle<()>>,
-    /// Shared, game-long transposition table; cleared on `ucinewgame`.
+    /// Shared, game-long transposition table; cleared on `ucinewgame` and
+    /// resized via `setoption name Hash`.
     tt: Arc<TranspositionTable>,
     /// Number of search threads (Lazy SMP); set via `setoption name Threads`.
     threads: usize,
+    /// Number of best root moves to report; set via `setoption name MultiPV`.
+    multi_pv: usize,
+    /// Whether the GUI plays Chess960; set via `setoption name UCI_Chess960`.
+    chess960: bool,
+    /// The network loaded from `EvalFile`, if any.
+    network: Option<Arc<Network>>,
+    /// Whether to evaluate with `network`; set via `setoption name UseNNUE`.
+    use_nnue: bool,
+    /// Classical evaluation weights loaded from `WeightsFile`, if any.
+    weights: Option<Arc<EvalWeights>>,
+    /// The opening book loaded from `BookFile`, if any.
+    book: Option<PolyglotBook>,
+    /// Whether `go` plays from `book`; set via `setoption name OwnBook`.
+    own_book: bool,
 }
 
 impl EngineState {
-    /// Signals any running search to stop, joins its thread, and resets the stop
-    /// flag for the next search.
+    /// Signals any running search to stop (ending a ponder search too) and
+    /// joins its thread.
     fn stop_search(&mut self) {
         self.stop.store(true, Ordering::Relaxed);
+        self.pondering.store(false, Ordering::Release);
         if let Some(handle) = self.search_thread.take() {
+            handle.thread().unpark();
             let _ = handle.join();
         }
-        self.stop.store(false, Ordering::Relaxed);
+    }
+
+    /// Handles `ponderhit`: the opponent played the expected move, so the
+    /// pondering search carries on as a normal search under the budget that came
+    /// with its `go ponder`, timed from now.
+    fn ponder_hit(&mut self) {
+        if !self.pondering.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        if let Some(clock) = self.ponder_clock.take() {
+            clock.ponder_hit();
+        }
+        // the search may already be done and waiting to report
+        if let Some(handle) = &self.search_thread {
+            handle.thread().unpark();
+        }
     }
 }
 
@@ -81,9 +155,18 @@ pub fn uci_protocol() -> Result<(), Box<dyn std::error::Error>> {
     let mut state = EngineState {
         board: Board::new_start_pos()?,
         stop: Arc::new(AtomicBool::new(false)),
+        pondering: Arc::new(AtomicBool::new(false)),
+        ponder_clock: None,
         search_thread: None,
         tt: Arc::new(TranspositionTable::new()),
         threads: default_threads(),
+        multi_pv: 1,
+        chess960: false,
+        network: None,
+        use_nnue: false,
+        weights: None,
+        book: None,
+        own_book: false,
     };
 
     let stdin = std::io::stdin();
@@ -102,17 +185,23 @@ pub fn uci_protocol() -> Result<(), Box<dyn std::error::Error>> {
                 state.stop_search();
                 state.tt.clear();
                 state.board = Board::new_start_pos()?;
+                state.board.chess960 = state.chess960;
             }
             "position" => {
                 state.stop_search();
-                match parse_position(&parts) {
+                match parse_position(&parts, state.chess960) {
                     Ok(board) => state.board = board,
                     Err(err) => println!("info string error: {err}"),
                 }
             }
             "go" => handle_go(&parts, &mut state),
+            "ponderhit" => state.ponder_hit(),
             "stop" => state.stop_search(),
-            "d" => state.board.print_board(),
+            "d" => {
+                state.board.print_board();
+                println!("Fen: {}", state.board.to_fen());
+                println!("Status: {}", state.board.outcome());
+            }
             "quit" => break,
             // per the UCI spec unknown tokens are ignored
             _ => (),
@@ -132,25 +221,131 @@ fn print_identity() {
         "option name Threads type spin default {} min 1 max {MAX_THREADS}",
         default_threads()
     );
+    println!(
+        "option name Hash type spin default {DEFAULT_HASH_MB} min {MIN_HASH_MB} max {MAX_HASH_MB}"
+    );
+    println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}");
+    println!("option name Ponder type check default false");
+    println!("option name UCI_Chess960 type check default false");
+    println!("option name EvalFile type string default <empty>");
+    println!("option name UseNNUE type check default false");
+    println!("option name WeightsFile type string default <empty>");
+    println!("option name OwnBook type check default false");
+    println!("option name BookFile type string default <empty>");
     println!("uciok");
 }
 
-/// Handles `setoption name <Name> value <X>`. Only `Threads` is supported; the
-/// value is clamped to `1..=MAX_THREADS`. Unknown options are ignored, per spec.
+/// Handles `setoption name <Name> value <X>` for `Threads` (clamped to
+/// `1..=MAX_THREADS`), `Hash` (the table size in MiB; any running search is
+/// stopped first), `MultiPV` (clamped to `1..=MAX_MULTI_PV`), `UCI_Chess960`
+/// (castles are then read and written king-takes-rook), `EvalFile` (an NNUE
+/// network to load), `UseNNUE` (evaluate with that network rather than the
+/// classical evaluation), `WeightsFile` (tuned weights for the classical
+/// evaluation), `BookFile` (an opening book to load) and `OwnBook` (play from
+/// that book while it has a move). `Ponder` is accepted but needs no state:
+/// the GUI decides when to send `go ponder`. Unknown options are ignored, per
+/// spec.
 fn handle_setoption(parts: &[&str], state: &mut EngineState) {
-    if let Some(name_idx) = parts.iter().position(|&p| p == "name")
-        && let Some(value_idx) = parts.iter().position(|&p| p == "value")
-        && parts.get(name_idx + 1) == Some(&"Threads")
-        && let Some(value) = parts
-            .get(value_idx + 1)
-            .and_then(|v| v.parse::<usize>().ok())
-    {
-        state.threads = value.clamp(1, MAX_THREADS);
+    let Some(name_idx) = parts.iter().position(|&p| p == "name") else {
+        return;
+    };
+    let Some(value) = parts
+        .iter()
+        .position(|&p| p == "value")
+        .and_then(|value_idx| parts.get(value_idx + 1))
+    else {
+        return;
+    };
+    let count = value.parse::<usize>().ok();
+    match parts.get(name_idx + 1) {
+        Some(&"Threads") => {
+            if let Some(threads) = count {
+                state.threads = threads.clamp(1, MAX_THREADS);
+            }
+        }
+        Some(&"Hash") => {
+            if let Some(mb) = count {
+                state.stop_search();
+                // the joined search thread has dropped its handle on the table
+                match Arc::get_mut(&mut state.tt) {
+                    Some(tt) => tt.resize(mb),
+                    None => state.tt = Arc::new(TranspositionTable::with_size_mb(mb)),
+                }
+            }
+        }
+        Some(&"MultiPV") => {
+            if let Some(multi_pv) = count {
+                state.multi_pv = multi_pv.clamp(1, MAX_MULTI_PV);
+            }
+        }
+        Some(&"EvalFile") => {
+            let value_idx = parts.iter().position(|&p| p == "value").unwrap_or(0);
+            let path = parts[value_idx + 1..].join(" ");
+            state.network = None;
+            if path != "<empty>" {
+                match Network::load(std::path::Path::new(&path)) {
+                    Ok(network) => {
+                        println!(
+                            "info string loaded NNUE {path} ({} hidden neurons)",
+                            network.hidden_size()
+                        );
+                        state.network = Some(Arc::new(network));
+                    }
+                    Err(error) => println!("info string error: {error}"),
+                }
+            }
+        }
+        Some(&"UseNNUE") => {
+            if let Ok(use_nnue) = value.parse::<bool>() {
+                state.use_nnue = use_nnue;
+            }
+        }
+        Some(&"WeightsFile") => {
+            let value_idx = parts.iter().position(|&p| p == "value").unwrap_or(0);
+            let path = parts[value_idx + 1..].join(" ");
+            state.weights = None;
+            if path != "<empty>" {
+                match EvalWeights::open(std::path::Path::new(&path)) {
+                    Ok(weights) => {
+                        println!("info string loaded weights {path}");
+                        state.weights = Some(Arc::new(weights));
+                    }
+                    Err(error) => println!("info string error: {error}"),
+                }
+            }
+        }
+        Some(&"BookFile") => {
+            let value_idx = parts.iter().position(|&p| p == "value").unwrap_or(0);
+            let path = parts[value_idx + 1..].join(" ");
+            state.book = None;
+            if path != "<empty>" {
+                match PolyglotBook::open(std::path::Path::new(&path)) {
+                    Ok(book) => {
+                        println!("info string loaded book {path} ({} entries)", book.len());
+                        state.book = Some(book);
+                    }
+                    Err(error) => println!("info string error: {error}"),
+                }
+            }
+        }
+        Some(&"OwnBook") => {
+            if let Ok(own_book) = value.parse::<bool>() {
+                state.own_book = own_book;
+            }
+        }
+        Some(&"UCI_Chess960") => {
+            if let Ok(chess960) = value.parse::<bool>() {
+                state.chess960 = chess960;
+                state.board.chess960 = chess960;
+            }
+        }
+        _ => (),
     }
 }
 
 /// Handles a `go` command: runs `perft` synchronously, or otherwise spawns a
-/// background search thread that prints the chosen `bestmove` when it finishes.
+/// background search thread that prints the chosen `bestmove` when it finishes
+/// (for `go ponder`, once `ponderhit` or `stop` also arrives).
 fn handle_go(parts: &[&str], state: &mut EngineState) {
     state.stop_search();
 
@@ -164,48 +359,130 @@ fn handle_go(parts: &[&str], state: &mut EngineState) {
         return;
     }
 
-    let limits = parse_go_limits(parts, &state.board);
+    if let Some(info) = status_info(state.board.outcome()) {
+        println!("info string {info}");
+    }
+
+    let (mut limits, budget) = parse_go_limits(parts, &state.board);
+    limits.multi_pv = state.multi_pv;
+    // a ponder search runs without a deadline; its budget starts on ponderhit
+    let ponder = parts.contains(&"ponder");
+    // analysis and pondering want a search, not a book move
+    if !ponder
+        && !parts.contains(&"infinite")
+        && limits.search_moves.is_empty()
+        && let Some(book_move) = book_move(state)
+    {
+        println!("info string book move");
+        println!("bestmove {book_move}");
+        return;
+    }
+    state.ponder_clock = None;
+    if ponder {
+        state.ponder_clock = budget.map(|budget| Arc::new(PonderClock::new(budget)));
+        limits.ponder.clone_from(&state.ponder_clock);
+    } else if let Some(budget) = budget {
+        limits.deadline = Some(Instant::now() + budget.hard);
+        limits.soft_time = budget.soft;
+    }
 
+    state.stop = Arc::new(AtomicBool::new(false));
+    state.pondering = Arc::new(AtomicBool::new(ponder));
     let mut board = state.board.clone();
+    // without a loaded network UseNNUE falls back to the classical evaluation
+    board.set_network(state.network.clone().filter(|_| state.use_nnue));
+    board.set_weights(state.weights.clone());
     let stop = Arc::clone(&state.stop);
+    let pondering = Arc::clone(&state.pondering);
     let tt = Arc::clone(&state.tt);
     let threads = state.threads;
     state.search_thread = Some(std::thread::spawn(move || {
-        let result = search_position(&mut board, limits, &stop, &tt, threads, true);
-        match result.best_move {
-            Some(best_move) => println!("bestmove {best_move}"),
-            None => println!("bestmove 0000"),
+        let result = search_position(&mut board, &limits, &stop, &tt, threads, true);
+        // the UCI spec forbids a bestmove while pondering, even once the search
+        // has finished; ponderhit and stop both clear the flag and unpark us
+        while pondering.load(Ordering::Acquire) {
+            std::thread::park();
+        }
+        match (result.best_move, result.pv.get(1)) {
+            (Some(best_move), Some(ponder_move)) => {
+                println!("bestmove {best_move} ponder {ponder_move}");
+            }
+            (Some(best_move), None) => println!("bestmove {best_move}"),
+            (None, _) => println!("bestmove 0000"),
         }
     }));
 }
 
-/// Turns the tokens of a `go` command into [`SearchLimits`], applying the time
-/// allocation described in the [module docs](self). A bare `go` (no depth and no
-/// clocks) falls back to a three-second budget so the engine stays responsive.
+/// A move from the loaded book for the current position, picked in proportion
+/// to the book weights, if `OwnBook` is on and the book has one.
+fn book_move(state: &mut EngineState) -> Option<Move> {
+    let book = state.book.as_ref().filter(|_| state.own_book)?;
+    let key = book_key(&mut state.board);
+    book.weighted_move(&mut state.board, key, &mut rand::rng())
+}
+
+/// What a `go` reports about a finished game, or `None` while it goes on.
+fn status_info(status: GameStatus) -> Option<String> {
+    if status.is_claim() {
+        Some(format!("draw claimable: {status}"))
+    } else if status.is_over() {
+        Some(format!("game over: {status}"))
+    } else {
+        None
+    }
+}
+
+/// Turns the tokens of a `go` command into [`SearchLimits`] (without time
+/// limits) and the thinking-time budget, allocated by [`TimeBudget`]. `nodes`
+/// and `mate` combine with any other limit, and `searchmoves` restricts the
+/// root to the listed moves (up to the next `go` keyword). `infinite` drops
+/// the time budget; a bare `go` (no depth, nodes, mate, clocks or `infinite`)
+/// falls back to a three-second budget so the engine stays responsive.
 #[allow(clippy::cast_possible_truncation)]
-fn parse_go_limits(parts: &[&str], board: &Board) -> SearchLimits {
+fn parse_go_limits(parts: &[&str], board: &Board) -> (SearchLimits, Option<TimeBudget>) {
+    let mut infinite = false;
     let mut depth: Option<u8> = None;
+    let mut nodes: Option<u64> = None;
+    let mut mate: Option<u8> = None;
+    let mut search_moves = Vec::new();
     let mut movetime: Option<u64> = None;
     let mut wtime: Option<u64> = None;
     let mut btime: Option<u64> = None;
     let mut winc: Option<u64> = None;
     let mut binc: Option<u64> = None;
+    let mut movestogo: Option<u64> = None;
 
-    let mut iter = parts.iter().skip(1);
+    let mut iter = parts.iter().skip(1).peekable();
     while let Some(&token) = iter.next() {
         match token {
-            "infinite" => return SearchLimits::infinite(),
-            "depth" | "movetime" | "wtime" | "btime" | "winc" | "binc" | "movestogo" => {
+            "infinite" => infinite = true,
+            "searchmoves" => {
+                // move generation makes and unmakes moves on the board
+                let legal_moves = board.clone().generate_moves(board.turn);
+                while let Some(&&name) = iter.peek()
+                    && !GO_KEYWORDS.contains(&name)
+                {
+                    iter.next();
+                    if let Some(&mv) = legal_moves.iter().find(|mv| mv.to_string() == name) {
+                        search_moves.push(mv);
+                    }
+                }
+            }
+            "depth" | "nodes" | "mate" | "movetime" | "wtime" | "btime" | "winc" | "binc"
+            | "movestogo" => {
                 let Some(value) = iter.next().and_then(|v| v.parse::<u64>().ok()) else {
                     continue;
                 };
                 match token {
                     "depth" => depth = Some(value.min(u64::from(u8::MAX)) as u8),
+                    "nodes" => nodes = Some(value),
+                    "mate" => mate = Some(value.min(u64::from(u8::MAX)) as u8),
                     "movetime" => movetime = Some(value),
                     "wtime" => wtime = Some(value),
                     "btime" => btime = Some(value),
                     "winc" => winc = Some(value),
                     "binc" => binc = Some(value),
+                    "movestogo" => movestogo = Some(value),
                     _ => (),
                 }
             }
@@ -213,7 +490,7 @@ fn parse_go_limits(parts: &[&str], board: &Board) -> SearchLimits {
         }
     }
 
-    let budget_ms = movetime.map_or_else(
+    let budget = movetime.map_or_else(
         || {
             let (my_time, my_inc) = if board.turn == WHITE {
                 (wtime, winc.unwrap_or(0))
@@ -221,40 +498,51 @@ fn parse_go_limits(parts: &[&str], board: &Board) -> SearchLimits {
                 (btime, binc.unwrap_or(0))
             };
             my_time.map(|remaining| {
-                // simple allocation: a slice of the remaining clock plus half the
-                // increment, never more than half the clock
-                let budget = remaining / 25 + my_inc / 2;
-                budget
-                    .min(remaining.saturating_sub(MOVE_OVERHEAD_MS) / 2)
-                    .max(1)
+                TimeBudget::from_clock(
+                    Duration::from_millis(remaining),
+                    Duration::from_millis(my_inc),
+                    movestogo.map(|moves| moves.min(u64::from(u32::MAX)) as u32),
+                )
             })
         },
-        |movetime| Some(movetime.saturating_sub(MOVE_OVERHEAD_MS).max(1)),
+        |movetime| Some(TimeBudget::fixed(Duration::from_millis(movetime))),
     );
 
-    let mut limits = depth.map_or_else(SearchLimits::infinite, SearchLimits::depth);
-    // bare `go` (no depth and no clocks) would search forever; give it a
-    // small default budget so the engine stays usable interactively
-    if budget_ms.is_none() && depth.is_none() {
-        limits.deadline = Some(Instant::now() + Duration::from_secs(3));
-        return limits;
+    let mut limits = mate.map_or_else(SearchLimits::infinite, SearchLimits::mate);
+    if let Some(depth) = depth {
+        limits.depth = limits.depth.min(SearchLimits::depth(depth).depth);
     }
-    limits.deadline = budget_ms.map(|ms| Instant::now() + Duration::from_millis(ms));
-    limits
+    limits.nodes = nodes;
+    limits.search_moves = search_moves;
+    if infinite {
+        return (limits, None);
+    }
+    // bare `go` (no depth, nodes, mate or clocks) would search forever; give it
+    // a small default budget so the engine stays usable interactively
+    if budget.is_none() && depth.is_none() && nodes.is_none() && mate.is_none() {
+        let default = TimeBudget {
+            soft: None,
+            hard: Duration::from_secs(3),
+        };
+        return (limits, Some(default));
+    }
+    (limits, budget)
 }
 
 /// Parses a `position` command into a [`Board`].
 ///
 /// Accepts `position startpos` or `position fen <fields>`, each optionally
 /// followed by `moves <m1> <m2> ...` which are applied in order. A FEN missing
-/// the half-move/full-move counters is tolerated (defaults `0`/`1` are filled
-/// in).
+/// the half-move/full-move counters is tolerated (see
+/// [`Board::from_fen_lenient`]). With `chess960` set (the `UCI_Chess960`
+/// option) castles are king-takes-rook, as they also are for any FEN with a
+/// Chess960 castling setup.
 ///
 /// # Errors
 ///
 /// Returns `Err` if the command is malformed, the FEN is invalid, or one of the
 /// listed moves is illegal in the resulting position.
-pub fn parse_position(parts: &[&str]) -> Result<Board, String> {
+pub fn parse_position(parts: &[&str], chess960: bool) -> Result<Board, String> {
     if parts.len() < 2 {
         return Err("position requires startpos or fen".to_string());
     }
@@ -265,18 +553,12 @@ pub fn parse_position(parts: &[&str]) -> Result<Board, String> {
         "startpos" => Board::new_start_pos()?,
         "fen" => {
             let fen_end = moves_index.unwrap_or(parts.len());
-            let mut fen_fields: Vec<&str> = parts[2..fen_end].to_vec();
             // tolerate FENs without the halfmove/fullmove counters
-            if fen_fields.len() == 4 {
-                fen_fields.push("0");
-            }
-            if fen_fields.len() == 5 {
-                fen_fields.push("1");
-            }
-            Board::from_fen(&fen_fields.join(" "))?
+            Board::from_fen_lenient(&parts[2..fen_end].join(" "))?
         }
         other => return Err(format!("invalid position type: {other}")),
     };
+    board.chess960 |= chess960;
 
     if let Some(index) = moves_index {
         for str_move in &parts[index + 1..] {
@@ -287,3 +569,44 @@ pub fn parse_position(parts: &[&str]) -> Result<Board, String> {
     }
     Ok(board)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_go_limits;
+    use crate::chess_engine::board::Board;
+    use crate::chess_engine::engine::search::MAX_DEPTH;
+    use crate::chess_engine::utils::init_tables;
+
+    /// The limits and whether there is a time budget for `go <args>` from
+    /// the start position, with the `searchmoves` list in UCI notation.
+    fn go(args: &str) -> (u8, Option<u64>, Vec<String>, bool) {
+        init_tables();
+        let board = Board::new_start_pos().unwrap();
+        let command = format!("go {args}");
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        let (limits, budget) = parse_go_limits(&parts, &board);
+        let moves = limits
+            .search_moves
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        (limits.depth, limits.nodes, moves, budget.is_some())
+    }
+
+    #[test]
+    fn infinite_keeps_parsing_the_other_parameters() {
+        let (depth, _, moves, timed) = go("infinite searchmoves a2a3 b2b3");
+        assert_eq!(
+            (depth, moves, timed),
+            (MAX_DEPTH, vec!["a2a3".into(), "b2b3".into()], false)
+        );
+
+        let (depth, nodes, moves, timed) = go("searchmoves a2a3 infinite depth 5 nodes 1000");
+        assert_eq!((depth, nodes, timed), (5, Some(1000), false));
+        assert_eq!(moves, ["a2a3"]);
+
+        // infinite overrides the clock, and a bare go gets a default budget
+        assert!(!go("wtime 1000 btime 1000 infinite").3);
+        assert!(go("").3);
+    }
+}
@@ -0,0 +1,92 @@
+//! A short match against this crate's own binary run as a UCI subprocess, so
+//! the handshake, `position`/`go` commands and `info`/`bestmove` parsing are
+//! exercised end to end.
+
+use std::time::Duration;
+
+use sabertooth::chess_engine::utils::init_tables;
+use sabertooth::epd::Budget;
+use sabertooth::match_runner::{
+    Adjudication, EngineSpec, MatchSettings, Sprt, parse_openings, run_match,
+};
+
+#[test]
+fn plays_a_uci_subprocess() {
+    init_tables();
+    let uci = format!("uci:{},Hash=1", env!("CARGO_BIN_EXE_sabertooth"));
+    let mut first = EngineSpec::parse("self,hash=1")
+        .unwrap()
+        .start("inside")
+        .unwrap();
+    let mut second = EngineSpec::parse(&uci).unwrap().start("outside").unwrap();
+
+    // a rook up: the stronger side resigns its opponent or mates
+    let openings = parse_openings("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+    let settings = MatchSettings {
+        games: 2,
+        budget: Budget::Depth(3),
+        adjudication: Adjudication {
+            max_plies: 60,
+            ..Adjudication::default()
+        },
+        sprt: Sprt::default(),
+    };
+    let mut games = Vec::new();
+    let score = run_match(
+        first.as_mut(),
+        second.as_mut(),
+        &openings,
+        &settings,
+        |_, game, _| games.push(game.clone()),
+    )
+    .unwrap();
+
+    assert_eq!(score.games(), 2);
+    assert_eq!(games[0].pgn.tag("White"), Some("inside"));
+    assert_eq!(games[1].pgn.tag("White"), Some("outside"));
+    for game in &games {
+        // every move the subprocess made came back with its evaluation
+        assert!(game.pgn.moves.iter().all(|node| node.eval.is_some()));
+        assert_ne!(game.reason, "illegal move");
+    }
+}
+
+#[test]
+fn a_uci_subprocess_plays_chess960_on_the_clock() {
+    init_tables();
+    let uci = format!("uci:{},Hash=1", env!("CARGO_BIN_EXE_sabertooth"));
+    let mut first = EngineSpec::parse("self,hash=1")
+        .unwrap()
+        .start("inside")
+        .unwrap();
+    let mut second = EngineSpec::parse(&uci).unwrap().start("outside").unwrap();
+
+    // a Chess960 start with castling rights on both wings, on a short clock
+    let openings =
+        parse_openings("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")
+            .unwrap();
+    assert!(openings[0].chess960);
+    let settings = MatchSettings {
+        games: 2,
+        budget: Budget::Time(Duration::from_millis(20)),
+        adjudication: Adjudication {
+            max_plies: 16,
+            ..Adjudication::default()
+        },
+        sprt: Sprt::default(),
+    };
+    let mut games = Vec::new();
+    run_match(
+        first.as_mut(),
+        second.as_mut(),
+        &openings,
+        &settings,
+        |_, game, _| games.push(game.clone()),
+    )
+    .unwrap();
+
+    assert_eq!(games.len(), 2);
+    for game in &games {
+        assert!(!["illegal move", "time forfeit"].contains(&game.reason));
+    }
+}
@@ -4,7 +4,7 @@
 //! [Chess Programming Wiki](https://www.chessprogramming.org/Perft_Results).
 
 pub use sabertooth::{
-    chess_engine::{board::Board, position::Position},
+    chess_engine::{board::Board, moves::Move, position::Position},
     perft::perft,
 };
 
@@ -64,14 +64,205 @@ fn run_perft_tests() {
         },
     ];
 
-    for test in &tests {
-        if let Ok(mut board) = Board::from_fen(test.fen) {
-            let result = perft(&mut board, test.depth);
+    run_cases(&tests);
+}
+
+#[test]
+fn run_chess960_perft_tests() {
+    // test taken from https://www.chessprogramming.org/Chess960_Perft_Results;
+    // castling fields are Shredder-FEN, naming the castling rooks by file
+
+    let tests = [
+        PerftTestCase {
+            description: "Chess960 position 1, depth 5",
+            fen: "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            depth: 5,
+            expected_nodes: 8_146_062,
+        },
+        PerftTestCase {
+            description: "Chess960 position 2, depth 4",
+            fen: "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            depth: 4,
+            expected_nodes: 667_366,
+        },
+        PerftTestCase {
+            description: "Chess960 position 3, depth 5",
+            fen: "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+            depth: 5,
+            expected_nodes: 6_417_013,
+        },
+        PerftTestCase {
+            description: "Chess960 position 4, depth 4",
+            fen: "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+            depth: 4,
+            expected_nodes: 382_958,
+        },
+        PerftTestCase {
+            description: "Chess960 position 5, depth 4",
+            fen: "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+            depth: 4,
+            expected_nodes: 1_171_749,
+        },
+        PerftTestCase {
+            description: "Chess960 position 6, depth 4",
+            fen: "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+            depth: 4,
+            expected_nodes: 824_055,
+        },
+        PerftTestCase {
+            description: "Chess960 position 7, depth 5",
+            fen: "q1bnrkr1/ppppp2p/2n2p2/4b1p1/2NP4/8/PPP1PPPP/QNB1RRKB w ge - 1 9",
+            depth: 5,
+            expected_nodes: 21_093_346,
+        },
+    ];
+
+    run_cases(&tests);
+}
+
+fn run_cases(tests: &[PerftTestCase]) {
+    for test in tests {
+        let mut board = Board::from_fen(test.fen)
+            .unwrap_or_else(|err| panic!("Bad FEN in {}: {err}", test.description));
+        let result = perft(&mut board, test.depth);
+        assert_eq!(
+            result, test.expected_nodes,
+            "Failed test: {} (FEN: {})",
+            test.description, test.fen
+        );
+    }
+}
+
+#[test]
+fn category_generators_partition_the_legal_moves() {
+    // captures and quiets split the legal moves between them, evasions are
+    // every move in check, and quiet checks are the quiets that give check —
+    // at every node of a shallow tree below positions rich in promotions,
+    // en passant, pins, checks and castling
+    let cases = [
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            3,
+        ),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            3,
+        ),
+        (
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            3,
+        ),
+        (
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            2,
+        ),
+    ];
+    for (fen, depth) in cases {
+        let board = Board::from_fen(fen).unwrap();
+        check_categories(&board, depth);
+    }
+}
+
+fn check_categories(board: &Board, depth: u32) {
+    let mut board = board.clone();
+    let turn = board.turn;
+    let raw = |moves: &[Move]| {
+        let mut raw: Vec<u16> = moves.iter().map(Move::get_raw).collect();
+        raw.sort_unstable();
+        raw
+    };
+    let all = board.generate_moves(turn);
+    let captures = board.generate_captures(turn);
+    let quiets = board.generate_quiets(turn);
+    let fen = board.to_fen();
+
+    let mut union: Vec<Move> = captures.to_vec();
+    union.extend_from_slice(&quiets);
+    assert_eq!(raw(&union), raw(&all), "captures + quiets in {fen}");
+    assert!(
+        captures.iter().all(|mv| !quiets.contains(mv)),
+        "captures and quiets overlap in {fen}"
+    );
+    // each category keeps the order of the full list
+    let in_order: Vec<Move> = all
+        .iter()
+        .copied()
+        .filter(|mv| captures.contains(mv))
+        .collect();
+    assert_eq!(in_order, captures.to_vec(), "capture order in {fen}");
+    if board.in_check(turn) {
+        let evasions = board.generate_evasions(turn);
+        assert_eq!(raw(&evasions), raw(&all), "evasions in {fen}");
+    }
+    let checks: Vec<Move> = quiets
+        .iter()
+        .copied()
+        .filter(|&mv| {
+            let mut child = board.clone();
+            child.commit_verified_move(mv);
+            child.in_check(child.turn)
+        })
+        .collect();
+    assert_eq!(
+        raw(&board.generate_quiet_checks(turn)),
+        raw(&checks),
+        "quiet checks in {fen}"
+    );
+
+    if depth > 1 {
+        for mv in all {
+            let mut child = board.clone();
+            child.commit_verified_move(mv);
+            check_categories(&child, depth - 1);
+        }
+    }
+}
+
+#[test]
+fn single_move_legality_matches_generation() {
+    // every 16-bit move value, in the perft positions and the positions one
+    // move later: legal exactly when generated, and pseudo-legal but not legal
+    // only when it leaves the mover's king in check
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w AGbg - 0 1",
+    ];
+    for fen in fens {
+        let mut board = Board::from_fen(fen).unwrap();
+        check_single_moves(&mut board);
+        for mv in board.generate_moves(board.turn) {
+            let mut child = board.clone();
+            child.commit_verified_move(mv);
+            check_single_moves(&mut child);
+        }
+    }
+}
+
+fn check_single_moves(board: &mut Board) {
+    let legal = board.generate_moves(board.turn);
+    for raw in 0..=u16::MAX {
+        let mv = Move::make_raw(raw);
+        let generated = legal.contains(&mv);
+        assert_eq!(
+            board.is_legal(mv),
+            generated,
+            "{mv:?} in {}",
+            board.to_fen()
+        );
+        if board.is_pseudo_legal(mv) {
             assert_eq!(
-                result, test.expected_nodes,
-                "Failed test: {} (FEN: {})",
-                test.description, test.fen
+                board.would_check(mv),
+                !generated,
+                "{mv:?} in {}",
+                board.to_fen()
             );
+        } else {
+            assert!(!generated, "{mv:?} in {}", board.to_fen());
         }
     }
 }